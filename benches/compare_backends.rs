@@ -0,0 +1,130 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+// Counts allocations, so we can report them next to the timings.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn load_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+// There is no 10 MB file in the repo, so we are generating one.
+fn generate_large() -> String {
+    let mut text = String::from("<?xml version=\"1.0\"?>\n<root>\n");
+    let mut i = 0;
+    while text.len() < 10 * 1024 * 1024 {
+        text.push_str(&format!(
+            "  <item id=\"{}\"><name>Item &amp; {}</name><value>{}</value></item>\n", i, i, i * 2));
+        i += 1;
+    }
+    text.push_str("</root>\n");
+    text
+}
+
+fn quick_xml_depth(text: &str) -> usize {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut buf = Vec::new();
+    let mut ns_buf = Vec::new();
+    let mut depth = 0;
+    let mut max_depth = 0;
+    loop {
+        match reader.read_namespaced_event(&mut buf, &mut ns_buf).unwrap() {
+            (_, Event::Start(_)) => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            (_, Event::End(_)) => depth -= 1,
+            (_, Event::Eof) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    max_depth
+}
+
+fn xml_rs_depth(text: &str) -> usize {
+    use xml::reader::XmlEvent;
+
+    let mut depth = 0;
+    let mut max_depth = 0;
+    for event in xml::EventReader::new(text.as_bytes()) {
+        match event.unwrap() {
+            XmlEvent::StartElement { .. } => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            XmlEvent::EndElement { .. } => depth -= 1,
+            _ => {}
+        }
+    }
+
+    max_depth
+}
+
+fn roxmltree_depth(text: &str) -> usize {
+    let doc = roxmltree::Document::parse(text).unwrap();
+    doc.descendants()
+        .filter(|n| n.is_element())
+        .map(|n| n.ancestors().filter(|a| a.is_element()).count())
+        .max()
+        .unwrap_or(0)
+}
+
+// Parses the text and returns the maximum depth of elements.
+type DepthFn = fn(&str) -> usize;
+
+fn count_allocations(f: impl Fn(&str) -> usize, text: &str) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f(text);
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn compare_backends(c: &mut Criterion) {
+    let files = vec![
+        ("small", load_file("data/complex.svg")),
+        ("medium", load_file("data/medium.xml")),
+        ("large", generate_large()),
+    ];
+
+    let backends: Vec<(&str, DepthFn)> = vec![
+        ("quick_xml", quick_xml_depth),
+        ("xml-rs", xml_rs_depth),
+        ("roxmltree", roxmltree_depth),
+    ];
+
+    let mut group = c.benchmark_group("depth");
+    for (size, text) in &files {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+
+        for (name, f) in &backends {
+            println!("{}/{}: {} allocations per parse", name, size, count_allocations(f, text));
+            group.bench_with_input(BenchmarkId::new(*name, size), text, |b, text| b.iter(|| f(text)));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compare_backends);
+criterion_main!(benches);
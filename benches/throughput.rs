@@ -0,0 +1,145 @@
+use std::fs;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use choose_your_xml::event::Event;
+use choose_your_xml::{collect_events, DumpEvent, QuickXmlReader, XmlRsReader, XmlSource};
+
+fn load_file(path: &str) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+fn generate_nested() -> String {
+    let mut text = String::from("<?xml version=\"1.0\"?>\n");
+    for i in 0..1000 {
+        text.push_str(&format!("<level n=\"{}\">", i));
+    }
+    text.push_str("deepest");
+    for _ in 0..1000 {
+        text.push_str("</level>");
+    }
+    text
+}
+
+fn generate_wide() -> String {
+    let mut text = String::from("<?xml version=\"1.0\"?>\n<root xmlns:x=\"http://example.com/x\">\n");
+    for i in 0..50_000 {
+        text.push_str(&format!("  <x:item id=\"{}\" x:kind=\"a &amp; b\"/>\n", i));
+    }
+    text.push_str("</root>\n");
+    text
+}
+
+// There is no 10 MB file in the repo, so we are generating one.
+fn generate_large() -> String {
+    let mut text = String::from("<?xml version=\"1.0\"?>\n<root>\n");
+    let mut i = 0;
+    while text.len() < 10 * 1024 * 1024 {
+        text.push_str(&format!(
+            "  <item id=\"{}\"><name>Item &amp; {}</name><value>{}</value></item>\n", i, i, i * 2));
+        i += 1;
+    }
+    text.push_str("</root>\n");
+    text
+}
+
+// Streaming backends go through `XmlSource`, so they resolve namespaces
+// and decode attribute values just like in the examples.
+fn quick_xml_source(text: &str) -> QuickXmlReader<&[u8]> {
+    QuickXmlReader::new(quick_xml::Reader::from_reader(text.as_bytes()))
+}
+
+fn xml_rs_source(text: &str) -> XmlRsReader<&[u8]> {
+    XmlRsReader::new(text.as_bytes())
+}
+
+// Parses the document and discards events, so only the parsing cost is measured.
+fn count_events(source: &mut impl XmlSource) -> usize {
+    let mut count = 0;
+    loop {
+        if let Event::Eof = source.next_event().unwrap() {
+            return count;
+        }
+        count += 1;
+    }
+}
+
+fn quick_xml_parse(text: &str) -> usize {
+    count_events(&mut quick_xml_source(text))
+}
+
+fn quick_xml_owned(text: &str) -> usize {
+    collect_events(&mut quick_xml_source(text)).unwrap().len()
+}
+
+fn xml_rs_parse(text: &str) -> usize {
+    count_events(&mut xml_rs_source(text))
+}
+
+fn xml_rs_owned(text: &str) -> usize {
+    collect_events(&mut xml_rs_source(text)).unwrap().len()
+}
+
+// roxmltree is a DOM, so the tree is walked instead and attribute values are decoded while parsing.
+fn roxmltree_parse(text: &str) -> usize {
+    let doc = roxmltree::Document::parse(text).unwrap();
+    doc.descendants().map(|n| if n.is_element() { 2 } else { 1 }).sum::<usize>() - 1
+}
+
+fn roxmltree_owned(text: &str) -> usize {
+    let doc = roxmltree::Document::parse(text).unwrap();
+    let mut events = Vec::new();
+    walk_roxmltree(doc.root(), &mut events);
+    events.len()
+}
+
+fn walk_roxmltree(parent: roxmltree::Node, events: &mut Vec<DumpEvent>) {
+    for node in parent.children() {
+        if node.is_element() {
+            let name = node.tag_name().name().to_string();
+            let ns = node.tag_name().namespace().map(str::to_string);
+            let attrs = node.attributes().iter().map(|a| (a.name().to_string(), a.value().to_string())).collect();
+
+            events.push(DumpEvent::StartElement { name: name.clone(), ns: ns.clone(), prefix: None, attrs });
+            walk_roxmltree(node, events);
+            events.push(DumpEvent::EndElement { name, ns, prefix: None });
+        } else if let Some(text) = node.text() {
+            events.push(DumpEvent::Text(text.to_string()));
+        }
+    }
+}
+
+// Names of backends and functions that parse a document and return the number of events.
+type Backends = Vec<(&'static str, fn(&str) -> usize)>;
+
+fn throughput(c: &mut Criterion) {
+    let files = vec![
+        ("config", load_file("data/small.xml")),
+        ("nested", generate_nested()),
+        ("wide", generate_wide()),
+        ("large", generate_large()),
+    ];
+
+    // "parse" discards events, while "owned" copies them into `DumpEvent`s,
+    // so the difference between them is the cost of allocating owned strings.
+    let modes: Vec<(&str, Backends)> = vec![
+        ("parse", vec![("quick_xml", quick_xml_parse), ("xml-rs", xml_rs_parse), ("roxmltree", roxmltree_parse)]),
+        ("owned", vec![("quick_xml", quick_xml_owned), ("xml-rs", xml_rs_owned), ("roxmltree", roxmltree_owned)]),
+    ];
+
+    for (mode, backends) in &modes {
+        let mut group = c.benchmark_group(*mode);
+        for (file, text) in &files {
+            group.throughput(Throughput::Bytes(text.len() as u64));
+
+            for (name, f) in backends {
+                println!("{}/{}/{}: {} events", mode, name, file, f(text));
+                group.bench_with_input(BenchmarkId::new(*name, file), text, |b, text| b.iter(|| f(text)));
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
@@ -106,7 +106,7 @@ fn xml5ever_large(bencher: &mut Bencher) {
 }
 
 fn sxd_document_parse(text: &str) {
-    let _ = sxd_document::parser::parse(&text).unwrap();
+    let _ = sxd_document::parser::parse(text).unwrap();
 }
 
 fn sxd_document_small(bencher: &mut Bencher) {
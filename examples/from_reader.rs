@@ -0,0 +1,125 @@
+use std::env;
+
+use serde::Deserialize;
+
+use choose_your_xml::{from_reader, open_input, Result};
+
+// The same schema as in the `quick_xml_serde` example, so both can be compared.
+// It matches `data/small.xml`.
+//
+// Attributes are matched by `@` + a name and text by `$text`.
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(default)]
+    libraries: Libraries,
+    #[serde(rename = "module", default)]
+    modules: Vec<Module>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Libraries {
+    #[serde(rename = "library", default)]
+    items: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Library {
+    #[serde(rename = "@groupId")]
+    group_id: String,
+    #[serde(rename = "@artifactId")]
+    artifact_id: String,
+    #[serde(rename = "@version")]
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Module {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(default)]
+    files: Files,
+    #[serde(default)]
+    libraries: Libraries,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Files {
+    #[serde(rename = "file", default)]
+    items: Vec<SourceFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceFile {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@type")]
+    kind: String,
+    #[serde(rename = "$text")]
+    content: String,
+}
+
+// A schema that doesn't match the document, to show how errors look like.
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Mismatch {
+    version: String,
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tfrom_reader input.xml|-");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{}", e.in_file(&args[1]));
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let project: Project = from_reader(open_input(path)?)?;
+
+    println!("Project: {}", project.name);
+    print_libraries(&project.libraries, 1);
+
+    for module in &project.modules {
+        indent(1);
+        println!("Module: {}", module.name);
+
+        for file in &module.files.items {
+            indent(2);
+            println!("File: {} ({})", file.name, file.kind);
+            indent(3);
+            println!("Content: {:?}", file.content);
+        }
+
+        print_libraries(&module.libraries, 2);
+    }
+
+    if path != "-" {
+        let res: Result<Mismatch> = from_reader(open_input(path)?);
+        if let Err(e) = res {
+            println!("Mismatched schema: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_libraries(libraries: &Libraries, depth: usize) {
+    for lib in &libraries.items {
+        indent(depth);
+        println!("Library: {}:{}:{}", lib.group_id, lib.artifact_id, lib.version);
+    }
+}
+
+fn indent(depth: usize) {
+    for _ in 0..depth {
+        print!("  ");
+    }
+}
@@ -0,0 +1,134 @@
+use std::env;
+use std::io;
+
+use libxml::parser::{Parser, XmlParseError};
+use libxml::tree::{Document, Node, NodeType};
+
+use choose_your_xml::{EventVisitor, PrintVisitor};
+
+// libxml2 is a C library, so this example is built only with the `libxml` feature:
+// cargo run --example libxml --features libxml -- input.xml
+
+#[derive(Debug)]
+enum Error {
+    LibXmlError(XmlParseError),
+    PrintError(choose_your_xml::Error),
+}
+
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(XmlParseError, LibXmlError);
+from_error!(choose_your_xml::Error, PrintError);
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tlibxml input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let doc = Parser::default().parse_file(path)?;
+
+    if let Some(root) = doc.get_root_element() {
+        walk(&root, &mut PrintVisitor::new(io::stdout()), 0)?;
+    }
+
+    match validate_dtd(&doc) {
+        Some(true) => println!("DTD: valid"),
+        Some(false) => println!("DTD: invalid"),
+        None => println!("DTD: not present"),
+    }
+
+    Ok(())
+}
+
+fn walk(node: &Node, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    match node.get_type() {
+        Some(NodeType::ElementNode) => {
+            let ns = node.get_namespace().map(|ns| ns.get_href());
+            let name = node.get_name();
+            let prefix = node.get_namespace().map(|ns| ns.get_prefix()).filter(|p| !p.is_empty());
+            let attrs = collect_attributes(node);
+            let children = node.get_child_nodes();
+
+            if children.is_empty() {
+                visitor.empty(ns.as_deref(), prefix.as_deref(), &name, &attrs, depth)?;
+            } else {
+                visitor.start(ns.as_deref(), prefix.as_deref(), &name, &attrs, depth)?;
+                for child in &children {
+                    walk(child, visitor, depth + 1)?;
+                }
+                visitor.end(ns.as_deref(), prefix.as_deref(), &name, depth)?;
+            }
+        }
+        Some(NodeType::TextNode) => visitor.text(&node.get_content(), depth)?,
+        Some(NodeType::CDataSectionNode) => visitor.cdata(&node.get_content(), depth)?,
+        Some(NodeType::CommentNode) => visitor.comment(&node.get_content(), depth)?,
+        Some(NodeType::PiNode) => {
+            let text = format!("{} {}", node.get_name(), node.get_content());
+            visitor.pi(text.trim_end(), depth)?;
+        }
+        // Entity references are already substituted by the parser.
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn collect_attributes(node: &Node) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+
+    for ns in node.get_namespace_declarations() {
+        let prefix = ns.get_prefix();
+        if prefix.is_empty() {
+            attrs.push(("xmlns".to_string(), ns.get_href()));
+        } else {
+            attrs.push((format!("xmlns:{}", prefix), ns.get_href()));
+        }
+    }
+
+    // Attributes are stored in a HashMap, so we have to sort them to get a stable output.
+    // Keys are local names, since libxml doesn't expose attribute prefixes here.
+    let mut props: Vec<_> = node.get_properties().into_iter().collect();
+    props.sort();
+    attrs.extend(props);
+
+    attrs
+}
+
+// The safe API doesn't expose validation, so we have to call libxml2 directly.
+// Validation errors are printed by libxml2 itself to stderr.
+//
+// Returns `None` when the document doesn't have an internal DTD.
+fn validate_dtd(doc: &Document) -> Option<bool> {
+    use libxml::bindings::{xmlFreeValidCtxt, xmlGetIntSubset, xmlNewValidCtxt, xmlValidateDocument};
+
+    unsafe {
+        if xmlGetIntSubset(doc.doc_ptr()).is_null() {
+            return None;
+        }
+
+        let ctxt = xmlNewValidCtxt();
+        let is_valid = xmlValidateDocument(ctxt, doc.doc_ptr()) == 1;
+        xmlFreeValidCtxt(ctxt);
+
+        Some(is_valid)
+    }
+}
@@ -0,0 +1,58 @@
+use std::env;
+use std::fs;
+use std::io;
+
+use minidom::{Element, Node};
+
+use choose_your_xml::{Error, EventVisitor, PrintVisitor, Result};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tminidom input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let root: Element = text.parse().map_err(Error::backend)?;
+
+    walk(&root, &mut PrintVisitor::new(io::stdout()), 0)
+}
+
+// minidom always keeps an element name as a (namespace, local name) pair,
+// so there is nothing to resolve here.
+fn walk(element: &Element, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    let ns = element.ns();
+    let ns = ns.as_deref();
+
+    // `xml:lang`, `xml:base` and other prefixed attributes are kept as written.
+    let attrs: Vec<_> = element.attrs()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    if element.nodes().next().is_none() {
+        visitor.empty(ns, element.prefix(), element.name(), &attrs, depth)?;
+        return Ok(());
+    }
+
+    visitor.start(ns, element.prefix(), element.name(), &attrs, depth)?;
+
+    for node in element.nodes() {
+        match node {
+            Node::Element(e) => walk(e, visitor, depth + 1)?,
+            Node::Text(text) => visitor.text(text, depth + 1)?,
+            _ => {}
+        }
+    }
+
+    visitor.end(ns, element.prefix(), element.name(), depth)?;
+
+    Ok(())
+}
@@ -1,152 +1,292 @@
 use std::env;
-use std::io::BufReader;
-use std::fs::File;
-use std::str::{self, Utf8Error};
+use std::io::{self, BufRead, IsTerminal};
+use std::process;
 
 use quick_xml::Reader;
-use quick_xml::events::{Event, BytesStart, BytesText};
-use quick_xml::Error as XmlError;
 
-type XmlReader = Reader<BufReader<File>>;
+use choose_your_xml::{collect_statistics, dump_events, expand_glob, open_input, parse_dom, parse_dom_fragment,
+                      walk_events, walk_events_recovering, write_canonical, write_events, write_tree, DecodingReader,
+                      DumpFormat, DumpOptions, Element, Minifier, PathSelector, PrettyPrinter, PrintVisitor, Result,
+                      Selected, TextVisitor};
 
-#[derive(Debug)]
-enum Error {
-    XmlError(XmlError),
-    Utf8Error(Utf8Error),
-}
+// Stop recovering after this many errors, so a completely broken file still terminates.
+const MAX_ERRORS: usize = 100;
 
-macro_rules! from_error {
-    ($err_type:ty, $err_name:ident) => (
-        impl From<$err_type> for Error {
-            fn from(value: $err_type) -> Error {
-                Error::$err_name(value)
-            }
-        }
-    )
-}
+const USAGE: &str = "Usage:\n\tquick_xml [--format text|json|jsonl|ndjson|tree|xml|c14n] [--indent N|STR|tab] [--path] \
+                     [--max-depth N] [--only NAME]... [--ns URI] [--base URI] [--entity NAME=VALUE]... [--select PATH] \
+                     [--bind PREFIX=URI]... [--text-only] [--attr NAME] [--stats] [--text] [--join SEP] [--pretty] \
+                     [--add-decl] [--minify] [--strip-comments] [--strip-pi] [--recover] [--fail-fast|--keep-going] \
+                     [--strict-encoding] [--fragment] [input.xml|GLOB|-]...";
 
-from_error!(XmlError, XmlError);
-from_error!(Utf8Error, Utf8Error);
+fn main() {
+    let mut args = env::args().skip(1);
 
-type Result<T> = std::result::Result<T, Error>;
+    let mut format = "text".to_string();
+    let mut opts = DumpOptions::default();
+    let mut fail_fast = false;
+    let mut strict_encoding = false;
+    let mut add_declaration = false;
+    let mut strip_comments = false;
+    let mut strip_pis = false;
+    let mut stats = false;
+    let mut join = String::new();
+    let mut select_path = None;
+    let mut bindings = Vec::new();
+    let mut paths = Vec::new();
 
-fn main() {
-    let args: Vec<_> = env::args().collect();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pretty" => format = "xml".to_string(),
+            "--add-decl" => add_declaration = true,
+            "--minify" => format = "minify".to_string(),
+            "--strip-comments" => strip_comments = true,
+            "--strip-pi" => strip_pis = true,
+            "--recover" => format = "recover".to_string(),
+            "--text" => format = "plain".to_string(),
+            "--text-only" => format = "values".to_string(),
+            "--fail-fast" => fail_fast = true,
+            "--keep-going" => fail_fast = false,
+            "--strict-encoding" => strict_encoding = true,
+            "--stats" => stats = true,
+            "--path" => opts.paths = true,
+            "--fragment" => opts.fragment = true,
+            "--format" | "--indent" | "--max-depth" | "--only" | "--ns" | "--base" | "--entity" | "--join" | "--select"
+            | "--bind" | "--attr" => {
+                let value = match args.next() {
+                    Some(v) => v,
+                    None => {
+                        println!("{}", USAGE);
+                        return;
+                    }
+                };
 
-    if args.len() != 2 {
-        println!("Usage:\n\tquick_xml input.xml");
-        return;
+                match arg.as_str() {
+                    "--format" => format = value,
+                    "--indent" => opts.indent = Some(match value.parse() {
+                        Ok(width) => " ".repeat(width),
+                        Err(_) if value == "tab" => "\t".to_string(),
+                        Err(_) => value,
+                    }),
+                    "--max-depth" => match value.parse() {
+                        Ok(v) => opts.max_depth = Some(v),
+                        Err(_) => {
+                            println!("Invalid depth: {}.", value);
+                            return;
+                        }
+                    },
+                    "--only" => opts.only.push(value),
+                    "--select" => select_path = Some(value),
+                    "--attr" => {
+                        format = "values".to_string();
+                        opts.attribute = Some(value);
+                    }
+                    "--base" => opts.base_uri = Some(value),
+                    "--bind" => match value.split_once('=') {
+                        Some((prefix, uri)) => bindings.push((prefix.to_string(), uri.to_string())),
+                        None => {
+                            println!("Invalid binding: {}.", value);
+                            return;
+                        }
+                    },
+                    "--entity" => match value.split_once('=') {
+                        Some((name, value)) => {
+                            opts.entities.insert(name.as_bytes().to_vec(), value.as_bytes().to_vec());
+                        }
+                        None => {
+                            println!("Invalid entity: {}.", value);
+                            return;
+                        }
+                    },
+                    "--join" => join = value.replace("\\n", "\n").replace("\\t", "\t"),
+                    _ => opts.ns = Some(value),
+                }
+            }
+            _ => paths.push(arg),
+        }
     }
 
-    if let Err(e) = parse(&args[1]) {
-        println!("{:?}", e);
+    // Piped input doesn't require an explicit `-`.
+    if paths.is_empty() {
+        if io::stdin().is_terminal() {
+            println!("{}", USAGE);
+            return;
+        }
+
+        paths.push("-".to_string());
     }
-}
 
-fn parse(path: &str) -> Result<()> {
-    let file = File::open(path).unwrap();
-    let file = BufReader::new(file);
-    let mut reader = Reader::from_reader(file);
+    // Wildcards are expanded here too, since not every shell does it.
+    // A pattern without matches is kept, so it's reported as a missing file.
+    let paths: Vec<_> = paths.into_iter()
+        .flat_map(|path| match expand_glob(&path) {
+            Ok(matched) if !matched.is_empty() => matched,
+            _ => vec![path],
+        })
+        .collect();
 
-    let mut buf = Vec::new();
-    let mut ns_buf = Vec::new();
+    let formats = ["text", "json", "jsonl", "ndjson", "tree", "xml", "c14n", "plain", "values", "minify", "recover"];
+    if !formats.contains(&format.as_str()) {
+        println!("Unknown format: {}.", format);
+        return;
+    }
 
-    let mut depth = 0;
+    // Other modes expand only the entities declared in the DOCTYPE.
+    let is_dump = ["text", "json", "jsonl", "ndjson", "values"].contains(&format.as_str()) && !stats;
+    if !opts.entities.is_empty() && !is_dump {
+        println!("--entity is supported only by the text, json, jsonl, ndjson and values formats.");
+        return;
+    }
 
-    loop {
-        match reader.read_namespaced_event(&mut buf, &mut ns_buf)? {
-            (ns, Event::Start(ref e)) => {
-                print_tag_name("Start", ns, e.local_name(), depth)?;
-                print_attributes(&reader, &e, depth)?;
-                depth += 1;
-            }
-            (ns, Event::Empty(ref e)) => {
-                print_tag_name("Empty", ns, e.local_name(), depth)?;
-                print_attributes(&reader, &e, depth)?;
-            }
-            (ns, Event::End(ref e)) => {
-                depth -= 1;
-                print_tag_name("End", ns, e.local_name(), depth)?;
-            }
-            (_, Event::Comment(ref e)) => {
-                print_text(&reader, "Comment", e, depth)?;
-            }
-            (_, Event::CData(ref e)) => {
-                print_text(&reader, "CDATA", e, depth)?;
+    // The tree format selects from the DOM, while dumps are filtered while streaming.
+    if let Some(ref path) = select_path {
+        match PathSelector::parse(path) {
+            Ok(selector) => {
+                opts.select = Some(bindings.iter().fold(selector, |s, (prefix, uri)| s.bind(prefix, uri)));
             }
-            (_, Event::PI(ref e)) => {
-                print_text(&reader, "Processing Instruction", e, depth)?;
+            Err(e) => {
+                println!("{}", e);
+                return;
             }
-            (_, Event::DocType(ref e)) => {
-                print_text(&reader, "Document Type", e, depth)?;
-            }
-            (_, Event::Decl(ref e)) => {
-                indent(depth);
-                println!("Declaration");
+        }
+    }
 
-                if let Ok(v) = e.version() {
-                    indent(depth);
-                    println!("  version=\"{}\"", str::from_utf8(v.as_ref())?);
-                }
+    let mut errors = Vec::new();
+    for path in &paths {
+        if paths.len() > 1 {
+            println!("=== {} ===", path);
+        }
 
-                if let Some(Ok(v)) = e.encoding() {
-                    indent(depth);
-                    println!("  encoding=\"{}\"", str::from_utf8(v.as_ref())?);
-                }
+        let input = open_input(path).and_then(|input| check_encoding(input, path, strict_encoding));
+        let res = input.and_then(|input| match format.as_str() {
+            "tree" => match opts.select {
+                Some(ref selector) => print_selected(input, selector, opts.fragment),
+                None => print_tree(input, opts.fragment),
+            },
+            "text" | "json" if stats => print_statistics(input, format == "json"),
+            "text" => parse(input, DumpFormat::Text, &opts),
+            "json" => parse(input, DumpFormat::Json, &opts),
+            "jsonl" => parse(input, DumpFormat::JsonLines, &opts),
+            "ndjson" => parse(input, DumpFormat::Ndjson, &opts),
+            "values" => parse(input, DumpFormat::Values, &opts),
+            "xml" => reformat(input, opts.indent.as_deref().unwrap_or("  "), add_declaration),
+            "c14n" => canonicalize(input),
+            "plain" => extract_text(input, &join),
+            "minify" => minify(input, strip_comments, strip_pis),
+            _ => recover(input),
+        });
 
-                if let Some(Ok(v)) = e.standalone() {
-                    indent(depth);
-                    println!("  standalone=\"{}\"", str::from_utf8(v.as_ref())?);
-                }
-            }
-            (_, Event::Text(ref e)) => {
-                print_text(&reader, "  Text", e, depth)?;
+        if let Err(e) = res {
+            let e = e.in_file(path);
+            println!("{}", e);
+            errors.push(e);
+
+            if fail_fast {
+                break;
             }
-            (_, Event::Eof) => break,
         }
-        buf.clear();
     }
 
+    if !errors.is_empty() {
+        if paths.len() > 1 {
+            println!("{} of {} files failed.", errors.len(), paths.len());
+        }
+        process::exit(1);
+    }
+}
+
+// A mismatch is only a warning by default, since the BOM still tells the actual encoding.
+fn check_encoding<R: BufRead>(input: DecodingReader<R>, path: &str, strict: bool) -> Result<DecodingReader<R>> {
+    if let Err(e) = input.check_declaration() {
+        if strict {
+            return Err(e);
+        }
+
+        eprintln!("Warning: {}", e.in_file(path));
+    }
+
+    Ok(input)
+}
+
+// All modes accept any `BufRead`, so `xml.as_bytes()` can be passed instead of a file.
+fn parse<R: BufRead>(reader: R, format: DumpFormat, opts: &DumpOptions) -> Result<()> {
+    let opts = DumpOptions { format, ..opts.clone() };
+    dump_events(reader, &mut io::stdout(), &opts)
+}
+
+fn print_statistics<R: BufRead>(reader: R, json: bool) -> Result<()> {
+    let stats = collect_statistics(reader)?;
+    if json {
+        stats.write_json(io::stdout())
+    } else {
+        print!("{}", stats);
+        Ok(())
+    }
+}
+
+fn extract_text<R: BufRead>(input: R, separator: &str) -> Result<()> {
+    let mut reader = Reader::from_reader(input);
+    let mut visitor = TextVisitor::new(io::stdout()).separator(separator);
+    walk_events(&mut reader, &mut visitor)?;
+    println!();
     Ok(())
 }
 
-fn print_tag_name(title: &str, ns: Option<&[u8]>, tag_name: &[u8], depth: usize) -> Result<()> {
-    indent(depth);
-
-    match ns {
-        Some(ns) => println!("{}: {} (ns: {})",
-            title,
-            str::from_utf8(tag_name)?,
-            str::from_utf8(ns)?),
-        None => println!("{}: {}",
-            title,
-            str::from_utf8(tag_name)?),
+// Without `--fragment` only the first root is kept, like before.
+fn parse_roots<R: BufRead>(input: R, fragment: bool) -> Result<Vec<Element>> {
+    let mut reader = Reader::from_reader(input);
+    if fragment {
+        parse_dom_fragment(&mut reader)
+    } else {
+        Ok(vec![parse_dom(&mut reader)?])
+    }
+}
+
+fn print_tree<R: BufRead>(input: R, fragment: bool) -> Result<()> {
+    for root in parse_roots(input, fragment)? {
+        write_tree(&root, io::stdout())?;
     }
 
     Ok(())
 }
 
-fn print_attributes(r: &XmlReader, e: &BytesStart, depth: usize) -> Result<()> {
-    for a in e.attributes() {
-        let a = a?;
-        indent(depth + 1);
-        println!("  Attribute: {}=\"{}\"",
-            str::from_utf8(a.key)?,
-            a.unescape_and_decode_value(r)?);
+fn print_selected<R: BufRead>(input: R, selector: &PathSelector, fragment: bool) -> Result<()> {
+    for root in parse_roots(input, fragment)? {
+        for item in selector.select(&root) {
+            match item {
+                Selected::Element(e) => write_tree(e, io::stdout())?,
+                Selected::Attribute(value) => println!("{}", value),
+            }
+        }
     }
 
     Ok(())
 }
 
-fn print_text(r: &XmlReader, title: &str, e: &BytesText, depth: usize) -> Result<()> {
-    indent(depth);
-    println!("{}: {:?}", title, e.unescape_and_decode(r)?);
+fn reformat<R: BufRead>(input: R, indent: &str, add_declaration: bool) -> Result<()> {
+    let mut reader = Reader::from_reader(input);
+    let mut printer = PrettyPrinter::new(io::stdout()).indent(indent).add_declaration(add_declaration);
+    write_events(&mut reader, &mut printer)
+}
 
+fn canonicalize<R: BufRead>(input: R) -> Result<()> {
+    let mut reader = Reader::from_reader(input);
+    write_canonical(&mut reader, io::stdout(), false)?;
     Ok(())
 }
 
-fn indent(depth: usize) {
-    for _ in 0..depth {
-        print!("  ");
+fn minify<R: BufRead>(input: R, strip_comments: bool, strip_pis: bool) -> Result<()> {
+    let mut reader = Reader::from_reader(input);
+    let mut minifier = Minifier::new(io::stdout()).strip_comments(strip_comments).strip_pis(strip_pis);
+    write_events(&mut reader, &mut minifier)
+}
+
+fn recover<R: BufRead>(input: R) -> Result<()> {
+    let mut reader = Reader::from_reader(input);
+    let errors = walk_events_recovering(&mut reader, &mut PrintVisitor::new(io::stdout()), MAX_ERRORS)?;
+
+    for e in &errors {
+        println!("Recovered: {}", e);
     }
+
+    Ok(())
 }
@@ -0,0 +1,120 @@
+use std::env;
+use std::process;
+
+use choose_your_xml::{diff_events_with_options, open_input, quick_xml_events, DiffItem, DiffOptions, DumpEvent};
+
+const USAGE: &str = "Usage:\n\tquick_xml_diff [--whitespace] [--attr-order] [--ignore-namespaces] old.xml new.xml\n\n\
+                     Exits with 0 when the documents are equal, 1 when they differ and 2 on errors.";
+
+fn main() {
+    // Whitespace and attribute order are insignificant unless requested.
+    let mut opts = DiffOptions {
+        ignore_whitespace: true,
+        ignore_attribute_order: true,
+        ..DiffOptions::default()
+    };
+    let mut paths = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--whitespace" => opts.ignore_whitespace = false,
+            "--attr-order" => opts.ignore_attribute_order = false,
+            "--ignore-namespaces" => opts.ignore_namespaces = true,
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        println!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut events = Vec::new();
+    for path in &paths {
+        match open_input(path).and_then(quick_xml_events) {
+            Ok(e) => events.push(e),
+            Err(e) => {
+                println!("{}", e.in_file(path));
+                process::exit(2);
+            }
+        }
+    }
+
+    let items = diff_events_with_options(&events[0], &events[1], &opts);
+    print_report(&items);
+
+    if !items.is_empty() {
+        process::exit(1);
+    }
+}
+
+// Inserted and deleted elements are reported once, without their content.
+fn print_report(items: &[DiffItem]) {
+    // A location of the inserted or deleted element whose content is being skipped.
+    let mut skipped: Option<&str> = None;
+
+    for item in items {
+        let (location, event) = match *item {
+            DiffItem::Inserted { ref location, ref event } | DiffItem::Deleted { ref location, ref event } => {
+                (location.as_str(), event)
+            }
+            DiffItem::Changed { ref location, ref old, ref new } => {
+                skipped = None;
+                print_change(location, old, new);
+                continue;
+            }
+        };
+
+        if let Some(parent) = skipped {
+            if location == parent || location.starts_with(&format!("{}/", parent)) {
+                if let DumpEvent::EndElement { .. } = *event {
+                    if location == parent {
+                        skipped = None;
+                    }
+                }
+                continue;
+            }
+        }
+
+        if let DumpEvent::StartElement { .. } = *event {
+            skipped = Some(location);
+        }
+
+        println!("{}", item);
+    }
+}
+
+fn print_change(location: &str, old: &DumpEvent, new: &DumpEvent) {
+    let (old_attrs, new_attrs) = match (old, new) {
+        (DumpEvent::StartElement { attrs: a, .. }, DumpEvent::StartElement { attrs: b, .. })
+        | (DumpEvent::EmptyElement { attrs: a, .. }, DumpEvent::EmptyElement { attrs: b, .. }) => (a, b),
+        _ => {
+            println!("~ {}\n{}\n{}", location, old, new);
+            return;
+        }
+    };
+
+    let find = |attrs: &[(String, String)], key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+
+    let mut reported = false;
+    for (key, value) in old_attrs {
+        match find(new_attrs, key) {
+            Some(ref new_value) if new_value == value => continue,
+            Some(new_value) => println!("~ {}/@{}: {:?} -> {:?}", location, key, value, new_value),
+            None => println!("- {}/@{}: {:?}", location, key, value),
+        }
+        reported = true;
+    }
+
+    for (key, value) in new_attrs {
+        if find(old_attrs, key).is_none() {
+            println!("+ {}/@{}: {:?}", location, key, value);
+            reported = true;
+        }
+    }
+
+    // Otherwise only the order or namespaces differ.
+    if !reported {
+        println!("~ {}\n{}\n{}", location, old, new);
+    }
+}
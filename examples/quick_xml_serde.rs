@@ -0,0 +1,149 @@
+use std::env;
+use std::io::{self, BufReader};
+use std::fs::File;
+
+use serde::Deserialize;
+
+// The same schema as in the `serde_xml_rs` example, so both can be compared.
+// It matches `data/small.xml`.
+//
+// Unlike serde-xml-rs, quick_xml doesn't distinguish attributes and child elements
+// in this version: both are matched by a field name. Text is captured by `$value`.
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    // An attribute.
+    name: String,
+    #[serde(default)]
+    libraries: Libraries,
+    #[serde(rename = "module", default)]
+    modules: Vec<Module>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Libraries {
+    #[serde(rename = "library", default)]
+    items: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Library {
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Module {
+    name: String,
+    #[serde(default)]
+    files: Files,
+    #[serde(default)]
+    libraries: Libraries,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Files {
+    #[serde(rename = "file", default)]
+    items: Vec<SourceFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceFile {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    // Text.
+    #[serde(rename = "$value")]
+    content: String,
+}
+
+// A schema that doesn't match the document, to show how errors look like.
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct Mismatch {
+    version: String,
+}
+
+// The fields are only read by the `Debug` output in `main`.
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Error {
+    IoError(io::Error),
+    DeError(quick_xml::DeError),
+}
+
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(io::Error, IoError);
+from_error!(quick_xml::DeError, DeError);
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tquick_xml_serde input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let project: Project = quick_xml::de::from_reader(open(path)?)?;
+
+    println!("Project: {}", project.name);
+    print_libraries(&project.libraries, 1);
+
+    for module in &project.modules {
+        indent(1);
+        println!("Module: {}", module.name);
+
+        for file in &module.files.items {
+            indent(2);
+            println!("File: {} ({})", file.name, file.kind);
+            indent(3);
+            println!("Content: {:?}", file.content);
+        }
+
+        print_libraries(&module.libraries, 2);
+    }
+
+    let res: std::result::Result<Mismatch, _> = quick_xml::de::from_reader(open(path)?);
+    if let Err(e) = res {
+        println!("Mismatched schema: {:?}", e);
+    }
+
+    Ok(())
+}
+
+fn open(path: &str) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+fn print_libraries(libraries: &Libraries, depth: usize) {
+    for lib in &libraries.items {
+        indent(depth);
+        println!("Library: {}:{}:{}", lib.group_id, lib.artifact_id, lib.version);
+    }
+}
+
+fn indent(depth: usize) {
+    for _ in 0..depth {
+        print!("  ");
+    }
+}
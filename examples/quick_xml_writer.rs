@@ -0,0 +1,91 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use quick_xml::Reader;
+
+use choose_your_xml::{open_input, quick_xml_events, write_round_trip, Error, Result};
+
+const USAGE: &str = "Usage:\n\tquick_xml_writer [--output PATH] [--verify] input.xml|-";
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let mut output = None;
+    let mut verify = false;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => match args.next() {
+                Some(path) => output = Some(path),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            "--verify" => verify = true,
+            _ if input.is_none() => input = Some(arg),
+            _ => {
+                println!("{}", USAGE);
+                return;
+            }
+        }
+    }
+
+    let input = match input {
+        Some(input) => input,
+        None => {
+            println!("{}", USAGE);
+            return;
+        }
+    };
+
+    match round_trip(&input, output.as_deref(), verify) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            println!("{}", e.in_file(&input));
+            process::exit(1);
+        }
+    }
+}
+
+// Returns `false` when the verification fails.
+fn round_trip(path: &str, output: Option<&str>, verify: bool) -> Result<bool> {
+    // The input is kept in memory, so it can be compared with the output.
+    let mut xml = Vec::new();
+    open_input(path)?.read_to_end(&mut xml)?;
+
+    let written = write_round_trip(&mut Reader::from_reader(xml.as_slice()), Vec::new())?;
+
+    match output {
+        Some(output) => {
+            fs::write(output, &written).map_err(|error| Error::Open { path: output.to_string(), error })?
+        }
+        None => io::stdout().write_all(&written)?,
+    }
+
+    if !verify {
+        return Ok(true);
+    }
+
+    // Re-parsed events are compared instead of bytes, since formatting inside of tags may change.
+    let original = quick_xml_events(xml.as_slice())?;
+    let copy = quick_xml_events(written.as_slice())?;
+
+    // A full diff is quadratic, so only the first difference is reported.
+    match original.iter().zip(&copy).position(|(a, b)| a != b) {
+        Some(idx) => eprintln!("Event {} differs:\n{}\n{}", idx, original[idx], copy[idx]),
+        None if original.len() != copy.len() => {
+            eprintln!("The copy has {} events instead of {}.", copy.len(), original.len());
+        }
+        None => {
+            eprintln!("Round trip preserved all {} events.", original.len());
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
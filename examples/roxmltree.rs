@@ -0,0 +1,126 @@
+use std::env;
+use std::fs;
+use std::io;
+
+use choose_your_xml::{EventVisitor, PrintVisitor, Result};
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\troxmltree input.xml");
+        return;
+    }
+
+    let text = match fs::read_to_string(&args[1]) {
+        Ok(text) => text,
+        Err(e) => {
+            println!("cannot open {}: {}", args[1], e);
+            return;
+        }
+    };
+
+    let doc = match roxmltree::Document::parse(&text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            println!("Error: {}.", e);
+            return;
+        }
+    };
+
+    if let Err(e) = walk(doc.root(), &mut PrintVisitor::new(io::stdout()), 0) {
+        println!("{:?}", e);
+    }
+}
+
+// roxmltree doesn't have an End event, so we are synthesizing one
+// when the walk leaves an element.
+// An element without children is reported as Empty.
+fn walk(parent: roxmltree::Node, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    for node in parent.children() {
+        match node.node_type() {
+            roxmltree::NodeType::Element => {
+                // Namespaces are already resolved, including the inherited and default ones.
+                let ns = node.tag_name().namespace();
+                let name = node.tag_name().name();
+                // roxmltree doesn't keep prefixes of element names.
+                let prefix = None;
+                let attrs = collect_attributes(node);
+
+                if node.has_children() {
+                    visitor.start(ns, prefix, name, &attrs, depth)?;
+                    walk(node, visitor, depth + 1)?;
+                    visitor.end(ns, prefix, name, depth)?;
+                } else {
+                    visitor.empty(ns, prefix, name, &attrs, depth)?;
+                }
+            }
+            roxmltree::NodeType::Text => {
+                visitor.text(node.text().unwrap_or(""), depth)?;
+            }
+            roxmltree::NodeType::Comment => {
+                visitor.comment(node.text().unwrap_or(""), depth)?;
+            }
+            roxmltree::NodeType::PI => {
+                if let Some(pi) = node.pi() {
+                    let text = match pi.value {
+                        Some(value) => format!("{} {}", pi.target, value),
+                        None => pi.target.to_string(),
+                    };
+                    visitor.pi(&text, depth)?;
+                }
+            }
+            roxmltree::NodeType::Root => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_attributes(node: roxmltree::Node) -> Vec<(String, String)> {
+    let mut attrs = namespace_declarations(node);
+
+    for a in node.attributes() {
+        // The `xml` prefix is bound implicitly and can't be redeclared.
+        let prefix = match a.namespace() {
+            Some(XML_NS) => Some("xml"),
+            Some(ns) => node.lookup_prefix(ns),
+            None => None,
+        };
+
+        let key = match prefix {
+            Some(prefix) => format!("{}:{}", prefix, a.name()),
+            None => a.name().to_string(),
+        };
+
+        attrs.push((key, a.value().to_string()));
+    }
+
+    attrs
+}
+
+// roxmltree stores in-scope namespaces instead of `xmlns` attributes,
+// so we have to restore declarations by comparing them with the parent ones.
+fn namespace_declarations(node: roxmltree::Node) -> Vec<(String, String)> {
+    let inherited = |name: Option<&str>, uri: &str| {
+        node.parent_element()
+            .map(|p| p.namespaces().iter().any(|ns| ns.name() == name && ns.uri() == uri))
+            .unwrap_or(false)
+    };
+
+    let mut attrs = Vec::new();
+    for ns in node.namespaces() {
+        if ns.uri() == XML_NS || inherited(ns.name(), ns.uri()) {
+            continue;
+        }
+
+        match ns.name() {
+            Some(name) => attrs.push((format!("xmlns:{}", name), ns.uri().to_string())),
+            None => attrs.push(("xmlns".to_string(), ns.uri().to_string())),
+        }
+    }
+
+    attrs
+}
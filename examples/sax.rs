@@ -0,0 +1,87 @@
+use std::env;
+
+use quick_xml::Reader;
+
+use choose_your_xml::{dispatch, open_input, AttributeMap, QuickXmlReader, Result, SaxHandler};
+
+/// Prints the same tree as the text dump of the quick_xml example.
+///
+/// SAX has no declaration and no Empty elements, and `AttributeMap` has no namespace declarations,
+/// so these are printed differently. Attributes are unordered, so they are sorted by local names.
+/// Text is printed with its `xml:lang`, if any.
+struct PrintHandler {
+    depth: usize,
+}
+
+impl SaxHandler for PrintHandler {
+    fn start_element(&mut self, ns: Option<&str>, local: &str, attrs: &AttributeMap) -> Result<()> {
+        print_tag_name("Start", ns, local, self.depth);
+
+        let mut attrs: Vec<_> = attrs.iter().collect();
+        attrs.sort();
+        for ((_, key), value) in attrs {
+            println!("{}  Attribute: {}=\"{}\"", indent(self.depth + 1), key, value);
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn end_element(&mut self, ns: Option<&str>, local: &str) -> Result<()> {
+        self.depth -= 1;
+        print_tag_name("End", ns, local, self.depth);
+        Ok(())
+    }
+
+    fn characters(&mut self, text: &str) -> Result<()> {
+        println!("{}  Text: {:?}", indent(self.depth), text);
+        Ok(())
+    }
+
+    fn characters_with_lang(&mut self, text: &str, lang: Option<&str>) -> Result<()> {
+        match lang {
+            Some(lang) => println!("{}  Text: {:?} (lang: {})", indent(self.depth), text, lang),
+            None => println!("{}  Text: {:?}", indent(self.depth), text),
+        }
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str) -> Result<()> {
+        println!("{}Comment: {:?}", indent(self.depth), text);
+        Ok(())
+    }
+
+    fn processing_instruction(&mut self, text: &str) -> Result<()> {
+        println!("{}Processing Instruction: {:?}", indent(self.depth), text);
+        Ok(())
+    }
+}
+
+fn print_tag_name(title: &str, ns: Option<&str>, local: &str, depth: usize) {
+    match ns {
+        Some(ns) => println!("{}{}: {} (ns: {})", indent(depth), title, local, ns),
+        None => println!("{}{}: {}", indent(depth), title, local),
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tsax input.xml|-");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{}", e.in_file(&args[1]));
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let mut reader = QuickXmlReader::new(Reader::from_reader(open_input(path)?));
+    dispatch(&mut reader, &mut PrintHandler { depth: 0 })
+}
@@ -0,0 +1,132 @@
+use std::env;
+use std::io::{self, BufReader};
+use std::fs::File;
+
+use serde::Deserialize;
+
+// serde can't describe an arbitrary document (mixed content, repeated elements
+// in a random order, etc.), so we have to define a schema for the input.
+// This one matches `data/small.xml`.
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    name: String,
+    #[serde(default)]
+    libraries: Libraries,
+    #[serde(rename = "module", default)]
+    modules: Vec<Module>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Libraries {
+    #[serde(rename = "library", default)]
+    items: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Library {
+    #[serde(rename = "groupId")]
+    group_id: String,
+    #[serde(rename = "artifactId")]
+    artifact_id: String,
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Module {
+    name: String,
+    #[serde(default)]
+    files: Files,
+    #[serde(default)]
+    libraries: Libraries,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Files {
+    #[serde(rename = "file", default)]
+    items: Vec<SourceFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceFile {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(rename = "$value")]
+    content: String,
+}
+
+// The fields are only read by the `Debug` output in `main`.
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Error {
+    IoError(io::Error),
+    SerdeError(serde_xml_rs::Error),
+}
+
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(io::Error, IoError);
+from_error!(serde_xml_rs::Error, SerdeError);
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tserde_xml_rs input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let file = File::open(path)?;
+    let file = BufReader::new(file);
+
+    let project: Project = serde_xml_rs::from_reader(file)?;
+
+    println!("Project: {}", project.name);
+    print_libraries(&project.libraries, 1);
+
+    for module in &project.modules {
+        indent(1);
+        println!("Module: {}", module.name);
+
+        for file in &module.files.items {
+            indent(2);
+            println!("File: {} ({})", file.name, file.kind);
+            indent(3);
+            println!("Content: {:?}", file.content);
+        }
+
+        print_libraries(&module.libraries, 2);
+    }
+
+    Ok(())
+}
+
+fn print_libraries(libraries: &Libraries, depth: usize) {
+    for lib in &libraries.items {
+        indent(depth);
+        println!("Library: {}:{}:{}", lib.group_id, lib.artifact_id, lib.version);
+    }
+}
+
+fn indent(depth: usize) {
+    for _ in 0..depth {
+        print!("  ");
+    }
+}
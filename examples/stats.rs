@@ -0,0 +1,39 @@
+use std::env;
+
+use quick_xml::Reader;
+
+use choose_your_xml::event::{Event, XmlSource};
+use choose_your_xml::{open_input, DocumentStatistics, QuickXmlReader, Result};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tstats input.xml|-");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{}", e.in_file(&args[1]));
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let input = open_input(path)?;
+    let encoding = input.encoding();
+    let mut reader = QuickXmlReader::new(Reader::from_reader(input));
+    let mut stats = DocumentStatistics::new();
+
+    loop {
+        let e = reader.next_event()?;
+        if let Event::Eof = e {
+            break;
+        }
+
+        stats.update(&e);
+    }
+
+    println!("Encoding:   {}", encoding);
+    print!("{}", stats);
+    Ok(())
+}
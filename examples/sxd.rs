@@ -0,0 +1,119 @@
+use std::env;
+use std::fs;
+use std::io;
+
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use sxd_xpath::{evaluate_xpath, Value};
+
+use choose_your_xml::{EventVisitor, PrintVisitor};
+
+// The fields are only read by the `Debug` output in `main`.
+#[derive(Debug)]
+#[allow(dead_code, clippy::enum_variant_names)]
+enum Error {
+    IoError(io::Error),
+    ParseError(sxd_document::parser::Error),
+    XPathError(sxd_xpath::Error),
+    DumpError(choose_your_xml::Error),
+}
+
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(io::Error, IoError);
+from_error!(sxd_document::parser::Error, ParseError);
+from_error!(sxd_xpath::Error, XPathError);
+from_error!(choose_your_xml::Error, DumpError);
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tsxd input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let package = sxd_document::parser::parse(&text)?;
+    let doc = package.as_document();
+
+    let mut visitor = PrintVisitor::new(io::stdout());
+    for child in doc.root().children() {
+        match child {
+            ChildOfRoot::Element(e) => walk(e, &mut visitor, 0)?,
+            ChildOfRoot::Comment(c) => visitor.comment(c.text(), 0)?,
+            ChildOfRoot::ProcessingInstruction(pi) => visitor.pi(&pi_text(pi.target(), pi.value()), 0)?,
+        }
+    }
+
+    // sxd-document is the only backend with XPath support.
+    println!();
+    for expr in &["count(//*)", "//@*"] {
+        match evaluate_xpath(&doc, expr)? {
+            Value::Nodeset(nodes) => {
+                println!("XPath: {} = {} node(s)", expr, nodes.size());
+                for node in nodes.document_order() {
+                    println!("  {:?}", node.string_value());
+                }
+            }
+            value => println!("XPath: {} = {}", expr, value.string()),
+        }
+    }
+
+    Ok(())
+}
+
+fn walk(element: Element, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    let ns = element.name().namespace_uri();
+    let name = element.name().local_part();
+    let prefix = element.preferred_prefix();
+
+    let attrs: Vec<_> = element.attributes().iter()
+        .map(|a| (a.name().local_part().to_string(), a.value().to_string()))
+        .collect();
+
+    let children = element.children();
+    if children.is_empty() {
+        visitor.empty(ns, prefix, name, &attrs, depth)?;
+        return Ok(());
+    }
+
+    visitor.start(ns, prefix, name, &attrs, depth)?;
+
+    for child in children {
+        match child {
+            ChildOfElement::Element(e) => walk(e, visitor, depth + 1)?,
+            ChildOfElement::Text(t) => visitor.text(t.text(), depth + 1)?,
+            ChildOfElement::Comment(c) => visitor.comment(c.text(), depth + 1)?,
+            ChildOfElement::ProcessingInstruction(pi) => {
+                visitor.pi(&pi_text(pi.target(), pi.value()), depth + 1)?;
+            }
+        }
+    }
+
+    visitor.end(ns, prefix, name, depth)?;
+
+    Ok(())
+}
+
+fn pi_text(target: &str, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("{} {}", target, value),
+        None => target.to_string(),
+    }
+}
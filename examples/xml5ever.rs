@@ -31,7 +31,13 @@ fn main() {
         text_buf: String::new(),
     };
 
-    let file = File::open(&args[1]).unwrap();
+    let file = match File::open(&args[1]) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("cannot open {}: {}", args[1], e);
+            return;
+        }
+    };
     let mut file = BufReader::new(file);
 
     let mut input = ByteTendril::new();
@@ -76,9 +82,9 @@ impl TokenSink for TokenPrinter {
                     StartTag => {
                         indent(self.depth);
 
-                        match &tag.name.prefix {
-                            &Some(ref prefix) => println!("Start: {}:{}", prefix, tag.name.local),
-                            &None => println!("Start: {}", tag.name.local),
+                        match tag.name.prefix {
+                            Some(ref prefix) => println!("Start: {}:{}", prefix, tag.name.local),
+                            None => println!("Start: {}", tag.name.local),
                         }
                         // TODO: find out how to get a namespace
                         self.depth += 1;
@@ -100,14 +106,14 @@ impl TokenSink for TokenPrinter {
 
                 for attr in tag.attrs.iter() {
                     indent(self.depth);
-                    match &attr.name.prefix {
-                        &Some(ref prefix) => println!("{}:{}=\"{}\"", prefix, attr.name.local, attr.value),
-                        &None => println!("{}=\"{}\"", attr.name.local, attr.value),
+                    match attr.name.prefix {
+                        Some(ref prefix) => println!("{}:{}=\"{}\"", prefix, attr.name.local, attr.value),
+                        None => println!("{}=\"{}\"", attr.name.local, attr.value),
                     }
                 }
             }
             Token::CharacterTokens(b) => {
-                self.text_buf.push_str(&*b)
+                self.text_buf.push_str(&b)
             }
             Token::NullCharacterToken => {}
             Token::PIToken(d) => {
@@ -0,0 +1,97 @@
+use std::default::Default;
+use std::env;
+use std::io::{self, BufReader};
+use std::fs::File;
+
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use xml5ever::driver::{parse_document, XmlParseOpts};
+use xml5ever::tendril::TendrilSink;
+
+use choose_your_xml::{EventVisitor, PrintVisitor, Result};
+
+// Unlike `xml5ever.rs`, which prints raw tokens, this example builds a tree,
+// so all the recovery done by xml5ever is visible. Try it with `data/broken.xml`,
+// which quick_xml refuses to parse.
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\txml5ever_rcdom input.xml");
+        return;
+    }
+
+    let file = match File::open(&args[1]) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("cannot open {}: {}", args[1], e);
+            return;
+        }
+    };
+    let mut file = BufReader::new(file);
+
+    let dom = parse_document(RcDom::default(), XmlParseOpts::default())
+        .from_utf8()
+        .read_from(&mut file)
+        .unwrap();
+
+    let mut visitor = PrintVisitor::new(io::stdout());
+    for child in dom.document.children.borrow().iter() {
+        if let Err(e) = walk(child, &mut visitor, 0) {
+            println!("{:?}", e);
+            return;
+        }
+    }
+
+    // xml5ever doesn't mark recovered nodes in the tree, but it keeps all the errors
+    // it recovered from. Elements after an error may be implied or closed implicitly.
+    if !dom.errors.is_empty() {
+        println!("Recovered from {} error(s):", dom.errors.len());
+        for e in &dom.errors {
+            println!("  RECOVERED: {}", e);
+        }
+    }
+}
+
+fn walk(node: &Handle, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    match node.data {
+        NodeData::Element { ref name, ref attrs, .. } => {
+            let ns = if name.ns.is_empty() { None } else { Some(&*name.ns) };
+
+            let attrs: Vec<_> = attrs.borrow().iter().map(|a| {
+                let key = match a.name.prefix {
+                    Some(ref prefix) => format!("{}:{}", prefix, a.name.local),
+                    None => a.name.local.to_string(),
+                };
+
+                (key, a.value.to_string())
+            }).collect();
+
+            let children = node.children.borrow();
+            if children.is_empty() {
+                return visitor.empty(ns, name.prefix.as_deref(), &name.local, &attrs, depth);
+            }
+
+            visitor.start(ns, name.prefix.as_deref(), &name.local, &attrs, depth)?;
+            for child in children.iter() {
+                walk(child, visitor, depth + 1)?;
+            }
+            visitor.end(ns, name.prefix.as_deref(), &name.local, depth)?;
+        }
+        NodeData::Text { ref contents } => {
+            visitor.text(&contents.borrow(), depth)?;
+        }
+        NodeData::Comment { ref contents } => {
+            visitor.comment(contents, depth)?;
+        }
+        NodeData::ProcessingInstruction { ref target, ref contents } => {
+            visitor.pi(&format!("{} {}", target, contents), depth)?;
+        }
+        NodeData::Doctype { ref name, .. } => {
+            visitor.doctype(name, depth)?;
+        }
+        NodeData::Document => {}
+    }
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use std::env;
+use std::io;
+
+use choose_your_xml::{walk_xml_rs_events, PrintVisitor, Result, XmlRsReader};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\txml_rs input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<()> {
+    walk_xml_rs_events(XmlRsReader::open(path)?, &mut PrintVisitor::new(io::stdout()))
+}
@@ -0,0 +1,54 @@
+use std::env;
+use std::io::BufRead;
+use std::process;
+
+use quick_xml::Reader;
+
+use choose_your_xml::node::DomBuilder;
+use choose_your_xml::{json_to_xml, open_input, xml_to_json_with_convention, JsonConvention, QuickXmlReader, Result};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let (flag, path) = match args.as_slice() {
+        [path] => ("", path),
+        [flag, path] if flag == "--parker" || flag == "--round-trip" => (flag.as_str(), path),
+        _ => {
+            println!("Usage:\n\txml_to_json [--parker|--round-trip] input.xml|-");
+            return;
+        }
+    };
+
+    let res = open_input(path).and_then(|input| match flag {
+        "--parker" => convert(input, JsonConvention::Parker),
+        "--round-trip" => round_trip(input),
+        _ => convert(input, JsonConvention::BadgerFish),
+    });
+
+    if let Err(e) = res {
+        println!("{}", e.in_file(path));
+        process::exit(1);
+    }
+}
+
+fn convert<R: BufRead>(input: R, convention: JsonConvention) -> Result<()> {
+    let root = DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_reader(input)))?;
+    println!("{:#}", xml_to_json_with_convention(&root, convention));
+    Ok(())
+}
+
+// Converts the document into JSON, back and into JSON again, which should produce the same value.
+fn round_trip<R: BufRead>(input: R) -> Result<()> {
+    let root = DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_reader(input)))?;
+    let json = xml_to_json_with_convention(&root, JsonConvention::BadgerFish);
+    let again = xml_to_json_with_convention(&json_to_xml(&json)?, JsonConvention::BadgerFish);
+
+    if json == again {
+        println!("The round trip is lossless.");
+    } else {
+        println!("The round trip changed the JSON:\n{:#}\n{:#}", json, again);
+        process::exit(1);
+    }
+
+    Ok(())
+}
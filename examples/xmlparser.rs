@@ -0,0 +1,131 @@
+use std::env;
+use std::fs;
+
+use xmlparser::{ElementEnd, StrSpan, Token, Tokenizer};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\txmlparser input.xml");
+        return;
+    }
+
+    let text = match fs::read_to_string(&args[1]) {
+        Ok(text) => text,
+        Err(e) => {
+            println!("cannot open {}: {}", args[1], e);
+            return;
+        }
+    };
+
+    if let Err(e) = parse(&text) {
+        println!("Error: {}.", e);
+    }
+}
+
+// xmlparser doesn't allocate and only returns string spans of the input,
+// so each token is printed with its byte range.
+fn parse(text: &str) -> Result<(), xmlparser::Error> {
+    let mut depth = 0;
+
+    for token in Tokenizer::from(text) {
+        match token? {
+            Token::Declaration { version, encoding, standalone, span } => {
+                print_span("Declaration", span, depth);
+
+                indent(depth);
+                println!("  version=\"{}\"", version);
+
+                if let Some(v) = encoding {
+                    indent(depth);
+                    println!("  encoding=\"{}\"", v);
+                }
+
+                if let Some(v) = standalone {
+                    indent(depth);
+                    println!("  standalone=\"{}\"", if v { "yes" } else { "no" });
+                }
+            }
+            Token::ProcessingInstruction { target, content, span } => {
+                let text = match content {
+                    Some(content) => format!("{} {}", target, content),
+                    None => target.to_string(),
+                };
+                print_text("Processing Instruction", &text, span, depth);
+            }
+            Token::Comment { text, span } => {
+                print_text("Comment", &text, span, depth);
+            }
+            Token::DtdStart { name, span, .. } => {
+                print_text("Document Type", &name, span, depth);
+                depth += 1;
+            }
+            Token::EmptyDtd { name, span, .. } => {
+                print_text("Document Type", &name, span, depth);
+            }
+            Token::EntityDeclaration { name, span, .. } => {
+                print_text("Entity Declaration", &name, span, depth);
+            }
+            Token::DtdEnd { span } => {
+                depth -= 1;
+                print_span("Document Type End", span, depth);
+            }
+            Token::ElementStart { prefix, local, span } => {
+                print_text("Start", &qualified_name(prefix, local), span, depth);
+            }
+            Token::Attribute { prefix, local, value, span } => {
+                indent(depth + 1);
+                println!("  Attribute: {}=\"{}\" [{}..{}]",
+                    qualified_name(prefix, local), value, span.start(), span.end());
+            }
+            Token::ElementEnd { end, span } => {
+                match end {
+                    ElementEnd::Open => {
+                        depth += 1;
+                    }
+                    ElementEnd::Close(prefix, local) => {
+                        depth -= 1;
+                        print_text("End", &qualified_name(prefix, local), span, depth);
+                    }
+                    ElementEnd::Empty => {
+                        print_span("Empty End", span, depth);
+                    }
+                }
+            }
+            Token::Text { text } => {
+                print_text("  Text", &text, text, depth);
+            }
+            Token::Cdata { text, span } => {
+                print_text("CDATA", &text, span, depth);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn qualified_name(prefix: StrSpan, local: StrSpan) -> String {
+    if prefix.is_empty() {
+        local.to_string()
+    } else {
+        format!("{}:{}", prefix, local)
+    }
+}
+
+fn print_span(title: &str, span: StrSpan, depth: usize) {
+    indent(depth);
+    println!("{} [{}..{}]", title, span.start(), span.end());
+}
+
+// Text is not unescaped, since xmlparser works only with the raw input.
+fn print_text(title: &str, text: &str, span: StrSpan, depth: usize) {
+    indent(depth);
+    println!("{}: {:?} [{}..{}]", title, text, span.start(), span.end());
+}
+
+fn indent(depth: usize) {
+    for _ in 0..depth {
+        print!("  ");
+    }
+}
@@ -8,6 +8,7 @@ use xml::common::XmlVersion;
 use xml::ParserConfig;
 
 #[derive(Debug)]
+#[allow(dead_code)]
 enum Error {
     XmlError(XmlError),
     Utf8Error(Utf8Error),
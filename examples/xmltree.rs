@@ -0,0 +1,78 @@
+use std::env;
+use std::io::{self, BufReader};
+use std::fs::File;
+
+use xmltree::{Element, XMLNode};
+
+use choose_your_xml::{EventVisitor, PrintVisitor, Result};
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\txmltree input.xml");
+        return;
+    }
+
+    let file = match File::open(&args[1]) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("cannot open {}: {}", args[1], e);
+            return;
+        }
+    };
+    let file = BufReader::new(file);
+
+    let root = match Element::parse(file) {
+        Ok(root) => root,
+        Err(e) => {
+            println!("Error: {}.", e);
+            return;
+        }
+    };
+
+    if let Err(e) = walk(&root, &mut PrintVisitor::new(io::stdout()), 0) {
+        println!("{:?}", e);
+        return;
+    }
+
+    // `Element::parse` drops everything except elements and text,
+    // so be honest about what wasn't printed.
+    println!("declaration: not supported by this backend");
+    println!("comments: not supported by this backend");
+    println!("processing instructions: not supported by this backend");
+}
+
+fn walk(element: &Element, visitor: &mut impl EventVisitor, depth: usize) -> Result<()> {
+    // Attributes are stored in a HashMap, so we have to sort them to get a stable output.
+    let mut attrs: Vec<_> = element.attributes.iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    attrs.sort();
+
+    let ns = element.namespace.as_deref();
+
+    if element.children.is_empty() {
+        return visitor.empty(ns, element.prefix.as_deref(), &element.name, &attrs, depth);
+    }
+
+    visitor.start(ns, element.prefix.as_deref(), &element.name, &attrs, depth)?;
+
+    for child in &element.children {
+        match child {
+            XMLNode::Element(e) => walk(e, visitor, depth + 1)?,
+            XMLNode::Text(text) => visitor.text(text, depth + 1)?,
+            XMLNode::CData(text) => visitor.cdata(text, depth + 1)?,
+            XMLNode::Comment(text) => visitor.comment(text, depth + 1)?,
+            XMLNode::ProcessingInstruction(name, data) => {
+                let text = match data {
+                    Some(data) => format!("{} {}", name, data),
+                    None => name.clone(),
+                };
+                visitor.pi(&text, depth + 1)?;
+            }
+        }
+    }
+
+    visitor.end(ns, element.prefix.as_deref(), &element.name, depth)
+}
@@ -0,0 +1,122 @@
+// `yaserde_derive` generates its impls inside of a `const` block.
+#![allow(non_local_definitions)]
+
+use std::env;
+use std::io::{self, BufReader, Read, Write};
+use std::fs::File;
+
+use yaserde::{YaDeserialize, YaSerialize};
+use yaserde_derive::{YaDeserialize, YaSerialize};
+
+// The same schema as in the serde examples. It matches `data/small.xml`.
+//
+// yaserde requires all types to implement `Default` and marks attributes explicitly.
+// `data/small.xml` doesn't use namespaces, but a namespaced element would be declared as:
+// #[yaserde(prefix = "p", namespace = "p: http://example.com/project")]
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "project")]
+struct Project {
+    #[yaserde(attribute)]
+    name: String,
+    libraries: Libraries,
+    #[yaserde(rename = "module")]
+    modules: Vec<Module>,
+}
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "libraries")]
+struct Libraries {
+    #[yaserde(rename = "library")]
+    items: Vec<Library>,
+}
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "library")]
+struct Library {
+    #[yaserde(attribute, rename = "groupId")]
+    group_id: String,
+    #[yaserde(attribute, rename = "artifactId")]
+    artifact_id: String,
+    #[yaserde(attribute)]
+    version: String,
+}
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "module")]
+struct Module {
+    #[yaserde(attribute)]
+    name: String,
+    files: Files,
+    libraries: Libraries,
+}
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "files")]
+struct Files {
+    #[yaserde(rename = "file")]
+    items: Vec<SourceFile>,
+}
+
+#[derive(Debug, Default, YaDeserialize, YaSerialize)]
+#[yaserde(rename = "file")]
+struct SourceFile {
+    #[yaserde(attribute)]
+    name: String,
+    #[yaserde(attribute, rename = "type")]
+    kind: String,
+    #[yaserde(text)]
+    content: String,
+}
+
+// The fields are only read by the `Debug` output in `main`.
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Error {
+    IoError(io::Error),
+    // yaserde reports errors as plain strings.
+    YaserdeError(String),
+}
+
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(io::Error, IoError);
+from_error!(String, YaserdeError);
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+
+    if args.len() != 2 {
+        println!("Usage:\n\tyaserde input.xml");
+        return;
+    }
+
+    if let Err(e) = parse(&args[1]) {
+        println!("{:?}", e);
+    }
+}
+
+fn parse(path: &str) -> Result<(), Error> {
+    let file = File::open(path)?;
+    let file = BufReader::new(file);
+
+    let project: Project = yaserde::de::from_reader(file)?;
+    println!("{:#?}", project);
+
+    // Serialize it back to compare the round-trip fidelity with the input.
+    let config = yaserde::ser::Config {
+        perform_indent: true,
+        .. Default::default()
+    };
+    println!("{}", yaserde::ser::to_string_with_config(&project, &config)?);
+
+    Ok(())
+}
@@ -0,0 +1,418 @@
+use std::io::{self, BufRead, Read};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::entity::DocumentEntities;
+use crate::error::LineCounter;
+use crate::{check_unclosed, declare_entities, track_open_elements, visit_event_with_entities};
+use crate::{Error, EventVisitor, OwnedEvent, Result};
+
+/// An async reader of quick_xml events.
+///
+/// quick_xml 0.18 can't read asynchronously, so the input is read in chunks
+/// until the buffered bytes contain a complete event, which is then parsed
+/// by a regular `quick_xml::Reader` without blocking.
+/// The buffer grows to the size of the largest event, like the quick_xml one.
+///
+/// The underlying reader uses the default settings, so end names are checked
+/// and the text is not trimmed.
+pub struct AsyncXmlReader<R: AsyncBufRead + Unpin> {
+    source: R,
+    reader: Reader<Feed>,
+    shared: Arc<Mutex<Shared>>,
+    // Bytes passed to the `reader` but not consumed by it yet.
+    window: Vec<u8>,
+    // The input offset of the `window` start.
+    window_start: usize,
+    source_eof: bool,
+    // Whether the `reader` has already consumed the `<` of the next markup.
+    in_markup: bool,
+    buf: Vec<u8>,
+    ns_buf: Vec<u8>,
+    lines: LineCounter,
+    done: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncXmlReader<R> {
+    /// Creates a new reader.
+    pub fn new(source: R) -> Self {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        AsyncXmlReader {
+            source,
+            reader: Reader::from_reader(Feed { shared: shared.clone(), chunk: Vec::new(), pos: 0 }),
+            shared,
+            window: Vec::new(),
+            window_start: 0,
+            source_eof: false,
+            in_markup: false,
+            buf: Vec::new(),
+            ns_buf: Vec::new(),
+            lines: LineCounter::default(),
+            done: false,
+        }
+    }
+
+    /// Reads the next event.
+    ///
+    /// Returns `Event::Eof` at the end of the document and on each call after it or after an error.
+    /// Parsing errors are reported as `Error::Syntax`, like in `EventIter`.
+    pub async fn next_event(&mut self) -> Result<OwnedEvent> {
+        Ok(self.next_namespaced_event().await?.1)
+    }
+
+    /// Reads the next event along with the namespace of an element.
+    pub async fn next_namespaced_event(&mut self) -> Result<(Option<Vec<u8>>, OwnedEvent)> {
+        if self.done {
+            return Ok((None, Event::Eof));
+        }
+
+        self.fill_event().await?;
+
+        // Lines are counted on the next read, because the previous event was copied out of the buffer.
+        self.lines.consume(&self.buf);
+        self.buf.clear();
+
+        let result = match self.reader.read_namespaced_event(&mut self.buf, &mut self.ns_buf) {
+            Ok((_, Event::Eof)) => Ok((None, Event::Eof)),
+            Ok((ns, event)) => {
+                self.lines.event_read(self.reader.buffer_position(), matches!(event, Event::Text(_)));
+                self.in_markup = matches!(event, Event::Text(_));
+                Ok((ns.map(|ns| ns.to_vec()), event.into_owned()))
+            }
+            Err(e) => Err(Error::syntax(&self.reader, &self.lines, &self.buf, e)),
+        };
+
+        if matches!(result, Ok((_, Event::Eof)) | Err(_)) {
+            self.done = true;
+        }
+
+        result
+    }
+
+    /// Returns the line of the last read event.
+    pub fn line(&self) -> usize {
+        self.lines.line()
+    }
+
+    // Reads the input until the next event is buffered completely or the input ends.
+    async fn fill_event(&mut self) -> Result<()> {
+        let consumed = self.shared.lock().unwrap().consumed;
+        self.window.drain(..consumed - self.window_start);
+        self.window_start = consumed;
+
+        while !self.source_eof && !is_event_complete(&self.window, self.in_markup) {
+            let chunk = self.source.fill_buf().await?;
+            if chunk.is_empty() {
+                self.source_eof = true;
+                break;
+            }
+
+            let len = chunk.len();
+            self.window.extend_from_slice(chunk);
+            self.shared.lock().unwrap().incoming.extend_from_slice(chunk);
+            self.source.consume(len);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads all events of the document, except the final `Event::Eof`.
+///
+/// An async counterpart of collecting an `EventIter`.
+pub async fn parse_async<R: AsyncBufRead + Unpin>(source: R) -> Result<Vec<OwnedEvent>> {
+    let mut reader = AsyncXmlReader::new(source);
+    let mut events = Vec::new();
+    loop {
+        match reader.next_event().await? {
+            Event::Eof => return Ok(events),
+            event => events.push(event),
+        }
+    }
+}
+
+/// Reads all events from the `source` and passes them to the `visitor`.
+///
+/// An async counterpart of `walk_events`, which reports errors the same way.
+pub async fn walk_async<R: AsyncBufRead + Unpin>(source: R, visitor: &mut impl EventVisitor) -> Result<()> {
+    let mut reader = AsyncXmlReader::new(source);
+    let mut depth = 0;
+    let mut open = Vec::new();
+    let mut entities = DocumentEntities::default();
+
+    loop {
+        let (ns, event) = match reader.next_namespaced_event().await? {
+            (_, Event::Eof) => break,
+            event => event,
+        };
+        declare_entities(&event, &mut entities);
+        visit_event_with_entities(&reader.reader, &entities, ns.as_deref(), &event, &mut depth, visitor)
+            .map_err(|e| e.in_event(&reader.lines))?;
+        track_open_elements(&event, &mut open);
+    }
+
+    check_unclosed(&open)
+}
+
+#[derive(Default)]
+struct Shared {
+    // Bytes read from the source but not passed to the `Feed` yet.
+    incoming: Vec<u8>,
+    // A number of bytes consumed by the `Reader`.
+    consumed: usize,
+}
+
+// The input of the `Reader`, i.e. the bytes that were already read asynchronously.
+//
+// An empty `fill_buf` means the end of the input for the `Reader`, so it must not be read
+// until a complete event is buffered.
+struct Feed {
+    shared: Arc<Mutex<Shared>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Feed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for Feed {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.chunk.len() {
+            self.chunk.clear();
+            self.pos = 0;
+            mem::swap(&mut self.chunk, &mut self.shared.lock().unwrap().incoming);
+        }
+
+        Ok(&self.chunk[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+        self.shared.lock().unwrap().consumed += amt;
+    }
+}
+
+// Checks that the `Reader` can read the next event from the `window` without reaching its end.
+//
+// Mirrors the way quick_xml looks for the end of text and markup.
+fn is_event_complete(window: &[u8], in_markup: bool) -> bool {
+    if in_markup {
+        markup_len(window).is_some()
+    } else {
+        window.contains(&b'<')
+    }
+}
+
+// Returns the length of the markup up to and including its `>`.
+// The `window` starts right after the `<`.
+fn markup_len(window: &[u8]) -> Option<usize> {
+    match *window.first()? {
+        b'!' => bang_markup_len(window),
+        b'/' | b'?' => window.iter().position(|&b| b == b'>').map(|i| i + 1),
+        _ => {
+            // `>` inside attribute values doesn't end a tag.
+            let mut quote = None;
+            for (i, &b) in window.iter().enumerate() {
+                match (quote, b) {
+                    (None, b'>') => return Some(i + 1),
+                    (None, b'"') | (None, b'\'') => quote = Some(b),
+                    (Some(q), b) if q == b => quote = None,
+                    _ => {}
+                }
+            }
+
+            None
+        }
+    }
+}
+
+// quick_xml reads up to the first `>` and then extends a comment, CDATA or DOCTYPE to its real end.
+fn bang_markup_len(window: &[u8]) -> Option<usize> {
+    let mut ends = window.iter().enumerate().filter(|&(_, &b)| b == b'>').map(|(i, _)| i);
+    let first = ends.next()?;
+
+    let content = &window[..first];
+    if content.starts_with(b"!--") {
+        let end = window.iter().enumerate()
+            .position(|(i, &b)| b == b'>' && i >= 5 && window[..i].ends_with(b"--"))?;
+        Some(end + 1)
+    } else if content.starts_with(b"![CDATA[") {
+        let end = window.iter().enumerate()
+            .position(|(i, &b)| b == b'>' && i >= 10 && window[..i].ends_with(b"]]"))?;
+        Some(end + 1)
+    } else if content.starts_with(b"!DOCTYPE") {
+        // Nested markup declarations are counted by their `<`.
+        let mut open = content.iter().filter(|&&b| b == b'<').count();
+        let mut end = first;
+        while open > 0 {
+            let next = ends.next()?;
+            open += window[end + 1..next].iter().filter(|&&b| b == b'<').count();
+            open -= 1;
+            end = next;
+        }
+
+        Some(end + 1)
+    } else {
+        Some(first + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use quick_xml::events::{BytesEnd, BytesStart, BytesText};
+    use tokio::io::{AsyncRead, ReadBuf};
+
+    use super::*;
+
+    // Returns at most `chunk` bytes per read, so events are split across reads.
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl AsyncRead for Chunked<'_> {
+        fn poll_read(mut self: Pin<&mut Self>, _: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+            let len = self.chunk.min(self.data.len()).min(buf.remaining());
+            buf.put_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+    }
+
+    // quick_xml events are compared by their `Debug` output, since they don't implement `PartialEq`.
+    fn debug(events: &[OwnedEvent]) -> String {
+        format!("{:?}", events)
+    }
+
+    fn sync_events(text: &str) -> Vec<OwnedEvent> {
+        let mut reader = Reader::from_str(text);
+        crate::EventIter::new(&mut reader).map(|e| e.unwrap().1).collect()
+    }
+
+    fn chunked(text: &str, chunk: usize) -> tokio::io::BufReader<Chunked<'_>> {
+        tokio::io::BufReader::with_capacity(chunk, Chunked { data: text.as_bytes(), chunk })
+    }
+
+    const DOCUMENT: &str = concat!(
+        "<?xml version=\"1.0\"?>\n",
+        "<!DOCTYPE root [<!ENTITY e \"<x>\"><!-- > -->]>\n",
+        "<root a=\"x > y\" b='\"'><!-- a -- > comment -->text &amp; more",
+        "<![CDATA[ ]]> ]]><?pi x?><p:item xmlns:p=\"urn:p\"/></root>\n",
+    );
+
+    #[test]
+    fn matches_sync_events() {
+        let expected = sync_events(DOCUMENT);
+        assert!(expected.len() > 10);
+
+        for chunk in 1..DOCUMENT.len() + 1 {
+            let events = block_on(parse_async(chunked(DOCUMENT, chunk))).unwrap();
+            assert_eq!(debug(&events), debug(&expected), "chunk size {}", chunk);
+        }
+    }
+
+    #[test]
+    fn reads_events_one_by_one() {
+        let events = block_on(async {
+            let mut reader = AsyncXmlReader::new(chunked("<a>t</a>", 1));
+            let mut events = Vec::new();
+            for _ in 0..6 {
+                events.push(reader.next_event().await.unwrap());
+            }
+            events
+        });
+
+        let expected = vec![
+            Event::Text(BytesText::from_escaped(&b""[..])),
+            Event::Start(BytesStart::owned_name("a")),
+            Event::Text(BytesText::from_escaped(&b"t"[..])),
+            Event::End(BytesEnd::owned(b"a".to_vec())),
+            Event::Eof,
+            Event::Eof,
+        ];
+        assert_eq!(debug(&events), debug(&expected));
+    }
+
+    #[test]
+    fn resolves_namespaces() {
+        block_on(async {
+            let mut reader = AsyncXmlReader::new(chunked("<p:a xmlns:p=\"urn:p\"/>", 3));
+            loop {
+                match reader.next_namespaced_event().await.unwrap() {
+                    (ns, Event::Empty(_)) => {
+                        assert_eq!(ns.as_deref(), Some(&b"urn:p"[..]));
+                        break;
+                    }
+                    (_, Event::Eof) => panic!("no element"),
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    fn sync_text(text: &str) -> String {
+        let mut visitor = crate::TextVisitor::new(Vec::new()).separator("|");
+        crate::walk_events(&mut Reader::from_str(text), &mut visitor).unwrap();
+        String::from_utf8(visitor.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn walks_events() {
+        let document = DOCUMENT.replace("more", "more &e;");
+        let expected = sync_text(&document);
+        assert!(expected.contains("text & more <x>"));
+
+        for &chunk in &[1, 7, document.len()] {
+            let mut visitor = crate::TextVisitor::new(Vec::new()).separator("|");
+            block_on(walk_async(chunked(&document, chunk), &mut visitor)).unwrap();
+            assert_eq!(String::from_utf8(visitor.into_inner()).unwrap(), expected, "chunk size {}", chunk);
+        }
+    }
+
+    #[test]
+    fn walks_unclosed_elements() {
+        struct Noop;
+
+        impl EventVisitor for Noop {}
+
+        match block_on(walk_async(chunked("<a><b></b>", 3), &mut Noop)) {
+            Err(Error::Unclosed { ref name }) => assert_eq!(name, "a"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let text = "<a>\n<b></c></a>";
+        let res = block_on(parse_async(chunked(text, 2)));
+        match res {
+            Err(Error::Syntax { line, error: quick_xml::Error::EndEventMismatch { .. }, .. }) => assert_eq!(line, 2),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn reports_unclosed_markup() {
+        let res = block_on(parse_async(chunked("<a><!-- unclosed", 4)));
+        match res {
+            Err(Error::Syntax { error: quick_xml::Error::UnexpectedEof(ref what), .. }) => assert_eq!(what, "Comment"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
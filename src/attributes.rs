@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::str;
+
+use quick_xml::events::BytesStart;
+
+use crate::event::Attr;
+use crate::{Error, NamespaceRegistry, Result};
+
+/// All attributes of an element, indexed by a namespace URI and a local name.
+///
+/// Values are unescaped on construction.
+/// Namespace declarations (`xmlns` and `xmlns:*`) are not attributes in this model, so they are skipped.
+/// Unprefixed attributes have no namespace, even when a default namespace is in scope.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct AttributeMap {
+    map: HashMap<(Option<String>, String), String>,
+}
+
+impl AttributeMap {
+    /// Collects attributes of the element, resolving prefixes with the `namespaces` in scope.
+    ///
+    /// The `namespaces` must already include declarations of the element itself,
+    /// like after `NamespaceRegistry::update` with its Start or Empty event.
+    ///
+    /// Returns `Error::UnboundPrefix` for an attribute with an undeclared prefix.
+    /// Attributes with the same qualified name are rejected by quick_xml.
+    pub fn with_namespaces(e: &BytesStart, namespaces: &NamespaceRegistry) -> Result<Self> {
+        let mut map = HashMap::new();
+
+        for a in e.attributes() {
+            let a = a?;
+            if let Some(key) = resolve_key(a.key, namespaces)? {
+                let value = str::from_utf8(&a.unescaped_value()?)?.to_string();
+                map.insert(key, value);
+            }
+        }
+
+        Ok(AttributeMap { map })
+    }
+
+    /// Collects already unescaped attributes of a backend-agnostic event.
+    ///
+    /// See `with_namespaces` for the requirements on the `namespaces` and errors.
+    pub fn from_attrs(attrs: &[Attr], namespaces: &NamespaceRegistry) -> Result<Self> {
+        let mut map = HashMap::new();
+
+        for a in attrs {
+            if let Some(key) = resolve_key(&a.key, namespaces)? {
+                map.insert(key, a.value.to_string());
+            }
+        }
+
+        Ok(AttributeMap { map })
+    }
+
+    /// Returns a value of an attribute without a namespace.
+    pub fn get(&self, local: &str) -> Option<&str> {
+        self.map.get(&(None, local.to_string())).map(String::as_str)
+    }
+
+    /// Returns a value of an attribute in the `ns` namespace.
+    pub fn get_ns(&self, ns: &str, local: &str) -> Option<&str> {
+        self.map.get(&(Some(ns.to_string()), local.to_string())).map(String::as_str)
+    }
+
+    /// Adds an attribute, replacing the value of an existing one.
+    pub fn insert(&mut self, ns: Option<String>, local: String, value: String) {
+        self.map.insert((ns, local), value);
+    }
+
+    /// Returns the number of attributes.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Checks that there are no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns all attributes as ((namespace URI, local name), value) pairs in an arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&(Option<String>, String), &str)> {
+        self.map.iter().map(|(k, v)| (k, v.as_str()))
+    }
+}
+
+// Returns `None` for namespace declarations.
+fn resolve_key(key: &[u8], namespaces: &NamespaceRegistry) -> Result<Option<(Option<String>, String)>> {
+    if key == b"xmlns" || key.starts_with(b"xmlns:") {
+        return Ok(None);
+    }
+
+    let key = match key.iter().position(|c| *c == b':') {
+        Some(idx) => match namespaces.resolve_prefix(&key[..idx]) {
+            Some(uri) => (Some(uri.to_string()), str::from_utf8(&key[idx + 1..])?.to_string()),
+            None => return Err(Error::UnboundPrefix { prefix: str::from_utf8(&key[..idx])?.to_string() }),
+        },
+        None => (None, str::from_utf8(key)?.to_string()),
+    };
+
+    Ok(Some(key))
+}
+
+/// Collects attributes using only the namespaces declared by the element itself.
+///
+/// Use `AttributeMap::with_namespaces` to resolve prefixes declared by ancestors.
+impl TryFrom<&BytesStart<'_>> for AttributeMap {
+    type Error = Error;
+
+    fn try_from(e: &BytesStart) -> Result<Self> {
+        let mut namespaces = NamespaceRegistry::new();
+        namespaces.push(e)?;
+        AttributeMap::with_namespaces(e, &namespaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    use super::*;
+
+    // Collects attributes of the first tag.
+    fn collect(text: &str) -> Result<AttributeMap> {
+        let mut reader = Reader::from_str(text);
+        let mut buf = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(ref e) | Event::Empty(ref e) => return AttributeMap::try_from(e),
+                Event::Eof => panic!("no tags"),
+                _ => buf.clear(),
+            }
+        }
+    }
+
+    #[test]
+    fn indexes_by_expanded_names() {
+        let text = "<a xmlns=\"urn:default\" xmlns:p=\"urn:p\" id=\"1\" p:id=\"2\" xml:lang=\"en\" v=\"&lt;&amp;\"/>";
+        let map = collect(text).unwrap();
+
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get("id"), Some("1"));
+        assert_eq!(map.get_ns("urn:p", "id"), Some("2"));
+        assert_eq!(map.get_ns("urn:default", "id"), None);
+        assert_eq!(map.get_ns("http://www.w3.org/XML/1998/namespace", "lang"), Some("en"));
+        assert_eq!(map.get("v"), Some("<&"));
+        assert_eq!(map.get("xmlns"), None);
+    }
+
+    #[test]
+    fn resolves_inherited_prefixes() {
+        let mut reader = Reader::from_str("<a xmlns:p=\"urn:p\"><b p:x=\"1\"/></a>");
+        let mut namespaces = NamespaceRegistry::new();
+        let mut buf = Vec::new();
+
+        loop {
+            let event = reader.read_event(&mut buf).unwrap();
+            namespaces.update(&event).unwrap();
+            if let Event::Empty(ref e) = event {
+                let map = AttributeMap::with_namespaces(e, &namespaces).unwrap();
+                assert_eq!(map.get_ns("urn:p", "x"), Some("1"));
+                return;
+            }
+            buf.clear();
+        }
+    }
+
+    #[test]
+    fn rejects_unbound_prefix() {
+        match collect("<a p:x=\"1\"/>") {
+            Err(Error::UnboundPrefix { ref prefix }) => assert_eq!(prefix, "p"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_attributes() {
+        match collect("<a x=\"1\" x=\"2\"/>") {
+            Err(Error::QuickXml(quick_xml::Error::DuplicatedAttribute(..))) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::str::FromStr;
+
+use quick_xml::Reader;
+use quick_xml::events::Event;
+#[cfg(feature = "xml_rs")]
+use xml::reader::{EventReader, ParserConfig2, XmlEvent};
+
+use crate::entity::EntityResolver;
+use crate::error::LineCounter;
+use crate::event::{Event as LoweredEvent, XmlSource};
+use crate::{open_input, DecodingReader, EntityLimits, Error, NamespaceRegistry, WhitespaceMode};
+#[cfg(feature = "roxmltree")]
+use crate::RoxmltreeReader;
+#[cfg(feature = "sxd_document")]
+use crate::SxdReader;
+#[cfg(feature = "xml_rs")]
+use crate::{xml_rs_config, XmlRsReader};
+
+/// A common interface of the supported XML libraries.
+pub trait XmlBackend {
+    type Reader;
+    type Event;
+    type Error;
+
+    /// Opens a file for parsing.
+    ///
+    /// Backends that support streaming treat `-` as the standard input.
+    fn open(path: &str) -> Result<Self::Reader, Self::Error>;
+
+    /// Reads the next event.
+    ///
+    /// It's up to the backend how the end of the document is reported.
+    fn next_event(reader: &mut Self::Reader) -> Result<Self::Event, Self::Error>;
+
+    /// Checks that the event marks the end of the document.
+    fn is_end(event: &Self::Event) -> bool;
+}
+
+/// The quick_xml backend.
+pub struct QuickXml;
+
+/// Reads the next raw event of a `QuickXmlReader` and updates line numbers, namespaces and declared entities.
+///
+/// Evaluates to `Result<(Option<&[u8]>, quick_xml::events::Event)>`. This is a macro rather than a method,
+/// so the event borrows only the buffers and the caller can still use the other fields of the reader.
+macro_rules! read_raw_event {
+    ($r:ident) => {{
+        // Lines are counted on the next read, because the event borrows the buffer.
+        $r.lines.consume(&$r.buf);
+        $r.buf.clear();
+        match $r.reader.read_namespaced_event(&mut $r.buf, &mut $r.ns_buf) {
+            Ok((ns, event)) => {
+                $r.lines.event_read($r.reader.buffer_position(), matches!(event, quick_xml::events::Event::Text(_)));
+                match $r.namespaces.update(&event) {
+                    Ok(()) => {
+                        if let quick_xml::events::Event::DocType(ref e) = event {
+                            for (name, value) in crate::entity::declared_entities(e) {
+                                $r.entities.entry(name).or_insert(value);
+                            }
+                        }
+
+                        Ok((ns, event))
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(Error::syntax(&$r.reader, &$r.lines, &$r.buf, e)),
+        }
+    }};
+}
+
+/// A quick_xml reader that owns its buffers.
+pub struct QuickXmlReader<R: BufRead = BufReader<File>> {
+    pub(crate) reader: Reader<R>,
+    pub(crate) buf: Vec<u8>,
+    pub(crate) ns_buf: Vec<u8>,
+    pub(crate) namespaces: NamespaceRegistry,
+    pub(crate) whitespace: WhitespaceMode,
+    pub(crate) peeked: Option<LoweredEvent<'static>>,
+    pub(crate) prev_is_tag: bool,
+    pub(crate) lines: LineCounter,
+    pub(crate) entities: HashMap<Vec<u8>, Vec<u8>>,
+    pub(crate) entity_limits: EntityLimits,
+    // Bytes of all entities expanded so far, limited by `entity_limits`.
+    pub(crate) entity_expanded_len: Cell<usize>,
+    pub(crate) entity_resolver: Option<Box<EntityResolver>>,
+}
+
+impl<R: BufRead> QuickXmlReader<R> {
+    /// Creates a new reader.
+    pub fn new(reader: Reader<R>) -> Self {
+        QuickXmlReader {
+            reader,
+            buf: Vec::new(),
+            ns_buf: Vec::new(),
+            namespaces: NamespaceRegistry::new(),
+            whitespace: WhitespaceMode::default(),
+            peeked: None,
+            prev_is_tag: false,
+            lines: LineCounter::default(),
+            entities: HashMap::new(),
+            entity_limits: EntityLimits::default(),
+            entity_expanded_len: Cell::new(0),
+            entity_resolver: None,
+        }
+    }
+
+    /// Sets how whitespace-only text is handled by `XmlSource::next_event`.
+    pub fn set_whitespace_mode(&mut self, mode: WhitespaceMode) -> &mut Self {
+        self.whitespace = mode;
+        self
+    }
+
+    /// Sets custom entities, indexed by names without `&` and `;`.
+    ///
+    /// Internal entities declared in the `DOCTYPE` are added to them,
+    /// but don't replace the ones set here. See `unescape_with_entities` for the details.
+    pub fn set_entities(&mut self, entities: HashMap<Vec<u8>, Vec<u8>>) -> &mut Self {
+        self.entities = entities;
+        self
+    }
+
+    /// Sets a callback for references to entities that are neither set with `set_entities`,
+    /// declared in the `DOCTYPE` nor predefined, like the external ones.
+    ///
+    /// The callback gets the entity name without `&` and `;`. `Some` value is expanded
+    /// like a custom entity, while `None` keeps the reference verbatim instead of failing.
+    pub fn set_entity_resolver<F>(&mut self, resolver: F) -> &mut Self
+        where F: Fn(&str) -> Option<String> + 'static
+    {
+        self.entity_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Sets limits of entity expansion. The default ones are used otherwise.
+    pub fn set_entity_limits(&mut self, limits: EntityLimits) -> &mut Self {
+        self.entity_limits = limits;
+        self
+    }
+
+    /// Returns the underlying reader. Required for decoding.
+    pub fn inner(&self) -> &Reader<R> {
+        &self.reader
+    }
+
+    /// Returns the line of the last read event.
+    pub fn line(&self) -> usize {
+        self.lines.line()
+    }
+
+    /// Returns namespaces in scope of the last read event.
+    pub fn namespaces(&self) -> &NamespaceRegistry {
+        &self.namespaces
+    }
+}
+
+impl XmlBackend for QuickXml {
+    type Reader = QuickXmlReader<DecodingReader<Box<dyn BufRead>>>;
+    /// A resolved namespace and an owned event.
+    /// The end of the document is reported as `Event::Eof`.
+    type Event = (Option<Vec<u8>>, Event<'static>);
+    type Error = Error;
+
+    fn open(path: &str) -> Result<Self::Reader, Self::Error> {
+        Ok(QuickXmlReader::new(Reader::from_reader(open_input(path)?)))
+    }
+
+    fn next_event(reader: &mut Self::Reader) -> Result<Self::Event, Self::Error> {
+        let (ns, event) = read_raw_event!(reader)?;
+        Ok((ns.map(|ns| ns.to_vec()), event.into_owned()))
+    }
+
+    fn is_end(event: &Self::Event) -> bool {
+        matches!(event.1, Event::Eof)
+    }
+}
+
+/// The xml-rs backend.
+#[cfg(feature = "xml_rs")]
+pub struct XmlRs;
+
+#[cfg(feature = "xml_rs")]
+impl XmlBackend for XmlRs {
+    type Reader = EventReader<DecodingReader<Box<dyn BufRead>>>;
+    /// The end of the document is reported as `XmlEvent::EndDocument`.
+    type Event = XmlEvent;
+    type Error = Error;
+
+    fn open(path: &str) -> Result<Self::Reader, Self::Error> {
+        Ok(EventReader::new_with_config(open_input(path)?, decoded_input_config()))
+    }
+
+    fn next_event(reader: &mut Self::Reader) -> Result<Self::Event, Self::Error> {
+        Ok(reader.next()?)
+    }
+
+    fn is_end(event: &Self::Event) -> bool {
+        matches!(*event, XmlEvent::EndDocument)
+    }
+}
+
+#[cfg(feature = "xml_rs")]
+impl XmlRsReader<DecodingReader<Box<dyn BufRead>>> {
+    /// Opens a file for parsing, like `XmlRs::open`, and lowers its events.
+    ///
+    /// `-` stands for the standard input.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Ok(XmlRsReader::with_config(open_input(path)?, decoded_input_config()))
+    }
+}
+
+// The input is already converted to UTF-8, so the declared encoding no longer applies.
+#[cfg(feature = "xml_rs")]
+fn decoded_input_config() -> ParserConfig2 {
+    ParserConfig2::from(xml_rs_config())
+        .override_encoding(Some(xml::Encoding::Utf8))
+        .ignore_invalid_encoding_declarations(true)
+}
+
+/// The roxmltree backend.
+#[cfg(feature = "roxmltree")]
+pub struct Roxmltree;
+
+#[cfg(feature = "roxmltree")]
+impl XmlBackend for Roxmltree {
+    type Reader = RoxmltreeReader;
+    /// The end of the document is reported as `Event::Eof`.
+    type Event = LoweredEvent<'static>;
+    type Error = Error;
+
+    fn open(path: &str) -> Result<Self::Reader, Self::Error> {
+        RoxmltreeReader::open(path)
+    }
+
+    fn next_event(reader: &mut Self::Reader) -> Result<Self::Event, Self::Error> {
+        Ok(reader.next_event()?.into_owned())
+    }
+
+    fn is_end(event: &Self::Event) -> bool {
+        matches!(*event, LoweredEvent::Eof)
+    }
+}
+
+/// The sxd-document backend.
+#[cfg(feature = "sxd_document")]
+pub struct SxdDocument;
+
+#[cfg(feature = "sxd_document")]
+impl XmlBackend for SxdDocument {
+    type Reader = SxdReader;
+    /// The end of the document is reported as `Event::Eof`.
+    type Event = LoweredEvent<'static>;
+    type Error = Error;
+
+    fn open(path: &str) -> Result<Self::Reader, Self::Error> {
+        SxdReader::open(path)
+    }
+
+    fn next_event(reader: &mut Self::Reader) -> Result<Self::Event, Self::Error> {
+        Ok(reader.next_event()?.into_owned())
+    }
+
+    fn is_end(event: &Self::Event) -> bool {
+        matches!(*event, LoweredEvent::Eof)
+    }
+}
+
+/// A backend chosen at runtime, see `create_reader_for_backend`.
+///
+/// Only the backends enabled by features are available.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BackendKind {
+    QuickXml,
+    #[cfg(feature = "xml_rs")]
+    XmlRs,
+    #[cfg(feature = "roxmltree")]
+    Roxmltree,
+    #[cfg(feature = "sxd_document")]
+    SxdDocument,
+}
+
+impl BackendKind {
+    /// All enabled backends.
+    pub const ALL: &'static [BackendKind] = &[
+        BackendKind::QuickXml,
+        #[cfg(feature = "xml_rs")]
+        BackendKind::XmlRs,
+        #[cfg(feature = "roxmltree")]
+        BackendKind::Roxmltree,
+        #[cfg(feature = "sxd_document")]
+        BackendKind::SxdDocument,
+    ];
+
+    /// An environment variable read by `from_env`.
+    pub const ENV_VAR: &'static str = "CHOOSE_YOUR_XML_BACKEND";
+
+    /// A config file read by `from_env`, relative to the current directory.
+    pub const CONFIG_FILE: &'static str = "choose-your-xml.conf";
+
+    /// Returns the backend named by the `CHOOSE_YOUR_XML_BACKEND` environment variable.
+    ///
+    /// Without the variable, the backend named in `CONFIG_FILE` is returned,
+    /// and without the file, the backend of the `prelude`.
+    pub fn from_env() -> Result<Self, Error> {
+        if let Ok(name) = env::var(Self::ENV_VAR) {
+            return name.parse();
+        }
+
+        Ok(BackendKind::from_config(Self::CONFIG_FILE)?.unwrap_or_default())
+    }
+
+    /// Returns the backend named by a `backend = name` line of the config file.
+    ///
+    /// Empty lines, `#` comments and other keys are ignored, and the last `backend` line wins.
+    /// Returns `None` when the file doesn't exist or doesn't name a backend.
+    pub fn from_config(path: &str) -> Result<Option<Self>, Error> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(Error::Open { path: path.to_string(), error }),
+        };
+
+        let mut backend = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.starts_with('#')) {
+            if let Some((key, name)) = line.split_once('=') {
+                if key.trim() == "backend" {
+                    backend = Some(name.parse().map_err(|e: Error| e.in_file(path))?);
+                }
+            }
+        }
+
+        Ok(backend)
+    }
+
+    /// Returns a name of the backend, as accepted by `from_str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            BackendKind::QuickXml => "quick_xml",
+            #[cfg(feature = "xml_rs")]
+            BackendKind::XmlRs => "xml_rs",
+            #[cfg(feature = "roxmltree")]
+            BackendKind::Roxmltree => "roxmltree",
+            #[cfg(feature = "sxd_document")]
+            BackendKind::SxdDocument => "sxd_document",
+        }
+    }
+}
+
+// The same backend as the one of the prelude.
+impl Default for BackendKind {
+    #[cfg(not(any(feature = "xml_rs", feature = "roxmltree", feature = "sxd_document")))]
+    fn default() -> Self {
+        BackendKind::QuickXml
+    }
+
+    #[cfg(feature = "xml_rs")]
+    fn default() -> Self {
+        BackendKind::XmlRs
+    }
+
+    #[cfg(all(feature = "roxmltree", not(feature = "xml_rs")))]
+    fn default() -> Self {
+        BackendKind::Roxmltree
+    }
+
+    #[cfg(all(feature = "sxd_document", not(any(feature = "xml_rs", feature = "roxmltree"))))]
+    fn default() -> Self {
+        BackendKind::SxdDocument
+    }
+}
+
+// Names are case-insensitive and accept `-` instead of `_`, like the crate names.
+impl FromStr for BackendKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase().replace('-', "_");
+        BackendKind::ALL.iter()
+            .copied()
+            .find(|backend| backend.name() == name)
+            .ok_or_else(|| Error::UnknownBackend(s.to_string()))
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A reader of owned backend-agnostic events, which can be used as a trait object.
+///
+/// Implemented for every `XmlSource`.
+pub trait DynXmlReader {
+    /// Reads the next event and copies it out of the reader buffers.
+    ///
+    /// Returns `Event::Eof` at the end of the document.
+    /// `DumpEvent::from_event` converts the event into the form recorded by `XmlEventRecorder`.
+    fn next_owned_event(&mut self) -> Result<LoweredEvent<'static>, Error>;
+}
+
+impl<S: XmlSource> DynXmlReader for S {
+    fn next_owned_event(&mut self) -> Result<LoweredEvent<'static>, Error> {
+        Ok(self.next_event()?.into_owned())
+    }
+}
+
+/// Opens a file for parsing with a backend chosen at runtime, like by `BackendKind::from_env`.
+///
+/// `-` stands for the standard input.
+pub fn create_reader_for_backend(backend: BackendKind, path: &str) -> Result<Box<dyn DynXmlReader>, Error> {
+    match backend {
+        BackendKind::QuickXml => Ok(Box::new(QuickXml::open(path)?)),
+        #[cfg(feature = "xml_rs")]
+        BackendKind::XmlRs => Ok(Box::new(XmlRsReader::open(path)?)),
+        #[cfg(feature = "roxmltree")]
+        BackendKind::Roxmltree => Ok(Box::new(RoxmltreeReader::open(path)?)),
+        #[cfg(feature = "sxd_document")]
+        BackendKind::SxdDocument => Ok(Box::new(SxdReader::open(path)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    fn config(name: &str, text: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("choose-your-xml-{}-{}.conf", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!("quick_xml".parse::<BackendKind>().unwrap(), BackendKind::QuickXml);
+        assert_eq!(" Quick-XML\n".parse::<BackendKind>().unwrap(), BackendKind::QuickXml);
+        for &backend in BackendKind::ALL {
+            assert_eq!(backend.to_string().parse::<BackendKind>().unwrap(), backend);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        match "quick xml".parse::<BackendKind>() {
+            Err(Error::UnknownBackend(ref name)) => assert_eq!(name, "quick xml"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn reads_last_backend_line() {
+        let last = *BackendKind::ALL.last().unwrap();
+        let text = format!("# backend = nothing\n\nthreads = 4\nbackend = quick_xml\n  backend =  {} \n",
+                           last.name().to_ascii_uppercase().replace('_', "-"));
+        let path = config("last", &text);
+        let backend = BackendKind::from_config(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(backend.unwrap(), Some(last));
+    }
+
+    #[test]
+    fn reads_config_without_backend() {
+        let path = config("none", "# backend = quick_xml\nthreads = 4\n");
+        let backend = BackendKind::from_config(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(backend.unwrap(), None);
+        assert_eq!(BackendKind::from_config("data/missing.conf").unwrap(), None);
+    }
+
+    #[test]
+    fn reports_file_of_unknown_backend() {
+        let path = config("unknown", "backend = libxml3\n");
+        let path = path.to_str().unwrap().to_string();
+        let backend = BackendKind::from_config(&path);
+        fs::remove_file(&path).unwrap();
+        match backend {
+            Err(Error::InFile { path: ref file, ref error }) => {
+                assert_eq!(*file, path);
+                assert!(matches!(**error, Error::UnknownBackend(ref name) if name.trim() == "libxml3"));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn reads_owned_events_until_eof() {
+        let mut reader = create_reader_for_backend(BackendKind::QuickXml, "data/small.xml").unwrap();
+        let mut names = Vec::new();
+        loop {
+            match reader.next_owned_event().unwrap() {
+                LoweredEvent::Start { name, .. } => names.push(String::from_utf8(name.into_owned()).unwrap()),
+                LoweredEvent::Eof => break,
+                _ => {}
+            }
+        }
+        drop(reader);
+
+        assert_eq!(names[..2], ["project", "libraries"]);
+    }
+}
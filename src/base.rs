@@ -0,0 +1,383 @@
+use std::borrow::Cow;
+
+use crate::event::Event;
+use crate::{Error, Result};
+
+const XML_BASE: &[u8] = b"xml:base";
+
+/// Base URIs of the current parse position, as established by `xml:base` attributes.
+///
+/// Each `xml:base` is resolved against the base of its parent element,
+/// which is the document URI at the root, so the stack always holds resolved URIs.
+/// Resolution follows RFC 3986, without normalizing case or percent-encoding.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct BaseUriStack {
+    document: Option<String>,
+    // A base of each open element.
+    bases: Vec<Option<String>>,
+    // An Empty element is both opened and closed by a single event,
+    // so its base stays on the stack until the next event.
+    pending_pop: bool,
+}
+
+impl BaseUriStack {
+    /// Creates a stack without a document URI.
+    pub fn new() -> Self {
+        BaseUriStack::default()
+    }
+
+    /// Sets a URI of the document itself, used as the base of the root element.
+    pub fn document_uri(mut self, uri: &str) -> Self {
+        self.document = Some(uri.to_string());
+        self
+    }
+
+    /// Updates the stack with an event.
+    ///
+    /// Start and Empty events push a base, End events pop it.
+    pub fn update(&mut self, event: &Event) {
+        if self.pending_pop {
+            self.bases.pop();
+            self.pending_pop = false;
+        }
+
+        match *event {
+            Event::Start { ref attrs, .. } | Event::Empty { ref attrs, .. } => {
+                let base = match attrs.iter().find(|a| a.key.as_ref() == XML_BASE) {
+                    Some(a) => Some(self.resolve_or_keep(&a.value)),
+                    None => self.base().map(str::to_string),
+                };
+
+                self.bases.push(base);
+                self.pending_pop = matches!(*event, Event::Empty { .. });
+            }
+            Event::End { .. } => {
+                self.bases.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the base URI of the current element.
+    ///
+    /// A just visited Empty element is still current.
+    pub fn base(&self) -> Option<&str> {
+        match self.bases.last() {
+            Some(base) => base.as_deref(),
+            None => self.document.as_deref(),
+        }
+    }
+
+    /// Resolves a URI reference against the current base.
+    ///
+    /// Absolute references are returned with dot segments removed.
+    /// A relative reference without any base is reported as `Error::InvalidUri`.
+    pub fn resolve(&self, reference: &str) -> Result<String> {
+        match self.base() {
+            Some(base) => Ok(resolve(base, reference)),
+            None if Uri::parse(reference).scheme.is_some() => Ok(resolve("", reference)),
+            None => Err(Error::InvalidUri {
+                uri: reference.to_string(),
+                message: "there is no base URI to resolve a relative reference against".to_string(),
+            }),
+        }
+    }
+
+    // Without any base, a relative `xml:base` is the best known base for the descendants.
+    fn resolve_or_keep(&self, reference: &str) -> String {
+        match self.resolve(reference) {
+            Ok(uri) => uri,
+            Err(_) => reference.to_string(),
+        }
+    }
+}
+
+// URI components as in RFC 3986, section 3.
+struct Uri<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Uri<'a> {
+    fn parse(uri: &'a str) -> Self {
+        let (rest, fragment) = split_off(uri, '#');
+        let (mut rest, query) = split_off(rest, '?');
+
+        let mut scheme = None;
+        if let Some(pos) = rest.find(':') {
+            let candidate = &rest[..pos];
+            let is_scheme = candidate.starts_with(|c: char| c.is_ascii_alphabetic())
+                && candidate.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            if is_scheme {
+                scheme = Some(candidate);
+                rest = &rest[pos + 1..];
+            }
+        }
+
+        let mut authority = None;
+        if let Some(after) = rest.strip_prefix("//") {
+            let end = after.find('/').unwrap_or(after.len());
+            authority = Some(&after[..end]);
+            rest = &after[end..];
+        }
+
+        Uri { scheme, authority, path: rest, query, fragment }
+    }
+}
+
+impl Uri<'_> {
+    fn compose(&self) -> String {
+        let mut s = String::new();
+        if let Some(scheme) = self.scheme {
+            s.push_str(scheme);
+            s.push(':');
+        }
+        if let Some(authority) = self.authority {
+            s.push_str("//");
+            s.push_str(authority);
+        }
+        s.push_str(self.path);
+        if let Some(query) = self.query {
+            s.push('?');
+            s.push_str(query);
+        }
+        if let Some(fragment) = self.fragment {
+            s.push('#');
+            s.push_str(fragment);
+        }
+
+        s
+    }
+}
+
+fn split_off(s: &str, delimiter: char) -> (&str, Option<&str>) {
+    match s.find(delimiter) {
+        Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+        None => (s, None),
+    }
+}
+
+// RFC 3986, section 5.2.2.
+fn resolve(base: &str, reference: &str) -> String {
+    let b = Uri::parse(base);
+    let r = Uri::parse(reference);
+
+    if r.scheme.is_some() {
+        let path = remove_dot_segments(r.path);
+        return Uri { path: &path, ..r }.compose();
+    }
+
+    let (authority, path, query) = if r.authority.is_some() {
+        (r.authority, remove_dot_segments(r.path), r.query)
+    } else if r.path.is_empty() {
+        (b.authority, Cow::Borrowed(b.path), r.query.or(b.query))
+    } else if r.path.starts_with('/') {
+        (b.authority, remove_dot_segments(r.path), r.query)
+    } else {
+        let merged = if b.authority.is_some() && b.path.is_empty() {
+            format!("/{}", r.path)
+        } else {
+            let dir = b.path.rfind('/').map_or("", |pos| &b.path[..=pos]);
+            format!("{}{}", dir, r.path)
+        };
+
+        // A relative base can't drop leading `..` segments, since they refer to an unknown parent.
+        let path = if b.scheme.is_some() || b.authority.is_some() || merged.starts_with('/') {
+            remove_dot_segments(&merged).into_owned()
+        } else {
+            merged
+        };
+
+        (b.authority, Cow::Owned(path), r.query)
+    };
+
+    Uri { scheme: b.scheme, authority, path: &path, query, fragment: r.fragment }.compose()
+}
+
+// RFC 3986, section 5.2.4.
+fn remove_dot_segments(path: &str) -> Cow<'_, str> {
+    if !path.split('/').any(|s| s == "." || s == "..") {
+        return Cow::Borrowed(path);
+    }
+
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+    while !input.is_empty() {
+        if let Some(rest) = input.strip_prefix("../").or_else(|| input.strip_prefix("./")) {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            output.truncate(output.rfind('/').unwrap_or(0));
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let start = usize::from(input.starts_with('/'));
+            let end = input[start..].find('/').map_or(input.len(), |pos| pos + start);
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::event::XmlSource;
+    use crate::QuickXmlReader;
+
+    const RFC_BASE: &str = "http://a/b/c/d;p?q";
+
+    fn check(cases: &[(&str, &str)]) {
+        for &(reference, expected) in cases {
+            assert_eq!(resolve(RFC_BASE, reference), expected, "{}", reference);
+        }
+    }
+
+    // RFC 3986, section 5.4.1.
+    #[test]
+    fn resolves_normal_examples() {
+        check(&[
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("g;x", "http://a/b/c/g;x"),
+            ("g;x?y#s", "http://a/b/c/g;x?y#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../", "http://a/"),
+            ("../../g", "http://a/g"),
+        ]);
+    }
+
+    // RFC 3986, section 5.4.2, with the strict handling of `http:g`.
+    #[test]
+    fn resolves_abnormal_examples() {
+        check(&[
+            ("../../../g", "http://a/g"),
+            ("../../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            (".g", "http://a/b/c/.g"),
+            ("g..", "http://a/b/c/g.."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+            ("g;x=1/./y", "http://a/b/c/g;x=1/y"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+            ("g?y/./x", "http://a/b/c/g?y/./x"),
+            ("g?y/../x", "http://a/b/c/g?y/../x"),
+            ("g#s/./x", "http://a/b/c/g#s/./x"),
+            ("g#s/../x", "http://a/b/c/g#s/../x"),
+            ("http:g", "http:g"),
+        ]);
+    }
+
+    #[test]
+    fn removes_dot_segments() {
+        assert_eq!(remove_dot_segments("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(remove_dot_segments("mid/content=5/../6"), "mid/6");
+        assert!(matches!(remove_dot_segments("/a/b.c/d"), Cow::Borrowed("/a/b.c/d")));
+    }
+
+    #[test]
+    fn resolves_nested_bases() {
+        let text = std::fs::read_to_string("data/base.xml").unwrap();
+        let mut reader = QuickXmlReader::new(Reader::from_str(&text));
+        let mut stack = BaseUriStack::new();
+        let mut links = Vec::new();
+
+        loop {
+            let e = reader.next_event().unwrap();
+            stack.update(&e);
+            match e {
+                Event::Eof => break,
+                Event::Empty { ref attrs, .. } => {
+                    let href = attrs.iter().find(|a| a.key.as_ref() == b"href").map_or("", |a| &a.value);
+                    links.push(stack.resolve(href).unwrap());
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(links, [
+            "http://example.com/docs/guide/intro.html",
+            "http://example.com/docs/api/index.html",
+            "http://example.com/blog/2020/01/post.html",
+        ]);
+        assert_eq!(stack.base(), None);
+    }
+
+    #[test]
+    fn uses_document_uri_at_root() {
+        let mut reader = QuickXmlReader::new(Reader::from_str("<a><b xml:base=\"sub/\"/></a>"));
+        let mut stack = BaseUriStack::new().document_uri("file:///data/doc.xml");
+        let mut bases = Vec::new();
+
+        loop {
+            let e = reader.next_event().unwrap();
+            stack.update(&e);
+            match e {
+                Event::Eof => break,
+                Event::Start { .. } | Event::Empty { .. } => bases.push(stack.base().unwrap().to_string()),
+                _ => {}
+            }
+        }
+
+        assert_eq!(bases, ["file:///data/doc.xml", "file:///data/sub/"]);
+        assert_eq!(stack.resolve("x.xml").unwrap(), "file:///data/x.xml");
+    }
+
+    #[test]
+    fn rejects_relative_reference_without_base() {
+        let stack = BaseUriStack::new();
+        assert!(matches!(stack.resolve("g"), Err(Error::InvalidUri { ref uri, .. }) if uri == "g"));
+        assert_eq!(stack.resolve("http://a/b/../c").unwrap(), "http://a/c");
+    }
+
+    #[test]
+    fn keeps_relative_base_without_document_uri() {
+        let mut reader = QuickXmlReader::new(Reader::from_str("<a xml:base=\"x/y/\"><b xml:base=\"../z/\"/></a>"));
+        let mut stack = BaseUriStack::new();
+        let mut bases = Vec::new();
+
+        loop {
+            let e = reader.next_event().unwrap();
+            stack.update(&e);
+            match e {
+                Event::Eof => break,
+                Event::Start { .. } | Event::Empty { .. } => bases.push(stack.base().map(str::to_string)),
+                _ => {}
+            }
+        }
+
+        assert_eq!(bases, [Some("x/y/".to_string()), Some("x/y/../z/".to_string())]);
+    }
+}
@@ -0,0 +1,401 @@
+use std::env;
+use std::io::Read;
+use std::process;
+use std::time::Instant;
+
+use quick_xml::Reader;
+
+use choose_your_xml::event::Event;
+use choose_your_xml::{diff_events_with_options, open_input, quick_xml_events, DiffItem, DiffOptions, DumpEvent,
+                      QuickXmlReader, XmlSource};
+#[cfg(any(feature = "roxmltree", feature = "sxd_document"))]
+use choose_your_xml::collect_events;
+#[cfg(feature = "roxmltree")]
+use choose_your_xml::RoxmltreeReader;
+#[cfg(feature = "sxd_document")]
+use choose_your_xml::SxdReader;
+#[cfg(feature = "xml_rs")]
+use choose_your_xml::{xml_rs_events, XmlRsReader};
+
+const USAGE: &str = "Usage:\n\tcompare [--memory] input.xml|-\n\n\
+                     Parses the file with every enabled backend and reports where their events differ from \
+                     the first backend that succeeded, usually quick_xml.\n\
+                     Exits with 0 when all backends agree, 1 when some don't and 2 on errors.\n\
+                     --memory prints the time, peak heap usage and number of events or nodes per backend instead.\n\
+                     Heap usage is counted only when built with the `memory` feature. It's counted for the whole \
+                     process, so the numbers are approximate, but comparable between backends.";
+
+// Parses a document into events or returns an error message.
+type Parse = fn(&str) -> Result<Vec<DumpEvent>, String>;
+
+// The first backend that succeeds is the reference. `None` marks a backend disabled by its feature.
+const BACKENDS: &[(&str, Option<Parse>)] = &[
+    ("quick_xml", Some(parse_quick_xml)),
+    #[cfg(feature = "xml_rs")]
+    ("xml-rs", Some(parse_xml_rs)),
+    #[cfg(not(feature = "xml_rs"))]
+    ("xml-rs", None),
+    #[cfg(feature = "roxmltree")]
+    ("roxmltree", Some(parse_roxmltree)),
+    #[cfg(not(feature = "roxmltree"))]
+    ("roxmltree", None),
+    #[cfg(feature = "sxd_document")]
+    ("sxd-document", Some(parse_sxd_document)),
+    #[cfg(not(feature = "sxd_document"))]
+    ("sxd-document", None),
+];
+
+// Parses a document and returns the number of events or nodes, without keeping them.
+type Measure = fn(&str) -> Result<usize, String>;
+
+const MEASUREMENTS: &[(&str, Option<Measure>)] = &[
+    ("quick_xml", Some(measure_quick_xml)),
+    #[cfg(feature = "xml_rs")]
+    ("xml-rs", Some(measure_xml_rs)),
+    #[cfg(not(feature = "xml_rs"))]
+    ("xml-rs", None),
+    #[cfg(feature = "roxmltree")]
+    ("roxmltree", Some(measure_roxmltree)),
+    #[cfg(not(feature = "roxmltree"))]
+    ("roxmltree", None),
+    #[cfg(feature = "sxd_document")]
+    ("sxd-document", Some(measure_sxd_document)),
+    #[cfg(not(feature = "sxd_document"))]
+    ("sxd-document", None),
+];
+
+enum Outcome {
+    Reference,
+    Pass,
+    Fail(usize),
+    Error,
+    Skipped,
+}
+
+fn main() {
+    let mut args: Vec<_> = env::args().skip(1).collect();
+    let memory = args.iter().any(|arg| arg == "--memory");
+    args.retain(|arg| arg != "--memory");
+    if args.len() != 1 {
+        println!("{}", USAGE);
+        process::exit(2);
+    }
+
+    // The input is decoded once, so all backends get the same UTF-8 text.
+    let mut text = String::new();
+    if let Err(e) = open_input(&args[0]).and_then(|mut input| Ok(input.read_to_string(&mut text)?)) {
+        println!("{}", e.in_file(&args[0]));
+        process::exit(2);
+    }
+
+    if memory {
+        print_measurements(&text);
+        return;
+    }
+
+    // Attributes are unordered, even though the backends keep the document order.
+    let opts = DiffOptions { ignore_attribute_order: true, ..DiffOptions::default() };
+    let mut reference = None;
+    let mut outcomes = Vec::new();
+
+    for &(name, parse) in BACKENDS {
+        let parse = match parse {
+            Some(parse) => parse,
+            None => {
+                println!("{} is skipped, since the `{}` feature is disabled.", name, name);
+                outcomes.push((name, Outcome::Skipped));
+                continue;
+            }
+        };
+
+        let events = match parse(&text) {
+            Ok(events) => normalize(events),
+            Err(e) => {
+                println!("{} failed: {}", name, e);
+                outcomes.push((name, Outcome::Error));
+                continue;
+            }
+        };
+
+        let (reference_name, reference_events) = match reference {
+            Some((reference_name, ref reference_events)) => (reference_name, reference_events),
+            None => {
+                reference = Some((name, events));
+                outcomes.push((name, Outcome::Reference));
+                continue;
+            }
+        };
+
+        let items = diff_events_with_options(reference_events, &events, &opts);
+        for item in &items {
+            println!("{}", describe(name, reference_name, item));
+        }
+
+        outcomes.push((name, if items.is_empty() { Outcome::Pass } else { Outcome::Fail(items.len()) }));
+    }
+
+    println!();
+    let mut passed = true;
+    for (name, outcome) in &outcomes {
+        match *outcome {
+            Outcome::Reference => println!("{:<12} PASS (reference)", name),
+            Outcome::Pass => println!("{:<12} PASS", name),
+            Outcome::Fail(count) => {
+                println!("{:<12} FAIL ({} differences)", name, count);
+                passed = false;
+            }
+            Outcome::Error => {
+                println!("{:<12} FAIL (error)", name);
+                passed = false;
+            }
+            Outcome::Skipped => println!("{:<12} SKIPPED", name),
+        }
+    }
+
+    if !passed {
+        process::exit(1);
+    }
+}
+
+// Each backend starts a new region, so its peak doesn't include the allocations of the previous ones.
+// The input text itself is allocated before, so it's not counted.
+fn print_measurements(text: &str) {
+    println!("{:<12} {:>12} {:>14} {:>14}", "backend", "time, ms", "peak bytes", "events/nodes");
+
+    for &(name, measure) in MEASUREMENTS {
+        let measure = match measure {
+            Some(measure) => measure,
+            None => {
+                println!("{:<12} skipped, since the `{}` feature is disabled", name, name);
+                continue;
+            }
+        };
+
+        let start_bytes = memory::start_region();
+        let start = Instant::now();
+        let res = measure(text);
+        let time = start.elapsed().as_secs_f64() * 1000.0;
+        let peak = match memory::peak() {
+            Some(peak) => (peak - start_bytes).to_string(),
+            None => "-".to_string(),
+        };
+
+        match res {
+            Ok(count) => println!("{:<12} {:>12.1} {:>14} {:>14}", name, time, peak, count),
+            Err(e) => println!("{:<12} failed: {}", name, e),
+        }
+    }
+
+    if memory::peak().is_none() {
+        println!("\nPeak heap usage requires the `memory` feature.");
+    }
+}
+
+fn count_events(source: &mut impl XmlSource) -> Result<usize, String> {
+    let mut count = 0;
+    loop {
+        match source.next_event() {
+            Ok(Event::Eof) => return Ok(count),
+            Ok(_) => count += 1,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+fn measure_quick_xml(text: &str) -> Result<usize, String> {
+    count_events(&mut QuickXmlReader::new(Reader::from_reader(text.as_bytes())))
+}
+
+#[cfg(feature = "xml_rs")]
+fn measure_xml_rs(text: &str) -> Result<usize, String> {
+    count_events(&mut XmlRsReader::new(text.as_bytes()))
+}
+
+// The whole tree is built, which is the point of the comparison.
+#[cfg(feature = "roxmltree")]
+fn measure_roxmltree(text: &str) -> Result<usize, String> {
+    let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+    Ok(doc.descendants().count())
+}
+
+#[cfg(feature = "sxd_document")]
+fn measure_sxd_document(text: &str) -> Result<usize, String> {
+    use sxd_document::dom::ChildOfRoot;
+
+    let package = sxd_document::parser::parse(text).map_err(|e| e.to_string())?;
+    Ok(package.as_document().root().children().into_iter()
+        .map(|child| match child {
+            ChildOfRoot::Element(e) => count_sxd_nodes(e),
+            _ => 1,
+        })
+        .sum())
+}
+
+#[cfg(feature = "sxd_document")]
+fn count_sxd_nodes(element: sxd_document::dom::Element) -> usize {
+    use sxd_document::dom::ChildOfElement;
+
+    1 + element.children().into_iter()
+        .map(|child| match child {
+            ChildOfElement::Element(e) => count_sxd_nodes(e),
+            _ => 1,
+        })
+        .sum::<usize>()
+}
+
+fn parse_quick_xml(text: &str) -> Result<Vec<DumpEvent>, String> {
+    quick_xml_events(text.as_bytes()).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "xml_rs")]
+fn parse_xml_rs(text: &str) -> Result<Vec<DumpEvent>, String> {
+    xml_rs_events(text.as_bytes()).map_err(|e| e.to_string())
+}
+
+// Removes differences that every backend is allowed to have:
+// an Empty element is the same as a Start/End pair, empty text is the same as no text,
+// whitespace outside of the root element is insignificant and the declaration is dropped,
+// since it describes the input rather than the document and DOM backends don't keep it.
+fn normalize(events: Vec<DumpEvent>) -> Vec<DumpEvent> {
+    let mut normalized = Vec::with_capacity(events.len());
+    let mut depth = 0usize;
+
+    for event in events {
+        match event {
+            DumpEvent::EmptyElement { name, ns, attrs, .. } => {
+                normalized.push(DumpEvent::StartElement { name: name.clone(), ns: ns.clone(), prefix: None, attrs });
+                normalized.push(DumpEvent::EndElement { name, ns, prefix: None });
+            }
+            DumpEvent::Text(ref text) if text.is_empty() || (depth == 0 && text.trim().is_empty()) => {}
+            DumpEvent::Decl { .. } => {}
+            // Element prefixes are not reported by every backend.
+            DumpEvent::StartElement { name, ns, attrs, .. } => {
+                depth += 1;
+                normalized.push(DumpEvent::StartElement { name, ns, prefix: None, attrs });
+            }
+            DumpEvent::EndElement { name, ns, .. } => {
+                depth = depth.saturating_sub(1);
+                normalized.push(DumpEvent::EndElement { name, ns, prefix: None });
+            }
+            event => normalized.push(event),
+        }
+    }
+
+    normalized
+}
+
+fn describe(name: &str, reference: &str, item: &DiffItem) -> String {
+    match *item {
+        DiffItem::Deleted { ref location, ref event } => {
+            format!("{} dropped {} at {}", name, kind(event), location)
+        }
+        DiffItem::Inserted { ref location, ref event } => {
+            format!("{} added {} at {}", name, kind(event), location)
+        }
+        DiffItem::Changed { ref location, ref old, ref new } => {
+            format!("{} reported {} differently at {}:\n  {}: {}\n  {}: {}",
+                    name, kind(old), location, reference, summary(old), name, summary(new))
+        }
+    }
+}
+
+fn kind(event: &DumpEvent) -> &'static str {
+    match *event {
+        DumpEvent::StartElement { .. } | DumpEvent::EmptyElement { .. } => "the start of an element",
+        DumpEvent::EndElement { .. } => "the end of an element",
+        DumpEvent::Text(_) => "text",
+        DumpEvent::CData(_) => "CDATA",
+        DumpEvent::Comment(_) => "a comment",
+        DumpEvent::PI { .. } => "a processing instruction",
+        DumpEvent::DocType(_) => "DOCTYPE",
+        DumpEvent::Decl { .. } => "the declaration",
+    }
+}
+
+// Multi-line events, like elements with attributes, are joined into a single line.
+fn summary(event: &DumpEvent) -> String {
+    event.to_string().lines().map(str::trim).collect::<Vec<_>>().join(", ")
+}
+
+// roxmltree and sxd-document are DOMs, so events are synthesized while walking the tree.
+#[cfg(feature = "roxmltree")]
+fn parse_roxmltree(text: &str) -> Result<Vec<DumpEvent>, String> {
+    RoxmltreeReader::parse(text).and_then(|mut reader| collect_events(&mut reader)).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "sxd_document")]
+fn parse_sxd_document(text: &str) -> Result<Vec<DumpEvent>, String> {
+    SxdReader::parse(text).and_then(|mut reader| collect_events(&mut reader)).map_err(|e| e.to_string())
+}
+
+// A global allocator that counts live heap bytes and their high-water mark.
+//
+// The counters cover the whole process, not just the parser, so the numbers are approximate,
+// but they are comparable between backends measured the same way.
+#[cfg(feature = "memory")]
+mod memory {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingAllocator;
+
+    static LIVE: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    fn grow(size: usize) {
+        let live = LIVE.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(live, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                grow(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                if new_size > layout.size() {
+                    grow(new_size - layout.size());
+                } else {
+                    LIVE.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+                }
+            }
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator;
+
+    // Resets the peak to the current usage and returns it.
+    pub fn start_region() -> usize {
+        let live = LIVE.load(Ordering::Relaxed);
+        PEAK.store(live, Ordering::Relaxed);
+        live
+    }
+
+    pub fn peak() -> Option<usize> {
+        Some(PEAK.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(not(feature = "memory"))]
+mod memory {
+    pub fn start_region() -> usize {
+        0
+    }
+
+    pub fn peak() -> Option<usize> {
+        None
+    }
+}
@@ -0,0 +1,225 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "Usage:\n\tgenxml [--elements N] [--depth D] [--attrs-per-element K] [--text-bytes B] \
+                     [--namespaces M] [--extras RATE] [--seed S] [--output PATH]\n\n\
+                     Generates a well-formed synthetic document with N elements nested up to D levels.\n\
+                     Leaf elements get B bytes of text with characters that require escaping.\n\
+                     RATE is a probability from 0 to 1 of adding a comment, CDATA or a processing instruction \
+                     before each element.";
+
+const NAMES: &[&str] = &["item", "record", "entry", "group", "name", "value", "data", "node"];
+
+// Mostly letters, so the text stays readable, but with everything that has to be escaped.
+const TEXT_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz      ABCDEFGHIJ0123456789&<>\"'";
+// Without `-` and `]`, so comments and CDATA sections can't be closed early.
+const RAW_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz      0123456789&<>\"'";
+
+struct Options {
+    elements: usize,
+    depth: usize,
+    attrs: usize,
+    text_bytes: usize,
+    namespaces: usize,
+    extras: f64,
+    seed: u64,
+}
+
+fn main() {
+    let mut opts = Options {
+        elements: 1000,
+        depth: 5,
+        attrs: 2,
+        text_bytes: 32,
+        namespaces: 0,
+        extras: 0.0,
+        seed: 1,
+    };
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => usage(),
+        };
+
+        let res = match arg.as_str() {
+            "--elements" => value.parse().map(|v| opts.elements = v).is_ok(),
+            "--depth" => value.parse().map(|v| opts.depth = v).is_ok(),
+            "--attrs-per-element" => value.parse().map(|v| opts.attrs = v).is_ok(),
+            "--text-bytes" => value.parse().map(|v| opts.text_bytes = v).is_ok(),
+            "--namespaces" => value.parse().map(|v| opts.namespaces = v).is_ok(),
+            "--extras" => value.parse().map(|v| opts.extras = v).is_ok(),
+            "--seed" => value.parse().map(|v| opts.seed = v).is_ok(),
+            "--output" => {
+                output = Some(value.clone());
+                true
+            }
+            _ => usage(),
+        };
+
+        if !res {
+            println!("Invalid value of {}: {}.", arg, value);
+            process::exit(1);
+        }
+    }
+
+    // The root is the only element at the first level, so others need a deeper one.
+    if opts.elements == 0 || opts.depth == 0 || (opts.depth == 1 && opts.elements > 1) {
+        println!("At least one element is required, and more than one requires a depth of at least 2.");
+        process::exit(1);
+    }
+
+    // Written through a buffer, so the document is never kept in memory.
+    let res = match output {
+        Some(ref path) => File::create(path).and_then(|file| generate(BufWriter::new(file), &opts)),
+        None => generate(BufWriter::new(io::stdout().lock()), &opts),
+    };
+
+    if let Err(e) = res {
+        println!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    println!("{}", USAGE);
+    process::exit(1);
+}
+
+fn generate<W: Write>(mut w: W, opts: &Options) -> io::Result<()> {
+    let mut rng = Rng::new(opts.seed);
+    // Names of the open elements.
+    let mut stack = Vec::new();
+
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    write!(w, "<root")?;
+    for i in 0..opts.namespaces {
+        write!(w, " xmlns:n{}=\"http://example.com/ns/{}\"", i, i)?;
+    }
+    write_attributes(&mut w, &mut rng, opts)?;
+    write!(w, ">")?;
+    stack.push("root".to_string());
+
+    let mut remaining = opts.elements - 1;
+    while remaining > 0 {
+        let r = rng.next_f64();
+
+        // Elements are closed less often than opened, so the document gets deep enough.
+        if stack.len() > 1 && r < 0.15 {
+            let name = stack.pop().unwrap_or_default();
+            write!(w, "</{}>", name)?;
+            continue;
+        }
+
+        write_newline(&mut w, stack.len())?;
+        if rng.next_f64() < opts.extras {
+            write_extra(&mut w, &mut rng)?;
+            write_newline(&mut w, stack.len())?;
+        }
+
+        let name = element_name(&mut rng, opts);
+        write!(w, "<{}", name)?;
+        write_attributes(&mut w, &mut rng, opts)?;
+        remaining -= 1;
+
+        if stack.len() + 1 < opts.depth && r < 0.45 {
+            write!(w, ">")?;
+            stack.push(name);
+        } else if opts.text_bytes == 0 {
+            write!(w, "/>")?;
+        } else {
+            write!(w, ">")?;
+            write_escaped(&mut w, &random_text(&mut rng, TEXT_CHARS, opts.text_bytes))?;
+            write!(w, "</{}>", name)?;
+        }
+    }
+
+    while let Some(name) = stack.pop() {
+        write_newline(&mut w, stack.len())?;
+        write!(w, "</{}>", name)?;
+    }
+    writeln!(w)?;
+
+    w.flush()
+}
+
+fn element_name(rng: &mut Rng, opts: &Options) -> String {
+    let name = NAMES[rng.next_below(NAMES.len())];
+    if opts.namespaces > 0 && rng.next_f64() < 0.5 {
+        format!("n{}:{}", rng.next_below(opts.namespaces), name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn write_attributes<W: Write>(w: &mut W, rng: &mut Rng, opts: &Options) -> io::Result<()> {
+    for i in 0..opts.attrs {
+        write!(w, " a{}=\"", i)?;
+        write_escaped(w, &random_text(rng, TEXT_CHARS, 8))?;
+        write!(w, "\"")?;
+    }
+
+    Ok(())
+}
+
+fn write_extra<W: Write>(w: &mut W, rng: &mut Rng) -> io::Result<()> {
+    let text = random_text(rng, RAW_CHARS, 16);
+    match rng.next_below(3) {
+        0 => write!(w, "<!--{}-->", text),
+        1 => write!(w, "<![CDATA[{}]]>", text),
+        // A PI can't contain `?>`, so only letters are used.
+        _ => write!(w, "<?gen {}?>", text.replace(|c: char| !c.is_ascii_alphanumeric(), "")),
+    }
+}
+
+fn write_newline<W: Write>(w: &mut W, depth: usize) -> io::Result<()> {
+    write!(w, "\n{:1$}", "", depth * 2)
+}
+
+fn write_escaped<W: Write>(w: &mut W, text: &str) -> io::Result<()> {
+    for c in text.chars() {
+        match c {
+            '&' => write!(w, "&amp;")?,
+            '<' => write!(w, "&lt;")?,
+            '>' => write!(w, "&gt;")?,
+            '"' => write!(w, "&quot;")?,
+            '\'' => write!(w, "&apos;")?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn random_text(rng: &mut Rng, chars: &[u8], len: usize) -> String {
+    (0..len).map(|_| chars[rng.next_below(chars.len())] as char).collect()
+}
+
+// A xorshift64* generator, so the output depends only on the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // A zero state would produce only zeros.
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
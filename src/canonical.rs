@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::entity::DocumentEntities;
+use crate::{EventWriter, Result};
+
+const XML_NS: &[u8] = b"http://www.w3.org/XML/1998/namespace";
+
+/// Writes quick_xml events as Canonical XML 1.0.
+///
+/// The output follows [RFC 3076](https://www.ietf.org/rfc/rfc3076.txt) for a whole document:
+///
+/// - the XML declaration and DOCTYPE are removed
+/// - empty elements are written as start-end pairs
+/// - namespace declarations are written only where they change the in-scope bindings,
+///   with the default namespace first and the rest sorted by prefix
+/// - attributes are sorted by namespace URI and then by local name,
+///   with unqualified attributes first
+/// - text, CDATA and attribute values are re-escaped using the canonical character references,
+///   after expanding entities declared in the DOCTYPE
+/// - whitespace outside the root element is removed
+/// - line endings are normalized to `\n`
+///
+/// Comments are removed unless `with_comments` is set.
+///
+/// The DTD is not processed beyond entity declarations, so default attributes from `ATTLIST`
+/// declarations are not added and attributes declared as tokens are not normalized further.
+///
+/// The input is expected to be UTF-8. Names and text are not transcoded.
+pub struct CanonicalSerializer<W: Write> {
+    writer: W,
+    with_comments: bool,
+    // Namespace bindings rendered by each open element, including the inherited ones.
+    // The default namespace has an empty prefix.
+    scopes: Vec<BTreeMap<Vec<u8>, Vec<u8>>>,
+    after_root: bool,
+    entities: DocumentEntities,
+}
+
+impl<W: Write> CanonicalSerializer<W> {
+    /// Creates a new serializer that writes to the `writer` and removes comments.
+    pub fn new(writer: W) -> Self {
+        CanonicalSerializer {
+            writer,
+            with_comments: false,
+            scopes: Vec::new(),
+            after_root: false,
+            entities: DocumentEntities::default(),
+        }
+    }
+
+    /// Sets whether comments are kept, i.e. Canonical XML with comments is written.
+    pub fn with_comments(mut self, with_comments: bool) -> Self {
+        self.with_comments = with_comments;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a single event.
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        match *event {
+            Event::Start(ref e) => {
+                self.write_start(e)?;
+            }
+            Event::Empty(ref e) => {
+                self.write_start(e)?;
+                self.write_end(e.name())?;
+            }
+            Event::End(ref e) => {
+                self.write_end(e.name())?;
+            }
+            Event::Text(ref e) => {
+                if !self.scopes.is_empty() {
+                    let text = normalize_line_endings(e.escaped());
+                    let text = self.entities.unescape(&text)?.into_owned();
+                    write_escaped_text(&mut self.writer, &text)?;
+                }
+            }
+            Event::CData(ref e) => {
+                if !self.scopes.is_empty() {
+                    write_escaped_text(&mut self.writer, &normalize_line_endings(e))?;
+                }
+            }
+            Event::Comment(ref e) => {
+                if self.with_comments {
+                    self.write_node(b"<!--", &normalize_line_endings(e), b"-->")?;
+                }
+            }
+            Event::PI(ref e) => {
+                let pi = normalize_line_endings(e);
+                // The target and the data are separated by a single space.
+                let target_len = pi.iter().position(|c| c.is_ascii_whitespace()).unwrap_or(pi.len());
+                let (target, data) = pi.split_at(target_len);
+                let data = trim_start(data);
+
+                let mut pi = target.to_vec();
+                if !data.is_empty() {
+                    pi.push(b' ');
+                    pi.extend_from_slice(data);
+                }
+
+                self.write_node(b"<?", &pi, b"?>")?;
+            }
+            Event::DocType(ref e) => self.entities.declare(e),
+            Event::Decl(_) | Event::Eof => {}
+        }
+
+        Ok(())
+    }
+
+    fn write_start(&mut self, e: &BytesStart) -> Result<()> {
+        let parent = self.scopes.last().cloned().unwrap_or_default();
+        let mut scope = parent.clone();
+        let mut attrs = Vec::new();
+
+        for a in e.attributes() {
+            let a = a?;
+            let prefix = if a.key == b"xmlns" {
+                &b""[..]
+            } else if a.key.starts_with(b"xmlns:") {
+                &a.key[6..]
+            } else {
+                attrs.push((a.key.to_vec(), attribute_value(&a.value, &self.entities)?));
+                continue;
+            };
+
+            // The `xml` prefix is bound implicitly and is never declared.
+            if prefix != b"xml" {
+                scope.insert(prefix.to_vec(), attribute_value(&a.value, &self.entities)?);
+            }
+        }
+
+        let w = &mut self.writer;
+        w.write_all(b"<")?;
+        w.write_all(e.name())?;
+
+        // Only declarations that differ from the parent ones are written.
+        // `BTreeMap` keeps them sorted, with the default namespace first.
+        for (prefix, uri) in &scope {
+            let parent_uri = parent.get(prefix).map(Vec::as_slice).unwrap_or(b"");
+            if parent_uri == uri.as_slice() {
+                continue;
+            }
+
+            if prefix.is_empty() {
+                w.write_all(b" xmlns=\"")?;
+            } else {
+                w.write_all(b" xmlns:")?;
+                w.write_all(prefix)?;
+                w.write_all(b"=\"")?;
+            }
+            write_escaped_attribute(w, uri)?;
+            w.write_all(b"\"")?;
+        }
+
+        // Unprefixed attributes have no namespace, so they go first.
+        attrs.sort_by_cached_key(|(key, _)| {
+            match key.iter().position(|c| *c == b':') {
+                Some(idx) => {
+                    let prefix = &key[..idx];
+                    let uri = match scope.get(prefix) {
+                        Some(uri) => uri.clone(),
+                        None if prefix == b"xml" => XML_NS.to_vec(),
+                        None => prefix.to_vec(),
+                    };
+                    (uri, key[idx + 1..].to_vec())
+                }
+                None => (Vec::new(), key.clone()),
+            }
+        });
+
+        for (key, value) in &attrs {
+            w.write_all(b" ")?;
+            w.write_all(key)?;
+            w.write_all(b"=\"")?;
+            write_escaped_attribute(w, value)?;
+            w.write_all(b"\"")?;
+        }
+
+        w.write_all(b">")?;
+
+        self.scopes.push(scope);
+        Ok(())
+    }
+
+    fn write_end(&mut self, name: &[u8]) -> Result<()> {
+        self.writer.write_all(b"</")?;
+        self.writer.write_all(name)?;
+        self.writer.write_all(b">")?;
+
+        self.scopes.pop();
+        if self.scopes.is_empty() {
+            self.after_root = true;
+        }
+
+        Ok(())
+    }
+
+    // Nodes outside the root element are separated from it by a line break.
+    fn write_node(&mut self, before: &[u8], value: &[u8], after: &[u8]) -> Result<()> {
+        let is_outside = self.scopes.is_empty();
+        if is_outside && self.after_root {
+            self.writer.write_all(b"\n")?;
+        }
+
+        self.writer.write_all(before)?;
+        self.writer.write_all(value)?;
+        self.writer.write_all(after)?;
+
+        if is_outside && !self.after_root {
+            self.writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> EventWriter for CanonicalSerializer<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        CanonicalSerializer::write_event(self, event)
+    }
+}
+
+// Applies the attribute-value normalization of a non-validating parser:
+// literal whitespace becomes a space, while character references are kept.
+fn attribute_value(raw: &[u8], entities: &DocumentEntities) -> Result<Vec<u8>> {
+    let raw = normalize_line_endings(raw);
+    let raw: Vec<u8> = raw.iter().map(|c| if c.is_ascii_whitespace() { b' ' } else { *c }).collect();
+    let value = entities.unescape(&raw)?.into_owned();
+    Ok(value)
+}
+
+fn normalize_line_endings(text: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(text.len());
+    let mut iter = text.iter().peekable();
+    while let Some(&c) = iter.next() {
+        if c == b'\r' {
+            if iter.peek() == Some(&&b'\n') {
+                iter.next();
+            }
+            normalized.push(b'\n');
+        } else {
+            normalized.push(c);
+        }
+    }
+
+    normalized
+}
+
+fn trim_start(text: &[u8]) -> &[u8] {
+    let start = text.iter().position(|c| !c.is_ascii_whitespace()).unwrap_or(text.len());
+    &text[start..]
+}
+
+fn write_escaped_text(w: &mut dyn Write, text: &[u8]) -> Result<()> {
+    for &c in text {
+        match c {
+            b'&' => w.write_all(b"&amp;")?,
+            b'<' => w.write_all(b"&lt;")?,
+            b'>' => w.write_all(b"&gt;")?,
+            b'\r' => w.write_all(b"&#xD;")?,
+            _ => w.write_all(&[c])?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_escaped_attribute(w: &mut dyn Write, value: &[u8]) -> Result<()> {
+    for &c in value {
+        match c {
+            b'&' => w.write_all(b"&amp;")?,
+            b'<' => w.write_all(b"&lt;")?,
+            b'"' => w.write_all(b"&quot;")?,
+            b'\t' => w.write_all(b"&#x9;")?,
+            b'\n' => w.write_all(b"&#xA;")?,
+            b'\r' => w.write_all(b"&#xD;")?,
+            _ => w.write_all(&[c])?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use crate::write_canonical;
+
+    fn canonicalize(text: &str, with_comments: bool) -> String {
+        String::from_utf8(write_canonical(&mut Reader::from_str(text), Vec::new(), with_comments).unwrap()).unwrap()
+    }
+
+    // Examples from section 3 of the Canonical XML 1.0 specification.
+
+    const PIS_AND_COMMENTS: &str = concat!(
+        "<?xml version=\"1.0\"?>\n\n",
+        "<?xml-stylesheet   href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n\n",
+        "<!DOCTYPE doc SYSTEM \"doc.dtd\">\n\n",
+        "<doc>Hello, world!<!-- Comment 1 --></doc>\n\n",
+        "<?pi-without-data     ?>\n\n",
+        "<!-- Comment 2 -->\n\n",
+        "<!-- Comment 3 -->\n",
+    );
+
+    #[test]
+    fn removes_declaration_and_comments() {
+        let expected = concat!(
+            "<?xml-stylesheet href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n",
+            "<doc>Hello, world!</doc>\n",
+            "<?pi-without-data?>",
+        );
+        assert_eq!(canonicalize(PIS_AND_COMMENTS, false), expected);
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let expected = concat!(
+            "<?xml-stylesheet href=\"doc.xsl\"\n   type=\"text/xsl\"   ?>\n",
+            "<doc>Hello, world!<!-- Comment 1 --></doc>\n",
+            "<?pi-without-data?>\n",
+            "<!-- Comment 2 -->\n",
+            "<!-- Comment 3 -->",
+        );
+        assert_eq!(canonicalize(PIS_AND_COMMENTS, true), expected);
+    }
+
+    #[test]
+    fn sorts_attributes_and_namespaces() {
+        let text = concat!(
+            "<!DOCTYPE doc [<!ATTLIST e9 attr CDATA \"default\">]>\n",
+            "<doc>\n",
+            "   <e1   />\n",
+            "   <e2   ></e2>\n",
+            "   <e3   name=\"elem3\"   id=\"elem3\"   />\n",
+            "   <e4   name=\"elem4\"   id=\"elem4\"   ></e4>\n",
+            "   <e5 a:attr=\"out\" b:attr=\"sorted\" attr2=\"all\" attr=\"I'm\"\n",
+            "      xmlns:b=\"http://www.ietf.org\"\n",
+            "      xmlns:a=\"http://www.w3.org\"\n",
+            "      xmlns=\"http://example.org\"/>\n",
+            "   <e6 xmlns=\"\" xmlns:a=\"http://www.w3.org\">\n",
+            "      <e7 xmlns=\"http://www.ietf.org\">\n",
+            "         <e8 xmlns=\"\" xmlns:a=\"http://www.w3.org\">\n",
+            "            <e9 xmlns=\"\" xmlns:a=\"http://www.ietf.org\"/>\n",
+            "         </e8>\n",
+            "      </e7>\n",
+            "   </e6>\n",
+            "</doc>\n",
+        );
+
+        // The specification adds `attr="default"` to `e9`, but attributes declared in the DTD are not applied.
+        let expected = concat!(
+            "<doc>\n",
+            "   <e1></e1>\n",
+            "   <e2></e2>\n",
+            "   <e3 id=\"elem3\" name=\"elem3\"></e3>\n",
+            "   <e4 id=\"elem4\" name=\"elem4\"></e4>\n",
+            "   <e5 xmlns=\"http://example.org\" xmlns:a=\"http://www.w3.org\" xmlns:b=\"http://www.ietf.org\" ",
+            "attr=\"I'm\" attr2=\"all\" b:attr=\"sorted\" a:attr=\"out\"></e5>\n",
+            "   <e6 xmlns:a=\"http://www.w3.org\">\n",
+            "      <e7 xmlns=\"http://www.ietf.org\">\n",
+            "         <e8 xmlns=\"\">\n",
+            "            <e9 xmlns:a=\"http://www.ietf.org\"></e9>\n",
+            "         </e8>\n",
+            "      </e7>\n",
+            "   </e6>\n",
+            "</doc>",
+        );
+        assert_eq!(canonicalize(text, false), expected);
+    }
+
+    #[test]
+    fn replaces_character_references() {
+        let text = concat!(
+            "<doc>\n",
+            "   <text>First line&#x0d;&#10;Second line</text>\n",
+            "   <value>&#x32;</value>\n",
+            "   <compute><![CDATA[value>\"0\" && value<\"10\" ?\"valid\":\"error\"]]></compute>\n",
+            "   <compute expr='value>\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"'>valid</compute>\n",
+            "   <norm attr=' &apos;   &#x20;&#13;&#xa;&#9;   &apos; '/>\n",
+            "</doc>",
+        );
+        let expected = concat!(
+            "<doc>\n",
+            "   <text>First line&#xD;\nSecond line</text>\n",
+            "   <value>2</value>\n",
+            "   <compute>value&gt;\"0\" &amp;&amp; value&lt;\"10\" ?\"valid\":\"error\"</compute>\n",
+            "   <compute expr=\"value>&quot;0&quot; &amp;&amp; value&lt;&quot;10&quot; ",
+            "?&quot;valid&quot;:&quot;error&quot;\">valid</compute>\n",
+            "   <norm attr=\" '    &#xD;&#xA;&#x9;   ' \"></norm>\n",
+            "</doc>",
+        );
+        assert_eq!(canonicalize(text, false), expected);
+    }
+
+    #[test]
+    fn expands_declared_entities() {
+        let text = "<!DOCTYPE doc [<!ENTITY ent \"text\">]>\r\n<doc attr=\"&ent;\">&ent;\r\n</doc>";
+        assert_eq!(canonicalize(text, false), "<doc attr=\"text\">text\n</doc>");
+    }
+}
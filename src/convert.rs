@@ -0,0 +1,406 @@
+use serde_json::{Map, Value};
+
+use crate::node::{NsElement, NsNode};
+use crate::{Error, Result};
+
+/// A convention of mapping elements onto JSON values, used by `xml_to_json_with_convention`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum JsonConvention {
+    /// Each element is an object:
+    ///
+    /// - an attribute becomes a `@` + its local name key,
+    ///   or `@{URI}name` for an attribute in a namespace
+    /// - the text becomes a `$` key
+    /// - a namespace URI becomes an `@xmlns` key, when it differs from the parent's one,
+    ///   so an empty string resets it like `xmlns=""`
+    /// - a child element becomes a key with its local name and an array of objects
+    ///   when there are several siblings with this name
+    ///
+    /// ```text
+    /// <item id="1"><name>A</name><tag>x</tag><tag>y</tag></item>
+    /// {"item":{"@id":"1","name":{"$":"A"},"tag":[{"$":"x"},{"$":"y"}]}}
+    /// ```
+    BadgerFish,
+    /// Like `BadgerFish`, but attributes and namespaces are dropped, an element without child elements
+    /// becomes a string or `null` when it's empty, and the text of an element with child elements is dropped.
+    ///
+    /// This is more compact, but can't be converted back.
+    Parker,
+}
+
+/// Converts the tree into JSON using the `BadgerFish` convention, see `xml_to_json_with_convention`.
+///
+/// Use `json_to_xml` to convert it back.
+pub fn xml_to_json(root: &NsElement) -> Value {
+    xml_to_json_with_convention(root, JsonConvention::BadgerFish)
+}
+
+/// Converts the tree into an object with a single key, the name of the `root`.
+///
+/// Text and CDATA of an element are concatenated, so the position of text between child elements is lost.
+/// Whitespace-only text is skipped. Comments are skipped as well.
+/// The order of child elements with different names is lost too, since an object is unordered.
+pub fn xml_to_json_with_convention(root: &NsElement, convention: JsonConvention) -> Value {
+    let mut object = Map::new();
+    let value = match convention {
+        JsonConvention::BadgerFish => badgerfish_element(root, None),
+        JsonConvention::Parker => parker_element(root),
+    };
+    object.insert(root.name.clone(), value);
+    Value::Object(object)
+}
+
+fn badgerfish_element(node: &NsElement, parent_ns: Option<&str>) -> Value {
+    let mut object = Map::new();
+
+    if node.namespace.as_deref() != parent_ns {
+        object.insert("@xmlns".to_string(), Value::String(node.namespace.clone().unwrap_or_default()));
+    }
+
+    for ((ns, name), value) in node.attributes.iter() {
+        let key = match ns {
+            Some(ns) => format!("@{{{}}}{}", ns, name),
+            None => format!("@{}", name),
+        };
+        object.insert(key, Value::String(value.to_string()));
+    }
+
+    if let Some(text) = text_content(node) {
+        object.insert("$".to_string(), Value::String(text));
+    }
+
+    for child in node.child_nodes() {
+        let value = badgerfish_element(child, node.namespace.as_deref());
+        insert_child(&mut object, &child.name, value);
+    }
+
+    Value::Object(object)
+}
+
+fn parker_element(node: &NsElement) -> Value {
+    if node.child_nodes().next().is_none() {
+        return match text_content(node) {
+            Some(text) => Value::String(text),
+            None => Value::Null,
+        };
+    }
+
+    let mut object = Map::new();
+    for child in node.child_nodes() {
+        insert_child(&mut object, &child.name, parker_element(child));
+    }
+
+    Value::Object(object)
+}
+
+// Turns the value into an array on the second sibling with the same name.
+fn insert_child(object: &mut Map<String, Value>, name: &str, value: Value) {
+    match object.get_mut(name) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(prev) => {
+            let first = prev.take();
+            *prev = Value::Array(vec![first, value]);
+        }
+        None => {
+            object.insert(name.to_string(), value);
+        }
+    }
+}
+
+fn text_content(node: &NsElement) -> Option<String> {
+    let mut text = String::new();
+    for child in &node.children {
+        match *child {
+            NsNode::Text(ref s) | NsNode::CData(ref s) if !s.trim().is_empty() => text.push_str(s),
+            _ => {}
+        }
+    }
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Rebuilds a tree from JSON in the `BadgerFish` convention, like the one returned by `xml_to_json`.
+///
+/// The `value` must be an object with a single key, the name of the root element.
+/// Hand-written JSON is accepted as well:
+///
+/// - an element can be a string, which becomes its text, or `null` for an empty one
+/// - an element without `@xmlns` inherits the namespace of its parent
+/// - `@xmlns` can be an object like `{"$":"URI","p":"URI"}`, like in the original BadgerFish
+/// - `@xmlns:p` keys declare prefixes, so elements and attributes can be named like `p:name`,
+///   while an unbound prefix is kept as a part of the name
+/// - numbers and booleans are allowed wherever strings are
+///
+/// The text becomes the first child, followed by child elements in the order of their keys.
+///
+/// Returns `Error::JsonStructure` for anything else, like nested arrays or a non-string attribute.
+pub fn json_to_xml(value: &Value) -> Result<NsElement> {
+    match value {
+        Value::Object(object) if object.len() == 1 => {
+            let (name, value) = object.iter().next().unwrap();
+            json_element(name, value, None, &mut Vec::new(), &pointer("", name))
+        }
+        _ => Err(structure_error("", "expected an object with a single root element")),
+    }
+}
+
+// `prefixes` are (prefix, URI) pairs declared by the ancestors, the innermost last.
+fn json_element(
+    key: &str,
+    value: &Value,
+    parent_ns: Option<&str>,
+    prefixes: &mut Vec<(String, String)>,
+    path: &str,
+) -> Result<NsElement> {
+    if key.is_empty() || key.starts_with('@') || key == "$" {
+        return Err(structure_error(path, "not an element name"));
+    }
+
+    let object = match value {
+        Value::Object(object) => object,
+        Value::Array(_) => return Err(structure_error(path, "nested arrays are not allowed")),
+        _ => {
+            let (namespace, name) = resolve_element_name(key, parent_ns.map(str::to_string), prefixes);
+            let mut node = NsElement { name, namespace, ..NsElement::default() };
+            push_text(&mut node, value, path)?;
+            return Ok(node);
+        }
+    };
+
+    // Declarations are applied first, since they affect the name of the element itself.
+    let len = prefixes.len();
+    let res = declare_namespaces(object, parent_ns, prefixes, path)
+        .and_then(|default_ns| json_element_content(key, object, default_ns, prefixes, path));
+    prefixes.truncate(len);
+    res
+}
+
+fn json_element_content(
+    key: &str,
+    object: &Map<String, Value>,
+    default_ns: Option<String>,
+    prefixes: &mut Vec<(String, String)>,
+    path: &str,
+) -> Result<NsElement> {
+    let (namespace, name) = resolve_element_name(key, default_ns.clone(), prefixes);
+    let mut node = NsElement { name, namespace, ..NsElement::default() };
+    let mut children = Vec::new();
+
+    for (key, value) in object {
+        let path = pointer(path, key);
+        if key == "@xmlns" || key.starts_with("@xmlns:") {
+            continue;
+        }
+
+        if key == "$" {
+            push_text(&mut node, value, &path)?;
+        } else if let Some(name) = key.strip_prefix('@') {
+            let (ns, local) = resolve_attribute_name(name, prefixes);
+            node.attributes.insert(ns, local, scalar_text(value, &path)?);
+        } else if let Value::Array(items) = value {
+            for (i, item) in items.iter().enumerate() {
+                let path = pointer(&path, &i.to_string());
+                children.push(NsNode::Element(json_element(key, item, default_ns.as_deref(), prefixes, &path)?));
+            }
+        } else {
+            children.push(NsNode::Element(json_element(key, value, default_ns.as_deref(), prefixes, &path)?));
+        }
+    }
+
+    node.children.append(&mut children);
+    Ok(node)
+}
+
+// Returns the default namespace of the element.
+fn declare_namespaces(
+    object: &Map<String, Value>,
+    parent_ns: Option<&str>,
+    prefixes: &mut Vec<(String, String)>,
+    path: &str,
+) -> Result<Option<String>> {
+    let mut default_ns = parent_ns.map(str::to_string);
+
+    for (key, value) in object {
+        let path = pointer(path, key);
+        if key == "@xmlns" {
+            match value {
+                Value::Object(declarations) => {
+                    for (prefix, uri) in declarations {
+                        let uri = scalar_text(uri, &pointer(&path, prefix))?;
+                        if prefix == "$" {
+                            default_ns = Some(uri);
+                        } else {
+                            prefixes.push((prefix.clone(), uri));
+                        }
+                    }
+                }
+                _ => default_ns = Some(scalar_text(value, &path)?),
+            }
+        } else if let Some(prefix) = key.strip_prefix("@xmlns:") {
+            prefixes.push((prefix.to_string(), scalar_text(value, &path)?));
+        }
+    }
+
+    // Like `xmlns=""`.
+    Ok(default_ns.filter(|ns| !ns.is_empty()))
+}
+
+fn resolve_element_name(
+    key: &str,
+    default_ns: Option<String>,
+    prefixes: &[(String, String)],
+) -> (Option<String>, String) {
+    match resolve_prefix(key, prefixes) {
+        Some((uri, local)) => (Some(uri.to_string()), local.to_string()),
+        None => (default_ns, key.to_string()),
+    }
+}
+
+// Accepts both `{URI}name`, written by `xml_to_json`, and `prefix:name`.
+fn resolve_attribute_name(name: &str, prefixes: &[(String, String)]) -> (Option<String>, String) {
+    if let Some((uri, local)) = name.strip_prefix('{').and_then(|s| s.split_once('}')) {
+        return (Some(uri.to_string()), local.to_string());
+    }
+
+    match resolve_prefix(name, prefixes) {
+        Some((uri, local)) => (Some(uri.to_string()), local.to_string()),
+        None => (None, name.to_string()),
+    }
+}
+
+fn resolve_prefix<'a>(name: &'a str, prefixes: &'a [(String, String)]) -> Option<(&'a str, &'a str)> {
+    let (prefix, local) = name.split_once(':')?;
+    let (_, uri) = prefixes.iter().rev().find(|(p, _)| p == prefix)?;
+    Some((uri, local))
+}
+
+fn push_text(node: &mut NsElement, value: &Value, path: &str) -> Result<()> {
+    let text = scalar_text(value, path)?;
+    if !text.is_empty() {
+        node.children.push(NsNode::Text(text));
+    }
+
+    Ok(())
+}
+
+// `null` is an empty string.
+fn scalar_text(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Null => Ok(String::new()),
+        _ => Err(structure_error(path, "expected a string")),
+    }
+}
+
+// Appends the `key` to a JSON pointer.
+fn pointer(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn structure_error(path: &str, message: &str) -> Error {
+    Error::JsonStructure { path: path.to_string(), message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+    use serde_json::json;
+
+    use super::*;
+    use crate::node::DomBuilder;
+    use crate::QuickXmlReader;
+
+    fn parse(text: &str) -> NsElement {
+        DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_str(text))).unwrap()
+    }
+
+    const DOCUMENT: &str = concat!(
+        "<item xmlns=\"urn:a\" xmlns:x=\"urn:x\" id=\"1\" x:flag=\"yes\">\n",
+        "  <name>A &amp; B</name>\n",
+        "  <tag>x</tag><tag>y</tag>\n",
+        "  <empty/>\n",
+        "  <other xmlns=\"\"><![CDATA[raw]]></other>\n",
+        "</item>",
+    );
+
+    #[test]
+    fn converts_with_badgerfish() {
+        let expected = json!({"item": {
+            "@xmlns": "urn:a",
+            "@id": "1",
+            "@{urn:x}flag": "yes",
+            "name": {"$": "A & B"},
+            "tag": [{"$": "x"}, {"$": "y"}],
+            "empty": {},
+            "other": {"@xmlns": "", "$": "raw"},
+        }});
+        assert_eq!(xml_to_json(&parse(DOCUMENT)), expected);
+    }
+
+    #[test]
+    fn converts_with_parker() {
+        let expected = json!({"item": {"name": "A & B", "tag": ["x", "y"], "empty": null, "other": "raw"}});
+        assert_eq!(xml_to_json_with_convention(&parse(DOCUMENT), JsonConvention::Parker), expected);
+    }
+
+    #[test]
+    fn round_trips_json() {
+        let value = xml_to_json(&parse(DOCUMENT));
+        assert_eq!(xml_to_json(&json_to_xml(&value).unwrap()), value);
+    }
+
+    #[test]
+    fn round_trips_fixtures() {
+        let fixtures = [
+            "data/small.xml",
+            "data/medium.xml",
+            "data/namespaces.xml",
+            "data/prefixes.xml",
+            "data/lang.xml",
+            "data/complex.svg",
+        ];
+
+        for path in &fixtures {
+            let json = xml_to_json(&parse(&std::fs::read_to_string(path).unwrap()));
+            let node = json_to_xml(&json).unwrap();
+            let json2 = xml_to_json(&node);
+            assert_eq!(json2, json, "{}", path);
+            assert_eq!(json_to_xml(&json2).unwrap(), node, "{}", path);
+        }
+    }
+
+    #[test]
+    fn accepts_hand_written_json() {
+        let value = json!({"p:root": {
+            "@xmlns:p": "urn:p",
+            "@n": 1,
+            "title": "Hello",
+            "none": null,
+            "p:flag": true,
+        }});
+        let expected = json!({"root": {
+            "@xmlns": "urn:p",
+            "@n": "1",
+            "flag": {"$": "true"},
+            "none": {"@xmlns": ""},
+            "title": {"@xmlns": "", "$": "Hello"},
+        }});
+        assert_eq!(xml_to_json(&json_to_xml(&value).unwrap()), expected);
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        for value in &[json!([]), json!({"a": 1, "b": 2}), json!({"a": [[{}]]}), json!({"a": {"@x": {}}})] {
+            match json_to_xml(value) {
+                Err(Error::JsonStructure { .. }) => {}
+                r => panic!("unexpected result for {}: {:?}", value, r),
+            }
+        }
+    }
+}
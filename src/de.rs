@@ -0,0 +1,459 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::BufRead;
+
+use quick_xml::Reader;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::{parse_dom, Element, Error, Node, Result};
+
+/// Deserializes a value from the document in the `reader`.
+///
+/// The root element is mapped onto `T` like this:
+///
+/// - an attribute becomes a field named `@` + its qualified name, so use `#[serde(rename = "@id")]`
+/// - a child element becomes a field named after its local name
+/// - repeated child elements become a `Vec` field
+/// - the text of an element becomes a `$text` field, or the value itself for primitives and enums
+///
+/// Missing `Option` fields are `None`. Unknown attributes and elements are ignored
+/// unless the type has `#[serde(deny_unknown_fields)]`.
+///
+/// The whole document is parsed into an `Element` tree first.
+pub fn from_reader<T: DeserializeOwned, R: BufRead>(reader: R) -> Result<T> {
+    let root = parse_dom(&mut Reader::from_reader(reader))?;
+    T::deserialize(ElementDeserializer(&root))
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
+// Parses a primitive from the text.
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => ($(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            match self.0.trim().parse() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&self.0), &visitor)),
+            }
+        }
+    )*)
+}
+
+// An attribute value or a text content.
+struct TextDeserializer<'a>(Cow<'a, str>);
+
+impl<'de, 'a> de::Deserializer<'de> for TextDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.0.into_owned())
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(de::Error::invalid_value(de::Unexpected::Str(&self.0), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Only unit variants can be written as a text.
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        visitor.visit_enum(self.0.trim().to_string().into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ElementDeserializer<'a>(&'a Element);
+
+impl<'a> ElementDeserializer<'a> {
+    fn text(&self) -> TextDeserializer<'a> {
+        TextDeserializer(text_content(self.0))
+    }
+}
+
+// Forwards primitives to the text of the element.
+macro_rules! deserialize_text {
+    ($($method:ident)*) => ($(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            self.text().$method(visitor)
+        }
+    )*)
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ElementDeserializer<'a> {
+    type Error = Error;
+
+    // An element with only text is a string, otherwise a map.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let has_elements = self.0.children.iter().any(|n| matches!(n, Node::Element(_)));
+        if self.0.attributes.is_empty() && !has_elements {
+            self.text().deserialize_any(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_text! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // A single element is a sequence of one item, so `Vec` fields work with one child too.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(ElementSeq(vec![self.0].into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(ElementMap::new(self.0))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str],
+                                           visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        self.text().deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.text().deserialize_identifier(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+}
+
+// Sibling elements with the same name.
+struct ElementsDeserializer<'a>(Vec<&'a Element>);
+
+// Everything except sequences is read from the first element.
+macro_rules! deserialize_first {
+    ($($method:ident($($arg:ident: $ty:ty),*))*) => ($(
+        fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+            ElementDeserializer(self.0[0]).$method($($arg,)* visitor)
+        }
+    )*)
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ElementsDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(ElementSeq(self.0.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    deserialize_first! {
+        deserialize_any() deserialize_bool() deserialize_i8() deserialize_i16() deserialize_i32()
+        deserialize_i64() deserialize_u8() deserialize_u16() deserialize_u32() deserialize_u64()
+        deserialize_f32() deserialize_f64() deserialize_char() deserialize_str() deserialize_string()
+        deserialize_bytes() deserialize_byte_buf() deserialize_option() deserialize_unit()
+        deserialize_unit_struct(name: &'static str)
+        deserialize_newtype_struct(name: &'static str)
+        deserialize_map()
+        deserialize_struct(name: &'static str, fields: &'static [&'static str])
+        deserialize_enum(name: &'static str, variants: &'static [&'static str])
+        deserialize_identifier() deserialize_ignored_any()
+    }
+}
+
+struct ElementSeq<'a>(std::vec::IntoIter<&'a Element>);
+
+impl<'de, 'a> SeqAccess<'de> for ElementSeq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.0.next() {
+            Some(e) => seed.deserialize(ElementDeserializer(e)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+enum Value<'a> {
+    Text(Cow<'a, str>),
+    Elements(Vec<&'a Element>),
+}
+
+// Attributes, then the text, then child elements grouped by name in the document order.
+struct ElementMap<'a> {
+    entries: std::vec::IntoIter<(String, Value<'a>)>,
+    value: Option<Value<'a>>,
+}
+
+impl<'a> ElementMap<'a> {
+    fn new(element: &'a Element) -> Self {
+        let mut entries: Vec<(String, Value)> = element.attributes.iter()
+            .map(|(k, v)| (format!("@{}", k), Value::Text(Cow::Borrowed(v.as_str()))))
+            .collect();
+
+        let text = text_content(element);
+        if !text.trim().is_empty() {
+            entries.push(("$text".to_string(), Value::Text(text)));
+        }
+
+        let attrs_len = entries.len();
+        for n in &element.children {
+            if let Node::Element(ref e) = *n {
+                let group = entries[attrs_len..].iter_mut().find(|(k, _)| *k == e.name);
+                match group {
+                    Some((_, Value::Elements(ref mut items))) => items.push(e),
+                    _ => entries.push((e.name.clone(), Value::Elements(vec![e]))),
+                }
+            }
+        }
+
+        ElementMap { entries: entries.into_iter(), value: None }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for ElementMap<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(Value::Text(text)) => seed.deserialize(TextDeserializer(text)),
+            Some(Value::Elements(elements)) => seed.deserialize(ElementsDeserializer(elements)),
+            None => Err(de::Error::custom("a value is requested before a key")),
+        }
+    }
+}
+
+// Direct text children only, so the text of nested elements isn't mixed in.
+fn text_content(element: &Element) -> Cow<'_, str> {
+    let mut texts = element.children.iter().filter_map(|n| match *n {
+        Node::Text(ref text) => Some(text.as_str()),
+        _ => None,
+    });
+
+    match (texts.next(), texts.next()) {
+        (None, _) => Cow::Borrowed(""),
+        (Some(text), None) => Cow::Borrowed(text),
+        (Some(first), Some(second)) => {
+            let mut text = format!("{}{}", first, second);
+            texts.for_each(|t| text.push_str(t));
+            Cow::Owned(text)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum Status {
+        Active,
+        Retired,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Name {
+        #[serde(rename = "@lang")]
+        lang: String,
+        #[serde(rename = "$text")]
+        text: String,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Item {
+        #[serde(rename = "@id")]
+        id: u32,
+        name: Name,
+        #[serde(default, rename = "tag")]
+        tags: Vec<String>,
+        price: Option<f64>,
+        status: Status,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Catalog {
+        #[serde(rename = "item")]
+        items: Vec<Item>,
+    }
+
+    fn from_str<T: DeserializeOwned>(text: &str) -> Result<T> {
+        from_reader(text.as_bytes())
+    }
+
+    #[test]
+    fn reads_attributes_and_text() {
+        let name: Name = from_str("<name lang=\"en\"> Widget </name>").unwrap();
+        assert_eq!(name, Name { lang: "en".to_string(), text: " Widget ".to_string() });
+    }
+
+    #[test]
+    fn reads_nested_structs() {
+        let text = "\
+<catalog>
+  <item id=\"1\">
+    <name lang=\"en\">Widget</name>
+    <tag>new</tag>
+    <tag>sale</tag>
+    <price>9.5</price>
+    <status>Active</status>
+  </item>
+  <item id=\"2\">
+    <name lang=\"de\">Gadget</name>
+    <status>Retired</status>
+  </item>
+</catalog>";
+
+        let catalog: Catalog = from_str(text).unwrap();
+        assert_eq!(catalog, Catalog {
+            items: vec![
+                Item {
+                    id: 1,
+                    name: Name { lang: "en".to_string(), text: "Widget".to_string() },
+                    tags: vec!["new".to_string(), "sale".to_string()],
+                    price: Some(9.5),
+                    status: Status::Active,
+                },
+                Item {
+                    id: 2,
+                    name: Name { lang: "de".to_string(), text: "Gadget".to_string() },
+                    tags: Vec::new(),
+                    price: None,
+                    status: Status::Retired,
+                },
+            ],
+        });
+    }
+
+    #[test]
+    fn reads_single_child_as_vec() {
+        let text = "<catalog><item id=\"7\"><name lang=\"en\">One</name><status>Active</status></item></catalog>";
+        let catalog: Catalog = from_str(text).unwrap();
+        assert_eq!(catalog.items.len(), 1);
+        assert_eq!(catalog.items[0].id, 7);
+    }
+
+    #[test]
+    fn reads_enums_and_primitives() {
+        assert_eq!(from_str::<Status>("<status> Retired </status>").unwrap(), Status::Retired);
+        assert!(from_str::<bool>("<flag>1</flag>").unwrap());
+        assert!(!from_str::<bool>("<flag>false</flag>").unwrap());
+        assert_eq!(from_str::<i32>("<n> -42 </n>").unwrap(), -42);
+        assert!(matches!(from_str::<Status>("<status>Unknown</status>"), Err(Error::Deserialize(_))));
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Point {
+            #[serde(rename = "@x")]
+            x: i32,
+        }
+
+        let point: Point = from_str("<point x=\"1\" y=\"2\"><z>3</z></point>").unwrap();
+        assert_eq!(point, Point { x: 1 });
+    }
+
+    #[test]
+    fn denies_unknown_fields() {
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Point {
+            #[serde(rename = "@x")]
+            x: i32,
+        }
+
+        assert!(from_str::<Point>("<point x=\"1\"/>").is_ok());
+        match from_str::<Point>("<point x=\"1\" y=\"2\"/>") {
+            Err(Error::Deserialize(message)) => assert!(message.contains("unknown field `@y`"), "{}", message),
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert!(matches!(from_str::<Point>("<point x=\"1\"><z/></point>"), Err(Error::Deserialize(_))));
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_fields() {
+        assert!(matches!(from_str::<Name>("<name>Widget</name>"), Err(Error::Deserialize(_))));
+        assert!(matches!(from_str::<i32>("<n>x</n>"), Err(Error::Deserialize(_))));
+    }
+}
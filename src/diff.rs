@@ -0,0 +1,363 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::DumpEvent;
+
+/// A difference between two event streams.
+///
+/// `location` is an XPath-like path of the event, like `/root[1]/item[2]/text()`.
+/// Locations of deleted and changed events are taken from the first stream
+/// and locations of inserted ones from the second.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DiffItem {
+    /// An event is present only in the second stream.
+    Inserted {
+        location: String,
+        event: DumpEvent,
+    },
+    /// An event is present only in the first stream.
+    Deleted {
+        location: String,
+        event: DumpEvent,
+    },
+    /// An event of the same kind has a different value, like a different text or attributes.
+    Changed {
+        location: String,
+        old: DumpEvent,
+        new: DumpEvent,
+    },
+}
+
+impl fmt::Display for DiffItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DiffItem::Inserted { ref location, ref event } => write!(f, "+ {}\n{}", location, event),
+            DiffItem::Deleted { ref location, ref event } => write!(f, "- {}\n{}", location, event),
+            DiffItem::Changed { ref location, ref old, ref new } => write!(f, "~ {}\n{}\n{}", location, old, new),
+        }
+    }
+}
+
+/// Options of `diff_events_with_options`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct DiffOptions {
+    /// Compare elements only by local names, ignoring their namespaces and namespace declarations.
+    ///
+    /// By default, elements are compared by expanded names, i.e. namespace URIs and local names.
+    /// Other attributes are always compared by qualified names, as written in the document.
+    pub ignore_namespaces: bool,
+    /// Skip text that contains only whitespace and compare other text without leading and trailing whitespace.
+    ///
+    /// Useful for comparing a pretty-printed document with a compact one.
+    pub ignore_whitespace: bool,
+    /// Compare attributes of an element as a set, ignoring their order.
+    pub ignore_attribute_order: bool,
+}
+
+/// Compares two event streams using the default options.
+///
+/// See `diff_events_with_options` for details.
+pub fn diff_events(a: &[DumpEvent], b: &[DumpEvent]) -> Vec<DiffItem> {
+    diff_events_with_options(a, b, &DiffOptions::default())
+}
+
+/// Compares two event streams.
+///
+/// Returns the shortest list of insertions and deletions that turns `a` into `b`,
+/// where a deletion immediately followed by an insertion of an event of the same kind
+/// is reported as a change. An empty list means that the streams are equal.
+///
+/// The comparison takes `O(a.len() * b.len())` time and memory.
+pub fn diff_events_with_options(a: &[DumpEvent], b: &[DumpEvent], opts: &DiffOptions) -> Vec<DiffItem> {
+    let a = skip_whitespace(a, opts);
+    let b = skip_whitespace(b, opts);
+    let (a, b) = (a.as_ref(), b.as_ref());
+
+    let a_keys: Vec<_> = a.iter().map(|e| comparison_key(e, opts)).collect();
+    let b_keys: Vec<_> = b.iter().map(|e| comparison_key(e, opts)).collect();
+
+    // The longest common subsequence of suffixes.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a_keys[i] == b_keys[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let a_locations = locations(a);
+    let b_locations = locations(b);
+
+    let mut items = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a_keys[i] == b_keys[j] {
+            flush_changes(a, b, &a_locations, &b_locations, &mut deleted, &mut inserted, &mut items);
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            deleted.push(i);
+            i += 1;
+        } else {
+            inserted.push(j);
+            j += 1;
+        }
+    }
+    flush_changes(a, b, &a_locations, &b_locations, &mut deleted, &mut inserted, &mut items);
+
+    items
+}
+
+// Pairs deletions with insertions of the same kind, in order.
+fn flush_changes(a: &[DumpEvent], b: &[DumpEvent], a_locations: &[String], b_locations: &[String],
+                 deleted: &mut Vec<usize>, inserted: &mut Vec<usize>, items: &mut Vec<DiffItem>) {
+    let mut inserted_iter = inserted.drain(..).peekable();
+
+    for i in deleted.drain(..) {
+        if let Some(&j) = inserted_iter.peek() {
+            if is_same_kind(&a[i], &b[j]) {
+                inserted_iter.next();
+                items.push(DiffItem::Changed {
+                    location: a_locations[i].clone(),
+                    old: a[i].clone(),
+                    new: b[j].clone(),
+                });
+                continue;
+            }
+        }
+
+        items.push(DiffItem::Deleted { location: a_locations[i].clone(), event: a[i].clone() });
+    }
+
+    for j in inserted_iter {
+        items.push(DiffItem::Inserted { location: b_locations[j].clone(), event: b[j].clone() });
+    }
+}
+
+// Whitespace-only text is removed before the comparison, since it would still be reported as inserted or deleted.
+fn skip_whitespace<'a>(events: &'a [DumpEvent], opts: &DiffOptions) -> Cow<'a, [DumpEvent]> {
+    if !opts.ignore_whitespace {
+        return Cow::Borrowed(events);
+    }
+
+    let is_whitespace = |e: &DumpEvent| match *e {
+        DumpEvent::Text(ref text) => text.trim().is_empty(),
+        _ => false,
+    };
+
+    Cow::Owned(events.iter().filter(|e| !is_whitespace(e)).cloned().collect())
+}
+
+// Element prefixes are never compared, since elements are identified by expanded names.
+fn comparison_key(event: &DumpEvent, opts: &DiffOptions) -> DumpEvent {
+    let strip_ns = |ns: Option<String>| if opts.ignore_namespaces { None } else { ns };
+    let strip_xmlns = |attrs: Vec<(String, String)>| -> Vec<(String, String)> {
+        if !opts.ignore_namespaces {
+            return attrs;
+        }
+
+        attrs.into_iter().filter(|(k, _)| k != "xmlns" && !k.starts_with("xmlns:")).collect()
+    };
+    let normalize_attrs = |attrs: Vec<(String, String)>| {
+        let mut attrs = strip_xmlns(attrs);
+        if opts.ignore_attribute_order {
+            attrs.sort();
+        }
+        attrs
+    };
+
+    match event.clone() {
+        DumpEvent::StartElement { name, ns, attrs, .. } => {
+            DumpEvent::StartElement { name, ns: strip_ns(ns), prefix: None, attrs: normalize_attrs(attrs) }
+        }
+        DumpEvent::EmptyElement { name, ns, attrs, .. } => {
+            DumpEvent::EmptyElement { name, ns: strip_ns(ns), prefix: None, attrs: normalize_attrs(attrs) }
+        }
+        DumpEvent::EndElement { name, ns, .. } => DumpEvent::EndElement { name, ns: strip_ns(ns), prefix: None },
+        DumpEvent::Text(text) if opts.ignore_whitespace => DumpEvent::Text(text.trim().to_string()),
+        event => event,
+    }
+}
+
+// Elements are of the same kind only when they have the same name,
+// otherwise a replaced element would be reported as a changed one.
+fn is_same_kind(a: &DumpEvent, b: &DumpEvent) -> bool {
+    match (a, b) {
+        (DumpEvent::StartElement { name: n1, .. }, DumpEvent::StartElement { name: n2, .. })
+        | (DumpEvent::EmptyElement { name: n1, .. }, DumpEvent::EmptyElement { name: n2, .. })
+        | (DumpEvent::EndElement { name: n1, .. }, DumpEvent::EndElement { name: n2, .. }) => n1 == n2,
+        (DumpEvent::Text(_), DumpEvent::Text(_))
+        | (DumpEvent::CData(_), DumpEvent::CData(_))
+        | (DumpEvent::Comment(_), DumpEvent::Comment(_))
+        | (DumpEvent::PI { .. }, DumpEvent::PI { .. })
+        | (DumpEvent::DocType(_), DumpEvent::DocType(_))
+        | (DumpEvent::Decl { .. }, DumpEvent::Decl { .. }) => true,
+        _ => false,
+    }
+}
+
+// Returns an XPath-like location of each event.
+//
+// Elements are numbered among the siblings with the same name, starting from 1.
+// An End event has the location of its element.
+fn locations(events: &[DumpEvent]) -> Vec<String> {
+    // Paths of the open elements and the sibling counters of their children.
+    let mut stack: Vec<(String, HashMap<String, usize>)> = vec![(String::new(), HashMap::new())];
+    let mut locations = Vec::with_capacity(events.len());
+
+    for event in events {
+        let parent = stack.last().map(|(path, _)| path.clone()).unwrap_or_default();
+
+        let location = match *event {
+            DumpEvent::StartElement { ref name, .. } | DumpEvent::EmptyElement { ref name, .. } => {
+                let counters = &mut stack.last_mut().unwrap().1;
+                let index = counters.entry(name.clone()).or_insert(0);
+                *index += 1;
+
+                let path = format!("{}/{}[{}]", parent, name, index);
+                if let DumpEvent::StartElement { .. } = *event {
+                    stack.push((path.clone(), HashMap::new()));
+                }
+                path
+            }
+            DumpEvent::EndElement { .. } => {
+                // An unmatched End event belongs to the document.
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+                parent
+            }
+            DumpEvent::Text(_) | DumpEvent::CData(_) => format!("{}/text()", parent),
+            DumpEvent::Comment(_) => format!("{}/comment()", parent),
+            DumpEvent::PI { .. } => format!("{}/processing-instruction()", parent),
+            DumpEvent::DocType(_) | DumpEvent::Decl { .. } => "/".to_string(),
+        };
+
+        locations.push(if location.is_empty() { "/".to_string() } else { location });
+    }
+
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{QuickXmlReader, XmlEventRecorder};
+
+    // Empty text between adjacent tags is skipped, since quick_xml reports it as a separate event.
+    fn events(text: &str) -> Vec<DumpEvent> {
+        let mut recorder = XmlEventRecorder::new(QuickXmlReader::new(Reader::from_str(text)));
+        recorder.record_all().unwrap();
+        recorder.into_events().into_iter().filter(|e| *e != DumpEvent::Text(String::new())).collect()
+    }
+
+    fn diff(a: &str, b: &str, opts: &DiffOptions) -> Vec<DiffItem> {
+        diff_events_with_options(&events(a), &events(b), opts)
+    }
+
+    fn start(name: &str, attrs: &[(&str, &str)]) -> DumpEvent {
+        DumpEvent::StartElement {
+            name: name.to_string(),
+            ns: None,
+            prefix: None,
+            attrs: attrs.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn empty(name: &str) -> DumpEvent {
+        DumpEvent::EmptyElement { name: name.to_string(), ns: None, prefix: None, attrs: Vec::new() }
+    }
+
+    #[test]
+    fn equal_streams() {
+        let text = "<root><a x=\"1\">text</a><!-- c --></root>";
+        assert_eq!(diff(text, text, &DiffOptions::default()), Vec::new());
+    }
+
+    #[test]
+    fn reports_insertion() {
+        let items = diff("<root><a/></root>", "<root><a/><b/><a/></root>", &DiffOptions::default());
+        assert_eq!(items, vec![
+            DiffItem::Inserted { location: "/root[1]/b[1]".to_string(), event: empty("b") },
+            DiffItem::Inserted { location: "/root[1]/a[2]".to_string(), event: empty("a") },
+        ]);
+    }
+
+    #[test]
+    fn reports_deletion() {
+        let items = diff("<root><a/><b/></root>", "<root><a/></root>", &DiffOptions::default());
+        assert_eq!(items, vec![DiffItem::Deleted { location: "/root[1]/b[1]".to_string(), event: empty("b") }]);
+    }
+
+    #[test]
+    fn reports_change() {
+        let items = diff("<root><a x=\"1\">old</a></root>", "<root><a x=\"2\">new</a></root>", &DiffOptions::default());
+        assert_eq!(items, vec![
+            DiffItem::Changed {
+                location: "/root[1]/a[1]".to_string(),
+                old: start("a", &[("x", "1")]),
+                new: start("a", &[("x", "2")]),
+            },
+            DiffItem::Changed {
+                location: "/root[1]/a[1]/text()".to_string(),
+                old: DumpEvent::Text("old".to_string()),
+                new: DumpEvent::Text("new".to_string()),
+            },
+        ]);
+    }
+
+    #[test]
+    fn replaced_element_is_not_a_change() {
+        let items = diff("<root><a/></root>", "<root><b/></root>", &DiffOptions::default());
+        assert_eq!(items, vec![
+            DiffItem::Deleted { location: "/root[1]/a[1]".to_string(), event: empty("a") },
+            DiffItem::Inserted { location: "/root[1]/b[1]".to_string(), event: empty("b") },
+        ]);
+    }
+
+    #[test]
+    fn ignore_namespaces() {
+        let a = "<root xmlns=\"urn:a\"><item/></root>";
+        let b = "<p:root xmlns:p=\"urn:b\"><p:item/></p:root>";
+        assert!(!diff(a, b, &DiffOptions::default()).is_empty());
+
+        let opts = DiffOptions { ignore_namespaces: true, ..DiffOptions::default() };
+        assert_eq!(diff(a, b, &opts), Vec::new());
+    }
+
+    #[test]
+    fn prefixes_are_not_compared() {
+        let a = "<root xmlns=\"urn:a\"/>";
+        let b = "<p:root xmlns:p=\"urn:a\"/>";
+        let opts = DiffOptions { ignore_namespaces: true, ..DiffOptions::default() };
+        assert_eq!(diff(a, b, &opts), Vec::new());
+    }
+
+    #[test]
+    fn ignore_whitespace() {
+        let a = "<root>\n  <a> text </a>\n</root>";
+        let b = "<root><a>text</a></root>";
+        assert!(!diff(a, b, &DiffOptions::default()).is_empty());
+
+        let opts = DiffOptions { ignore_whitespace: true, ..DiffOptions::default() };
+        assert_eq!(diff(a, b, &opts), Vec::new());
+    }
+
+    #[test]
+    fn ignore_attribute_order() {
+        let a = "<root x=\"1\" y=\"2\"/>";
+        let b = "<root y=\"2\" x=\"1\"/>";
+        assert_eq!(diff(a, b, &DiffOptions::default()).len(), 1);
+
+        let opts = DiffOptions { ignore_attribute_order: true, ..DiffOptions::default() };
+        assert_eq!(diff(a, b, &opts), Vec::new());
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::BufRead;
+
+use quick_xml::Reader;
+
+use crate::{walk_events, EventVisitor, Result};
+
+/// An owned element of a document tree.
+///
+/// `name` is a local name and `ns` is a resolved namespace URI.
+/// Attributes are stored in document order, with qualified names.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Element {
+    pub ns: Option<String>,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+/// A child of an `Element`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Node {
+    Element(Element),
+    /// An unescaped text. Adjacent text and CDATA are merged into a single node.
+    Text(String),
+    Comment(String),
+    /// A processing instruction, including its target.
+    PI(String),
+}
+
+impl Element {
+    /// Returns all descendant elements with the local `name`, in document order.
+    ///
+    /// The element itself is not included.
+    pub fn find<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        Descendants::new(self).filter(move |e| e.name == name)
+    }
+
+    /// Returns the value of the attribute with the qualified `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+// An element of an owned tree, so `Element` and `NsElement` share `Descendants`.
+pub(crate) trait TreeElement: Sized {
+    type Child;
+
+    fn child_slice(&self) -> &[Self::Child];
+
+    fn as_element(child: &Self::Child) -> Option<&Self>;
+}
+
+impl TreeElement for Element {
+    type Child = Node;
+
+    fn child_slice(&self) -> &[Node] {
+        &self.children
+    }
+
+    fn as_element(child: &Node) -> Option<&Self> {
+        match *child {
+            Node::Element(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// A depth-first iterator over descendant elements.
+pub(crate) struct Descendants<'a, T: TreeElement> {
+    stack: Vec<std::slice::Iter<'a, T::Child>>,
+}
+
+impl<'a, T: TreeElement> Descendants<'a, T> {
+    pub(crate) fn new(element: &'a T) -> Self {
+        Descendants { stack: vec![element.child_slice().iter()] }
+    }
+}
+
+impl<'a, T: TreeElement + 'a> Iterator for Descendants<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(child) => {
+                    if let Some(e) = T::as_element(child) {
+                        self.stack.push(e.child_slice().iter());
+                        return Some(e);
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Parses the whole document from the `reader` into an owned tree and returns its root element.
+///
+/// Comments and processing instructions are kept only inside the root element.
+/// Returns an error when the document has no root element.
+/// Only the first root is returned when there are several of them, see `parse_dom_fragment`.
+pub fn parse_dom<R: BufRead>(reader: &mut Reader<R>) -> Result<Element> {
+    match parse_dom_fragment(reader)?.into_iter().next() {
+        Some(root) => Ok(root),
+        None => Err(quick_xml::Error::UnexpectedEof("root element".to_string()).into()),
+    }
+}
+
+/// Parses a fragment, i.e. a sequence of root elements, like a log of records, and returns all of them.
+///
+/// Comments and processing instructions are kept only inside the root elements.
+/// Unlike `parse_dom`, an input without root elements is not an error.
+pub fn parse_dom_fragment<R: BufRead>(reader: &mut Reader<R>) -> Result<Vec<Element>> {
+    let mut builder = TreeBuilder::default();
+    walk_events(reader, &mut builder)?;
+    Ok(builder.roots)
+}
+
+// Partially built elements are kept on a stack and attached to their parents on End.
+#[derive(Default)]
+struct TreeBuilder {
+    stack: Vec<Element>,
+    roots: Vec<Element>,
+}
+
+impl TreeBuilder {
+    fn push_node(&mut self, node: Node) {
+        // Nodes outside the root element are dropped.
+        let parent = match self.stack.last_mut() {
+            Some(parent) => parent,
+            None => return,
+        };
+
+        match (parent.children.last_mut(), node) {
+            (_, Node::Text(ref text)) if text.is_empty() => {}
+            (Some(Node::Text(last)), Node::Text(text)) => last.push_str(&text),
+            (_, node) => parent.children.push(node),
+        }
+    }
+
+    fn close(&mut self, element: Element) {
+        if self.stack.is_empty() {
+            // quick_xml allows several roots.
+            self.roots.push(element);
+        } else {
+            self.push_node(Node::Element(element));
+        }
+    }
+}
+
+fn new_element(ns: Option<&str>, local_name: &str, attributes: &[(String, String)]) -> Element {
+    Element {
+        ns: ns.map(str::to_string),
+        name: local_name.to_string(),
+        attributes: attributes.to_vec(),
+        children: Vec::new(),
+    }
+}
+
+impl EventVisitor for TreeBuilder {
+    fn start(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.stack.push(new_element(ns, local_name, attributes));
+        Ok(())
+    }
+
+    fn empty(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.close(new_element(ns, local_name, attributes));
+        Ok(())
+    }
+
+    fn end(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str, _depth: usize) -> Result<()> {
+        // `walk_events` reports unmatched End events itself.
+        if let Some(element) = self.stack.pop() {
+            self.close(element);
+        }
+
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_node(Node::Text(text.to_string()));
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_node(Node::Text(text.to_string()));
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_node(Node::Comment(text.to_string()));
+        Ok(())
+    }
+
+    fn pi(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_node(Node::PI(text.to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::str;
+
+use quick_xml::Reader;
+
+use crate::event::Event;
+use crate::{skip_element, BaseUriStack, DumpEvent, ElementPath, EntityLimits, Error, EventVisitor, JsonLinesVisitor,
+            JsonVisitor, NdjsonVisitor, PathSelector, PrintVisitor, QuickXmlReader, Result, ValueVisitor,
+            WhitespaceMode, XmlSource};
+
+/// An output format of `dump_events`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum DumpFormat {
+    /// An indented tree of events, as written by `PrintVisitor`. The default.
+    #[default]
+    Text,
+    /// A JSON tree, as written by `JsonVisitor`.
+    Json,
+    /// One JSON object per event, as written by `JsonLinesVisitor`.
+    JsonLines,
+    /// One flat JSON object per event with inlined attributes, as written by `NdjsonVisitor`.
+    Ndjson,
+    /// Text content or `DumpOptions::attribute` of top-level elements, one per line, as written by `ValueVisitor`.
+    ///
+    /// Usually combined with `DumpOptions::select`, so each matched element is a line.
+    Values,
+}
+
+/// Options of `dump_events`.
+#[derive(Clone, Default, Debug)]
+pub struct DumpOptions {
+    pub format: DumpFormat,
+    pub whitespace: WhitespaceMode,
+    /// A string written once per indentation level by `DumpFormat::Text`. `None` uses two spaces.
+    pub indent: Option<String>,
+    /// Whether `DumpFormat::Text` starts each line with the path of its element, like `/catalog[1]/book[2]`.
+    pub paths: bool,
+    /// A number of element levels to dump. `None` dumps all of them.
+    ///
+    /// Deeper elements are skipped together with their content,
+    /// while the content of the deepest dumped elements, like text, is still dumped.
+    pub max_depth: Option<usize>,
+    /// Local names of elements to dump. Empty dumps all of them.
+    ///
+    /// Restricts Start, Empty and End events to the matching elements and all other events,
+    /// like text, comments and processing instructions, to the ones directly inside of them,
+    /// so the declaration and DOCTYPE are skipped too.
+    /// Depth is still counted for all elements.
+    pub only: Vec<String>,
+    /// A namespace URI of elements to dump. `None` dumps all of them.
+    ///
+    /// Matched against the resolved namespace, not the prefix.
+    /// Applied together with `only`, just like it, so events outside of the matching elements,
+    /// including the declaration, comments and processing instructions, are skipped.
+    pub ns: Option<String>,
+    /// A URI of the document. `None` leaves `xml:base` attributes as is.
+    ///
+    /// When set, `xml:base` values are dumped resolved against it and the bases of their ancestors,
+    /// as tracked by `BaseUriStack`.
+    pub base_uri: Option<String>,
+    /// Custom entities, in addition to the ones declared in the document.
+    /// See `QuickXmlReader::set_entities`.
+    pub entities: HashMap<Vec<u8>, Vec<u8>>,
+    /// Limits of entity expansion. See `EntityLimits`.
+    pub entity_limits: EntityLimits,
+    /// A path of elements to dump together with their content. `None` dumps the whole document.
+    ///
+    /// Everything outside of the matched elements, including the declaration, is skipped.
+    /// Applied together with `only` and `ns`. Paths with indexes or an attribute step are rejected,
+    /// see `PathSelector::check_streamable`.
+    pub select: Option<PathSelector>,
+    /// A qualified name of an attribute written by `DumpFormat::Values` instead of text content.
+    pub attribute: Option<String>,
+    /// Whether the input is a fragment, i.e. a sequence of root elements, like a log of records.
+    ///
+    /// Otherwise, another root element is reported as `Error::MultipleRoots`.
+    pub fragment: bool,
+}
+
+/// Parses the document from the `reader` using quick_xml and dumps its events to the `out`.
+///
+/// In-memory documents can be passed as `xml.as_bytes()`, since `&[u8]` implements `BufRead`.
+pub fn dump_events<R: BufRead, W: Write>(reader: R, out: &mut W, opts: &DumpOptions) -> Result<()> {
+    if let Some(ref selector) = opts.select {
+        selector.check_streamable()?;
+    }
+
+    let mut reader = QuickXmlReader::new(Reader::from_reader(reader));
+    reader.set_whitespace_mode(opts.whitespace);
+    reader.set_entities(opts.entities.clone());
+    reader.set_entity_limits(opts.entity_limits);
+
+    match opts.format {
+        DumpFormat::Text => {
+            let mut visitor = PrintVisitor::new(out).paths(opts.paths);
+            if let Some(ref indent) = opts.indent {
+                visitor = visitor.indent(indent);
+            }
+
+            dump_to_visitor(&mut reader, opts, &mut visitor)
+        }
+        DumpFormat::Json => dump_to_visitor(&mut reader, opts, &mut JsonVisitor::new(out)),
+        DumpFormat::JsonLines => dump_to_visitor(&mut reader, opts, &mut JsonLinesVisitor::new(out)),
+        DumpFormat::Ndjson => dump_to_visitor(&mut reader, opts, &mut NdjsonVisitor::new(out)),
+        DumpFormat::Values => {
+            let mut visitor = ValueVisitor::new(out);
+            if let Some(ref name) = opts.attribute {
+                visitor = visitor.attribute(name);
+            }
+
+            dump_to_visitor(&mut reader, opts, &mut visitor)
+        }
+    }
+}
+
+fn dump_to_visitor<R: BufRead>(source: &mut QuickXmlReader<R>, opts: &DumpOptions,
+                               visitor: &mut impl EventVisitor) -> Result<()> {
+    let mut path = ElementPath::new();
+    let mut bases = opts.base_uri.as_ref().map(|uri| BaseUriStack::new().document_uri(uri));
+    let mut selection = Selection::default();
+    // Whether each open element is selected, so its text is selected too.
+    let mut selected = Vec::new();
+    let mut has_root = false;
+
+    loop {
+        let e = source.next_event()?;
+
+        if opts.max_depth.is_some_and(|max_depth| path.depth() >= max_depth) {
+            match e {
+                Event::Start { .. } => {
+                    skip_element(source)?;
+                    continue;
+                }
+                Event::Empty { .. } => continue,
+                _ => {}
+            }
+        }
+
+        let mut event = match DumpEvent::from_event(&e)? {
+            Some(event) => event,
+            None => break,
+        };
+
+        match event {
+            DumpEvent::EndElement { ref name, .. } if path.depth() == 0 => {
+                return Err(Error::UnexpectedEnd { name: name.clone(), position: source.inner().buffer_position() });
+            }
+            DumpEvent::StartElement { ref name, .. } | DumpEvent::EmptyElement { ref name, .. }
+                if path.depth() == 0 =>
+            {
+                if has_root && !opts.fragment {
+                    return Err(Error::MultipleRoots { name: name.clone(), position: source.inner().buffer_position() });
+                }
+                has_root = true;
+            }
+            _ => {}
+        }
+
+        // quick_xml reports empty text between adjacent tags and whitespace outside the root element,
+        // while xml-rs doesn't, so both are skipped to get the same dump from both backends.
+        if let DumpEvent::Text(ref text) = event {
+            if text.is_empty() || (path.depth() == 0 && text.trim().is_empty()) {
+                continue;
+            }
+        }
+
+        let mut depth = path.depth();
+        path.push_event(&e);
+
+        if let Some(ref mut bases) = bases {
+            bases.update(&e);
+            resolve_base_attribute(&mut event, bases);
+        }
+
+        let is_selected = match e {
+            Event::Start { ref ns, ref name, .. } => {
+                let is_selected = is_selected(opts, ns.as_deref(), name);
+                selected.push(is_selected);
+                is_selected
+            }
+            Event::Empty { ref ns, ref name, .. } => is_selected(opts, ns.as_deref(), name),
+            Event::End { .. } => selected.pop().unwrap_or(true),
+            _ => match selected.last() {
+                Some(is_selected) => *is_selected,
+                None => opts.only.is_empty() && opts.ns.is_none(),
+            },
+        };
+
+        let is_selected = match opts.select {
+            Some(ref selector) => selection.update(selector, &e)? && is_selected,
+            None => is_selected,
+        };
+
+        if is_selected {
+            event.visit(&mut depth, visitor)?;
+        }
+    }
+
+    // An Empty element is kept in the path until the next event, so the depth is used instead.
+    match path.depth() {
+        0 => Ok(()),
+        depth => Err(Error::Unclosed { name: path.names()[depth - 1].clone() }),
+    }
+}
+
+// Namespaces and local names of the open elements, matched against `DumpOptions::select`.
+#[derive(Default)]
+struct Selection {
+    open: Vec<(Option<String>, String)>,
+    // A number of open elements when the matched one was opened.
+    matched: Option<usize>,
+}
+
+impl Selection {
+    // Returns whether the event is inside of a matched element.
+    fn update(&mut self, selector: &PathSelector, event: &Event) -> Result<bool> {
+        match *event {
+            Event::Start { ref ns, ref name, .. } | Event::Empty { ref ns, ref name, .. } => {
+                let ns = match *ns {
+                    Some(ref ns) => Some(str::from_utf8(ns)?.to_string()),
+                    None => None,
+                };
+                self.open.push((ns, str::from_utf8(name)?.to_string()));
+
+                if self.matched.is_none() {
+                    let path: Vec<_> = self.open.iter().map(|(ns, name)| (ns.as_deref(), name.as_str())).collect();
+                    if selector.matches(&path) {
+                        self.matched = Some(self.open.len());
+                    }
+                }
+
+                let is_matched = self.matched.is_some();
+                if let Event::Empty { .. } = *event {
+                    self.close();
+                }
+
+                Ok(is_matched)
+            }
+            Event::End { .. } => {
+                let is_matched = self.matched.is_some();
+                self.close();
+                Ok(is_matched)
+            }
+            _ => Ok(self.matched.is_some()),
+        }
+    }
+
+    fn close(&mut self) {
+        if self.matched == Some(self.open.len()) {
+            self.matched = None;
+        }
+        self.open.pop();
+    }
+}
+
+fn resolve_base_attribute(event: &mut DumpEvent, bases: &BaseUriStack) {
+    if let DumpEvent::StartElement { ref mut attrs, .. } | DumpEvent::EmptyElement { ref mut attrs, .. } = *event {
+        if let Some((_, value)) = attrs.iter_mut().find(|(key, _)| key == "xml:base") {
+            if let Some(base) = bases.base() {
+                *value = base.to_string();
+            }
+        }
+    }
+}
+
+fn is_selected(opts: &DumpOptions, ns: Option<&[u8]>, name: &[u8]) -> bool {
+    let is_name_selected = opts.only.is_empty() || opts.only.iter().any(|n| n.as_bytes() == name);
+    let is_ns_selected = match opts.ns {
+        Some(ref uri) => ns == Some(uri.as_bytes()),
+        None => true,
+    };
+
+    is_name_selected && is_ns_selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(text: &str, opts: &DumpOptions) -> Result<String> {
+        let mut out = Vec::new();
+        dump_events(text.as_bytes(), &mut out, opts)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn dumps_inline_document() {
+        let text = "<?xml version=\"1.0\"?>\n<a x=\"1\">\n  <b>text</b>\n</a>\n";
+        let expected = "Declaration\n  version=\"1.0\"\nStart: a\n    Attribute: x=\"1\"\n    Text: \"\\n  \"\n  \
+                        Start: b\n      Text: \"text\"\n  End: b\n    Text: \"\\n\"\nEnd: a\n";
+        assert_eq!(dump(text, &DumpOptions::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn dumps_with_custom_indent() {
+        let opts = DumpOptions { indent: Some("\t".to_string()), ..DumpOptions::default() };
+        assert_eq!(dump("<a><b/></a>", &opts).unwrap(), "Start: a\n\tEmpty: b\nEnd: a\n");
+    }
+
+    #[test]
+    fn dumps_json_lines() {
+        let opts = DumpOptions { format: DumpFormat::JsonLines, ..DumpOptions::default() };
+        assert_eq!(dump("<a/>", &opts).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn skips_elements_below_max_depth() {
+        let mut text = String::new();
+        for i in 1..=10 {
+            text.push_str(&format!("<l{}>", i));
+        }
+        for i in (1..=10).rev() {
+            text.push_str(&format!("</l{}>", i));
+        }
+
+        let opts = DumpOptions { max_depth: Some(2), ..DumpOptions::default() };
+        assert_eq!(dump(&text, &opts).unwrap(), "Start: l1
+  Start: l2
+  End: l2
+End: l1
+");
+    }
+
+    #[test]
+    fn resumes_after_skipped_siblings() {
+        let text = "<a><b><c><d/></c>text</b><b><c/></b><e/></a>";
+        let opts = DumpOptions { max_depth: Some(2), ..DumpOptions::default() };
+        let expected = "Start: a
+  Start: b
+      Text: \"text\"
+  End: b
+  Start: b
+  End: b
+  Empty: e
+End: a
+";
+        assert_eq!(dump(text, &opts).unwrap(), expected);
+    }
+
+    #[test]
+    fn dumps_only_matching_elements() {
+        let text = "<root><group><item>a</item><other><item/></other></group><item>b</item></root>";
+        let opts = DumpOptions { only: vec!["item".to_string()], ..DumpOptions::default() };
+        let expected = "    Start: item\n        Text: \"a\"\n    End: item\n      Empty: item\n  Start: item\n      \
+                        Text: \"b\"\n  End: item\n";
+        assert_eq!(dump(text, &opts).unwrap(), expected);
+    }
+
+    #[test]
+    fn dumps_union_of_names() {
+        let text = "<root><a/><b/><c/></root>";
+        let opts = DumpOptions { only: vec!["a".to_string(), "c".to_string()], ..DumpOptions::default() };
+        assert_eq!(dump(text, &opts).unwrap(), "  Empty: a\n  Empty: c\n");
+    }
+
+    #[test]
+    fn reports_extra_end_tag() {
+        // `dump_events` checks end names, so quick_xml would report the extra tag first.
+        let mut reader = Reader::from_str("<a></a></b>");
+        reader.check_end_names(false);
+
+        let mut out = Vec::new();
+        let mut source = QuickXmlReader::new(reader);
+        let res = dump_to_visitor(&mut source, &DumpOptions::default(), &mut PrintVisitor::new(&mut out));
+        match res {
+            Err(Error::UnexpectedEnd { ref name, position }) => {
+                assert_eq!(name, "b");
+                assert_eq!(position, 11);
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn dumps_only_matching_namespace() {
+        let text = concat!(
+            "<root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\">",
+            "<a:title>first</a:title><b:title>second</b:title>",
+            "</root>",
+        );
+        let opts = DumpOptions { ns: Some("urn:b".to_string()), ..DumpOptions::default() };
+        let expected = concat!(
+            "  Start: title (prefix: b, ns: urn:b)\n",
+            "      Text: \"second\"\n",
+            "  End: title (prefix: b, ns: urn:b)\n",
+        );
+        assert_eq!(dump(text, &opts).unwrap(), expected);
+    }
+
+    #[test]
+    fn combines_namespace_with_names() {
+        let text = "<root xmlns=\"urn:a\"><title/><name/><other xmlns=\"urn:b\"><title/></other></root>";
+        let opts = DumpOptions {
+            only: vec!["title".to_string()],
+            ns: Some("urn:a".to_string()),
+            ..DumpOptions::default()
+        };
+        assert_eq!(dump(text, &opts).unwrap(), "  Empty: title (ns: urn:a)\n");
+    }
+
+    #[test]
+    fn dumps_from_cursor() {
+        let text = "<root xmlns=\"urn:a\"><p:item xmlns:p=\"urn:p\">text</p:item><item/></root>";
+        let mut out = Vec::new();
+        dump_events(std::io::Cursor::new(text.to_string().into_bytes()), &mut out, &DumpOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), dump(text, &DumpOptions::default()).unwrap());
+    }
+}
@@ -0,0 +1,530 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::str;
+
+use quick_xml::events::BytesText;
+
+use crate::{Error, Result};
+
+/// A character encoding of raw document bytes.
+///
+/// Only encodings that can be decoded without extra dependencies are supported.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+    /// ISO-8859-1, where each byte is a code point.
+    Latin1,
+    /// ISO-8859-5, the Cyrillic one.
+    Iso8859_5,
+}
+
+impl Encoding {
+    /// Returns a canonical name of the encoding.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Iso8859_5 => "ISO-8859-5",
+        }
+    }
+
+    /// Returns an encoding for a label from an XML declaration, like `utf-8` or `latin1`.
+    ///
+    /// Labels are case-insensitive. Unsupported encodings return `None`.
+    pub fn for_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            // Without a BOM, UTF-16 is big-endian.
+            "utf-16" | "utf-16be" => Some(Encoding::Utf16Be),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            // The same goes for UTF-32.
+            "utf-32" | "utf-32be" => Some(Encoding::Utf32Be),
+            "utf-32le" => Some(Encoding::Utf32Le),
+            // ASCII is a subset of both.
+            "us-ascii" | "ascii" => Some(Encoding::Utf8),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" => Some(Encoding::Latin1),
+            "iso-8859-5" | "iso8859-5" | "iso_8859-5" | "cyrillic" => Some(Encoding::Iso8859_5),
+            _ => None,
+        }
+    }
+
+    /// Decodes the `bytes` to a string.
+    ///
+    /// Invalid sequences are reported as `Error::Decode`.
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>> {
+        let error = |position| Error::Decode { encoding: self, position };
+
+        match self {
+            Encoding::Utf8 => match str::from_utf8(bytes) {
+                Ok(s) => Ok(Cow::Borrowed(s)),
+                Err(e) => Err(error(e.valid_up_to())),
+            },
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(error(bytes.len() - 1));
+                }
+
+                let units = bytes.chunks(2).map(|pair| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+
+                let mut s = String::with_capacity(bytes.len() / 2);
+                let mut position = 0;
+                for c in char::decode_utf16(units) {
+                    let c = c.map_err(|_| error(position))?;
+                    position += c.len_utf16() * 2;
+                    s.push(c);
+                }
+
+                Ok(Cow::Owned(s))
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                if !bytes.len().is_multiple_of(4) {
+                    return Err(error(bytes.len() - bytes.len() % 4));
+                }
+
+                let mut s = String::with_capacity(bytes.len() / 4);
+                for (i, unit) in bytes.chunks(4).enumerate() {
+                    let c = char::from_u32(self.utf32_unit(unit)).ok_or_else(|| error(i * 4))?;
+                    s.push(c);
+                }
+
+                Ok(Cow::Owned(s))
+            }
+            Encoding::Latin1 | Encoding::Iso8859_5 => {
+                if bytes.is_ascii() {
+                    // ASCII is valid UTF-8, so there is nothing to convert.
+                    Ok(Cow::Borrowed(str::from_utf8(bytes)?))
+                } else {
+                    Ok(Cow::Owned(bytes.iter().map(|b| self.single_byte_char(*b)).collect()))
+                }
+            }
+        }
+    }
+
+    fn utf32_unit(self, unit: &[u8]) -> u32 {
+        let unit = [unit[0], unit[1], unit[2], unit[3]];
+        match self {
+            Encoding::Utf32Le => u32::from_le_bytes(unit),
+            _ => u32::from_be_bytes(unit),
+        }
+    }
+
+    // Both single-byte encodings are ASCII-compatible and each byte maps to a single code point.
+    fn single_byte_char(self, b: u8) -> char {
+        match (self, b) {
+            (Encoding::Iso8859_5, 0xf0) => '\u{2116}',
+            (Encoding::Iso8859_5, 0xfd) => '\u{a7}',
+            (Encoding::Iso8859_5, 0xa1..=0xff) if b != 0xad => {
+                char::from_u32(0x360 + u32::from(b)).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+            _ => char::from(b),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Decodes raw text or attribute value `bytes` and replaces entity and character references.
+///
+/// Works like `unescape_and_decode` of quick_xml events, but doesn't require a `Reader`.
+/// The `bytes` are decoded first, so references are found in UTF-16 text as well.
+pub fn unescape(bytes: &[u8], encoding: Encoding) -> Result<Cow<'_, str>> {
+    let decoded = encoding.decode(bytes)?;
+
+    // quick_xml unescapes bytes, so a UTF-8 input stays UTF-8.
+    let unescaped = match BytesText::from_escaped(decoded.as_bytes()).unescaped()? {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(unescaped) => Some(unescaped),
+    };
+
+    let unescaped = match unescaped {
+        Some(unescaped) => unescaped,
+        None => return Ok(decoded),
+    };
+
+    match String::from_utf8(unescaped) {
+        Ok(s) => Ok(Cow::Owned(s)),
+        Err(e) => Err(e.utf8_error().into()),
+    }
+}
+
+// Large enough for a BOM and a typical XML declaration.
+const SNIFF_LEN: usize = 1024;
+
+/// A reader that converts a document in any supported encoding to UTF-8.
+///
+/// The encoding is detected on construction: a BOM takes precedence,
+/// then the `encoding` of the XML declaration, and UTF-8 is the default.
+/// UTF-16 and UTF-32 without a BOM are recognized by a declaration, since it starts with `<?`.
+/// The BOM itself is removed, and the `encoding` of the declaration is replaced with `UTF-8`,
+/// so the output still declares its actual encoding when it's written out or parsed again.
+/// The original one is available with `declared_label`.
+///
+/// Gzip-compressed input is recognized by its magic bytes and rejected with `Error::Compressed`.
+/// `open_input` decompresses it beforehand when the `gzip` feature is enabled.
+///
+/// Invalid sequences are replaced with U+FFFD instead of failing the whole document.
+/// Documents in unsupported encodings, like Shift_JIS, are decoded as UTF-8 the same way,
+/// so their ASCII parts are still readable.
+pub struct DecodingReader<R: BufRead> {
+    inner: R,
+    encoding: Encoding,
+    label: Option<String>,
+    // An encoding detected by a BOM or the byte layout rather than the declaration.
+    detected: Option<Encoding>,
+    // Raw bytes that end with an incomplete sequence.
+    pending: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    // Whether the declaration is yet to be rewritten, see `replace_declared_encoding`.
+    rewrite_declaration: bool,
+}
+
+impl<R: BufRead> DecodingReader<R> {
+    /// Creates a new reader, reading the beginning of the document to detect its encoding.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut pending = Vec::new();
+        let mut eof = false;
+        while pending.len() < SNIFF_LEN {
+            let buf = inner.fill_buf()?;
+            if buf.is_empty() {
+                eof = true;
+                break;
+            }
+
+            let len = buf.len().min(SNIFF_LEN - pending.len());
+            pending.extend_from_slice(&buf[..len]);
+            inner.consume(len);
+        }
+
+        if pending.starts_with(&[0x1f, 0x8b]) {
+            return Err(Error::Compressed);
+        }
+
+        // The UTF-32LE BOM starts with the UTF-16LE one, so it's checked first.
+        let (encoding, bom_len) = match pending.as_slice() {
+            [0xff, 0xfe, 0, 0, ..] => (Some(Encoding::Utf32Le), 4),
+            [0, 0, 0xfe, 0xff, ..] => (Some(Encoding::Utf32Be), 4),
+            [0xef, 0xbb, 0xbf, ..] => (Some(Encoding::Utf8), 3),
+            [0xff, 0xfe, ..] => (Some(Encoding::Utf16Le), 2),
+            [0xfe, 0xff, ..] => (Some(Encoding::Utf16Be), 2),
+            _ => (None, 0),
+        };
+        pending.drain(..bom_len);
+
+        let label = declared_encoding(&pending);
+        let unmarked = match pending.as_slice() {
+            [b'<', 0, 0, 0, b'?', 0, 0, 0, ..] => Some(Encoding::Utf32Le),
+            [0, 0, 0, b'<', 0, 0, 0, b'?', ..] => Some(Encoding::Utf32Be),
+            [b'<', 0, b'?', 0, ..] => Some(Encoding::Utf16Le),
+            [0, b'<', 0, b'?', ..] => Some(Encoding::Utf16Be),
+            _ => None,
+        };
+
+        let rewrite_declaration = label.as_deref()
+            .is_some_and(|label| Encoding::for_label(label) != Some(Encoding::Utf8));
+        let detected = encoding.or(unmarked);
+        let encoding = detected
+            .or_else(|| label.as_deref().and_then(Encoding::for_label))
+            .unwrap_or(Encoding::Utf8);
+
+        Ok(DecodingReader {
+            inner,
+            encoding,
+            label,
+            detected,
+            pending,
+            decoded: Vec::new(),
+            pos: 0,
+            eof,
+            rewrite_declaration,
+        })
+    }
+
+    /// Returns the encoding used for decoding.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the `encoding` of the XML declaration, even when it's unsupported.
+    pub fn declared_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Checks that the `encoding` of the XML declaration agrees with a BOM or the byte layout.
+    ///
+    /// A conflict, like a UTF-16 BOM in a document that declares UTF-8, is reported
+    /// as `Error::EncodingMismatch`. The document is still decoded in the detected encoding,
+    /// so the caller decides whether it's a warning or an error.
+    /// Without a declaration or a BOM, there is nothing to check.
+    pub fn check_declaration(&self) -> Result<()> {
+        let (detected, label) = match (self.detected, self.label.as_deref()) {
+            (Some(detected), Some(label)) => (detected, label),
+            _ => return Ok(()),
+        };
+
+        // `UTF-16` and `UTF-32` don't specify the byte order, which is up to the BOM.
+        let is_compatible = match label.trim().to_ascii_lowercase().as_str() {
+            "utf-16" => matches!(detected, Encoding::Utf16Le | Encoding::Utf16Be),
+            "utf-32" => matches!(detected, Encoding::Utf32Le | Encoding::Utf32Be),
+            _ => Encoding::for_label(label) == Some(detected),
+        };
+
+        if is_compatible {
+            Ok(())
+        } else {
+            Err(Error::EncodingMismatch { detected, declared: label.to_string() })
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Decodes all complete sequences of `pending`, leaving an incomplete tail until more bytes are read.
+    fn decode_pending(&mut self) {
+        let mut out = String::new();
+        let used = match self.encoding {
+            Encoding::Utf8 => decode_utf8_lossy(&self.pending, self.eof, &mut out),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                decode_utf16_lossy(&self.pending, self.encoding == Encoding::Utf16Le, self.eof, &mut out)
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                let used = self.pending.len() - self.pending.len() % 4;
+                out.extend(self.pending[..used].chunks(4).map(|unit| {
+                    char::from_u32(self.encoding.utf32_unit(unit)).unwrap_or(char::REPLACEMENT_CHARACTER)
+                }));
+
+                if self.eof && used < self.pending.len() {
+                    out.push(char::REPLACEMENT_CHARACTER);
+                    self.pending.len()
+                } else {
+                    used
+                }
+            }
+            Encoding::Latin1 | Encoding::Iso8859_5 => {
+                out.extend(self.pending.iter().map(|b| self.encoding.single_byte_char(*b)));
+                self.pending.len()
+            }
+        };
+
+        self.pending.drain(..used);
+        self.decoded.extend_from_slice(out.as_bytes());
+
+        // The declaration was found in the sniffed bytes, so it's decoded in the first chunk.
+        if self.rewrite_declaration && !self.decoded.is_empty() {
+            self.rewrite_declaration = false;
+            replace_declared_encoding(&mut self.decoded);
+        }
+    }
+}
+
+impl<R: BufRead> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.decoded.len() {
+            self.decoded.clear();
+            self.pos = 0;
+
+            self.decode_pending();
+            if !self.decoded.is_empty() || self.eof {
+                break;
+            }
+
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                self.eof = true;
+            } else {
+                let len = buf.len();
+                self.pending.extend_from_slice(buf);
+                self.inner.consume(len);
+            }
+        }
+
+        Ok(&self.decoded[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.decoded.len());
+    }
+}
+
+// Replaces the value of `encoding="..."` in the decoded XML declaration with `UTF-8`.
+fn replace_declared_encoding(decoded: &mut Vec<u8>) {
+    if !decoded.starts_with(b"<?xml") {
+        return;
+    }
+
+    let end = match decoded.windows(2).position(|w| w == b"?>") {
+        Some(end) => end,
+        None => return,
+    };
+    let decl = String::from_utf8_lossy(&decoded[..end]).into_owned();
+    let value_range = decl.find("encoding").and_then(|start| {
+        let value = &decl[start + "encoding".len()..];
+        let value = value.trim_start().strip_prefix('=')?.trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value_start = end - value.len() + 1;
+        Some(value_start..value_start + value[1..].find(quote)?)
+    });
+
+    if let Some(range) = value_range {
+        decoded.splice(range, b"UTF-8".iter().copied());
+    }
+}
+
+// Finds `encoding="..."` in an ASCII-compatible, UTF-16 or UTF-32 XML declaration.
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    // Dropping zeros turns UTF-16 and UTF-32 ASCII into plain ASCII.
+    let ascii: Vec<u8> = bytes.iter().copied().filter(|b| *b != 0).take(SNIFF_LEN).collect();
+    if !ascii.starts_with(b"<?xml") {
+        return None;
+    }
+
+    let end = ascii.windows(2).position(|w| w == b"?>")?;
+    let decl = String::from_utf8_lossy(&ascii[..end]);
+    let value = &decl[decl.find("encoding")? + "encoding".len()..];
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+// Returns the number of consumed bytes.
+fn decode_utf8_lossy(bytes: &[u8], eof: bool, out: &mut String) -> usize {
+    let mut rest = bytes;
+    loop {
+        match str::from_utf8(rest) {
+            Ok(s) => {
+                out.push_str(s);
+                return bytes.len();
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                out.push_str(str::from_utf8(valid).unwrap_or_default());
+
+                match e.error_len() {
+                    Some(len) => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        rest = &invalid[len..];
+                    }
+                    None if eof => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        return bytes.len();
+                    }
+                    None => return bytes.len() - invalid.len(),
+                }
+            }
+        }
+    }
+}
+
+// Returns the number of consumed bytes.
+fn decode_utf16_lossy(bytes: &[u8], little_endian: bool, eof: bool, out: &mut String) -> usize {
+    let mut units: Vec<u16> = bytes.chunks_exact(2)
+        .map(|pair| if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        })
+        .collect();
+
+    // A high surrogate at the end may be completed by the next chunk.
+    let mut used = units.len() * 2;
+    if !eof && units.last().is_some_and(|u| (0xd800..0xdc00).contains(u)) {
+        units.pop();
+        used -= 2;
+    }
+
+    out.extend(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+
+    if eof && used < bytes.len() {
+        out.push(char::REPLACEMENT_CHARACTER);
+        return bytes.len();
+    }
+
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::*;
+
+    fn decode_file(path: &str) -> (Encoding, String) {
+        let mut reader = DecodingReader::new(BufReader::new(File::open(path).unwrap())).unwrap();
+        reader.check_declaration().unwrap();
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        (reader.encoding(), text)
+    }
+
+    #[test]
+    fn decodes_fixtures() {
+        let cases = [
+            ("data/latin1.xml", Encoding::Latin1, "<café résumé=\"naïve\">Crème brûlée &amp; gâteau</café>\n"),
+            ("data/ISO-8859-5.xml", Encoding::Iso8859_5, "<text>\n    Текст\n</text>\n"),
+            ("data/utf16le.xml", Encoding::Utf16Le, "<文書 言語=\"日本語\">こんにちは 😀</文書>\n"),
+            ("data/utf16be.xml", Encoding::Utf16Be, "<greeting lang=\"ru\">Привет, мир</greeting>\n"),
+            ("data/utf32le.xml", Encoding::Utf32Le, "<note>Grüße 😀</note>\n"),
+        ];
+
+        for &(path, encoding, body) in &cases {
+            let (detected, text) = decode_file(path);
+            assert_eq!(detected, encoding, "{}", path);
+            assert!(text.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\""), "{}: {}", path, text);
+            assert!(text.ends_with(body), "{}: {}", path, text);
+        }
+    }
+
+    #[test]
+    fn decodes_labels() {
+        assert_eq!(Encoding::for_label(" Latin1 "), Some(Encoding::Latin1));
+        assert_eq!(Encoding::for_label("UTF-16"), Some(Encoding::Utf16Be));
+        assert_eq!(Encoding::for_label("Shift_JIS"), None);
+        assert_eq!(Encoding::Iso8859_5.decode(&[0xc2, 0xd5]).unwrap(), "Те");
+    }
+
+    #[test]
+    fn reports_mismatched_declaration() {
+        let mut bytes = vec![0xff, 0xfe];
+        for c in "<?xml version=\"1.0\" encoding=\"UTF-8\"?><a/>".encode_utf16() {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+
+        let reader = DecodingReader::new(bytes.as_slice()).unwrap();
+        match reader.check_declaration() {
+            Err(Error::EncodingMismatch { detected: Encoding::Utf16Le, ref declared }) => assert_eq!(declared, "UTF-8"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use quick_xml::events::BytesText;
+
+use crate::{Error, Result};
+
+// Predefined entities are always left to quick_xml.
+const PREDEFINED: [&[u8]; 5] = [b"lt", b"gt", b"amp", b"apos", b"quot"];
+
+// A callback that expands references to entities that are neither custom nor predefined.
+pub(crate) type EntityResolver = dyn Fn(&str) -> Option<String>;
+
+/// Limits of entity expansion.
+///
+/// Entities that reference each other can expand exponentially, like in the billion laughs attack,
+/// so both the nesting and the total size of expanded entities are limited.
+/// The size is counted across the whole document, so many small references can't add up
+/// to an unlimited size either. The defaults are 16 nested entities and a megabyte.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EntityLimits {
+    max_depth: usize,
+    max_expanded_len: usize,
+}
+
+impl Default for EntityLimits {
+    fn default() -> Self {
+        EntityLimits { max_depth: 16, max_expanded_len: 1 << 20 }
+    }
+}
+
+impl EntityLimits {
+    /// Creates the default limits.
+    pub fn new() -> Self {
+        EntityLimits::default()
+    }
+
+    /// Sets a maximum number of entities nested in each other.
+    ///
+    /// `0` allows only the predefined entities and character references.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets a maximum length in bytes of all entity values expanded in a document.
+    ///
+    /// Only the expanded values are counted, not the text around the references.
+    pub fn max_expanded_len(mut self, len: usize) -> Self {
+        self.max_expanded_len = len;
+        self
+    }
+}
+
+/// Replaces entity and character references in raw text or an attribute value.
+///
+/// Custom `entities` are indexed by names without `&` and `;` and are consulted
+/// before the five predefined ones. Their values are unescaped as well,
+/// so they can reference other entities.
+///
+/// Expansion is restricted by the default `EntityLimits`.
+pub fn unescape_with_entities<'a>(raw: &'a [u8], entities: &HashMap<Vec<u8>, Vec<u8>>) -> Result<Cow<'a, [u8]>> {
+    unescape_with_limits(raw, entities, &EntityLimits::default())
+}
+
+/// Same as `unescape_with_entities`, but with custom `limits`.
+///
+/// An entity nested too deeply, including a recursive one, or entities that expand
+/// beyond the limit are reported as `Error::EntityExpansionLimit`.
+/// The size is counted only within the `raw` value.
+pub fn unescape_with_limits<'a>(raw: &'a [u8], entities: &HashMap<Vec<u8>, Vec<u8>>,
+                                limits: &EntityLimits) -> Result<Cow<'a, [u8]>> {
+    resolve_references(raw, entities, None, limits, &Cell::new(0))
+}
+
+// Same as `unescape_with_limits`, but unknown entities are passed to the `resolver`
+// and the size of expanded entities is added to the running total of the document.
+pub(crate) fn resolve_references<'a>(raw: &'a [u8], entities: &HashMap<Vec<u8>, Vec<u8>>,
+                                     resolver: Option<&EntityResolver>, limits: &EntityLimits,
+                                     expanded_len: &Cell<usize>) -> Result<Cow<'a, [u8]>> {
+    if !raw.contains(&b'&') {
+        return Ok(Cow::Borrowed(raw));
+    }
+
+    let mut unescaped = Vec::with_capacity(raw.len());
+    Expansion { entities, resolver, limits, expanded_len, entity_start: Cell::new(0) }.expand(raw, 0, &mut unescaped)?;
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Entities declared in the `DOCTYPE` of a document being read, see `declared_entities`.
+///
+/// Also keeps the running total of expanded entities, which is limited by `EntityLimits`.
+#[derive(Default)]
+pub(crate) struct DocumentEntities {
+    values: HashMap<Vec<u8>, Vec<u8>>,
+    limits: EntityLimits,
+    expanded_len: Cell<usize>,
+}
+
+impl DocumentEntities {
+    /// Adds entities declared in the `DOCTYPE` content. The first declaration wins.
+    pub fn declare(&mut self, doctype: &[u8]) {
+        for (name, value) in declared_entities(doctype) {
+            self.values.entry(name).or_insert(value);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Replaces entity and character references, like `unescape_with_entities`.
+    pub fn unescape<'a>(&self, raw: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        resolve_references(raw, &self.values, None, &self.limits, &self.expanded_len)
+    }
+}
+
+struct Expansion<'e> {
+    entities: &'e HashMap<Vec<u8>, Vec<u8>>,
+    resolver: Option<&'e EntityResolver>,
+    limits: &'e EntityLimits,
+    // Bytes appended by all entities expanded so far, including the ones of previous values.
+    expanded_len: &'e Cell<usize>,
+    // The output length before the outermost entity being expanded.
+    entity_start: Cell<usize>,
+}
+
+impl Expansion<'_> {
+    fn expand(&self, raw: &[u8], depth: usize, out: &mut Vec<u8>) -> Result<()> {
+        let mut last_end = 0;
+        while let Some(start) = raw[last_end..].iter().position(|b| *b == b'&').map(|pos| pos + last_end) {
+            out.extend_from_slice(&raw[last_end..start]);
+
+            let end = match raw[start..].iter().position(|b| *b == b';') {
+                Some(pos) => pos + start,
+                None => {
+                    // quick_xml reports the unterminated reference.
+                    unescape_reference(&raw[start..], out)?;
+                    return Ok(());
+                }
+            };
+
+            let name = &raw[start + 1..end];
+            if depth == 0 {
+                self.entity_start.set(out.len());
+            }
+
+            let is_expanded = if let Some(value) = self.entities.get(name) {
+                self.expand_entity(name, value, depth, out)?;
+                true
+            } else if let Some(resolver) = self.resolver.filter(|_| is_unknown(name)) {
+                match resolver(&String::from_utf8_lossy(name)) {
+                    Some(value) => {
+                        self.expand_entity(name, value.as_bytes(), depth, out)?;
+                        true
+                    }
+                    None => {
+                        out.extend_from_slice(&raw[start..=end]);
+                        false
+                    }
+                }
+            } else {
+                unescape_reference(&raw[start..=end], out)?;
+                false
+            };
+
+            // The outermost entity is added to the total once it's expanded,
+            // while the nested ones are checked on the way, so a huge one fails early.
+            if is_expanded || depth > 0 {
+                let expanded_len = self.expanded_len.get() + out.len() - self.entity_start.get();
+                if expanded_len > self.limits.max_expanded_len {
+                    return Err(Error::EntityExpansionLimit {
+                        entity: String::from_utf8_lossy(name).into_owned(),
+                        message: format!("expanded entities are longer than {} bytes", self.limits.max_expanded_len),
+                    });
+                }
+
+                if depth == 0 {
+                    self.expanded_len.set(expanded_len);
+                }
+            }
+
+            last_end = end + 1;
+        }
+
+        out.extend_from_slice(&raw[last_end..]);
+        Ok(())
+    }
+
+    fn expand_entity(&self, name: &[u8], value: &[u8], depth: usize, out: &mut Vec<u8>) -> Result<()> {
+        if depth == self.limits.max_depth {
+            return Err(Error::EntityExpansionLimit {
+                entity: String::from_utf8_lossy(name).into_owned(),
+                message: format!("more than {} entities are nested", self.limits.max_depth),
+            });
+        }
+
+        self.expand(value, depth + 1, out)
+    }
+}
+
+// Neither a predefined entity nor a character reference.
+fn is_unknown(name: &[u8]) -> bool {
+    !name.starts_with(b"#") && !PREDEFINED.contains(&name)
+}
+
+// Predefined entities and character references are left to quick_xml.
+fn unescape_reference(reference: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    out.extend_from_slice(&BytesText::from_escaped(reference).unescaped()?);
+    Ok(())
+}
+
+/// Collects internal general entities declared in the `DOCTYPE` content, in the declaration order.
+///
+/// Parameter entities and external entities with `SYSTEM` or `PUBLIC` identifiers are skipped.
+pub(crate) fn declared_entities(doctype: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    const DECLARATION: &[u8] = b"<!ENTITY";
+
+    let mut entities = Vec::new();
+    let mut rest = doctype;
+    while let Some(pos) = rest.windows(DECLARATION.len()).position(|w| w == DECLARATION) {
+        rest = trim_start(&rest[pos + DECLARATION.len()..]);
+        if rest.starts_with(b"%") {
+            continue;
+        }
+
+        let name_len = rest.iter().position(|b| b.is_ascii_whitespace()).unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        rest = trim_start(&rest[name_len..]);
+
+        let quote = match rest.first() {
+            Some(&q) if q == b'"' || q == b'\'' => q,
+            _ => continue,
+        };
+
+        if let Some(len) = rest[1..].iter().position(|b| *b == quote) {
+            entities.push((name.to_vec(), rest[1..len + 1].to_vec()));
+            rest = &rest[len + 2..];
+        }
+    }
+
+    entities
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::{dump_events, DumpOptions};
+
+    fn entities(pairs: &[(&str, &str)]) -> HashMap<Vec<u8>, Vec<u8>> {
+        pairs.iter().map(|(name, value)| (name.as_bytes().to_vec(), value.as_bytes().to_vec())).collect()
+    }
+
+    #[test]
+    fn rejects_billion_laughs() {
+        let input = BufReader::new(File::open("data/billion_laughs.xml").unwrap());
+        match dump_events(input, &mut std::io::sink(), &DumpOptions::default()) {
+            Err(Error::EntityExpansionLimit { .. }) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn rejects_recursive_entity() {
+        let entities = entities(&[("a", "&b;"), ("b", "&a;")]);
+        match unescape_with_entities(b"&a;", &entities) {
+            Err(Error::EntityExpansionLimit { ref message, .. }) => assert!(message.contains("nested"), "{}", message),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn expands_within_limits() {
+        let entities = entities(&[("x", "&y;&y;"), ("y", "&lt;y&gt;")]);
+        assert_eq!(unescape_with_entities(b"[&x;] &amp;", &entities).unwrap().as_ref(), b"[<y><y>] &");
+
+        let limits = EntityLimits::new().max_expanded_len(4);
+        assert!(unescape_with_limits(b"&y;", &entities, &limits).is_ok());
+        // Nested entities are checked on the way, so the innermost reference is reported.
+        match unescape_with_limits(b"&x;", &entities, &limits) {
+            Err(Error::EntityExpansionLimit { ref entity, .. }) => assert_eq!(entity, "gt"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
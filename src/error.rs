@@ -0,0 +1,381 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::Utf8Error;
+
+use quick_xml::Reader;
+
+use crate::{BackendKind, DepthLimitError, Encoding, XPathError};
+
+/// A crate error that wraps errors from all supported backends.
+#[derive(Debug)]
+pub enum Error {
+    /// An input can't be opened.
+    Open {
+        path: String,
+        error: io::Error,
+    },
+    /// An I/O error.
+    Io(io::Error),
+    /// A quick_xml parsing error.
+    QuickXml(quick_xml::Error),
+    /// A quick_xml parsing error with a position in the input.
+    ///
+    /// `line` and `column` start from 1, `column` and `position` are in bytes.
+    Syntax {
+        line: usize,
+        column: usize,
+        position: usize,
+        error: quick_xml::Error,
+    },
+    /// An element has two attributes with the same expanded name.
+    ///
+    /// `name` is a qualified name of the second attribute,
+    /// `position` is its byte offset from the start of the tag name.
+    DuplicateAttribute {
+        name: String,
+        position: usize,
+    },
+    /// A prefix of an attribute name isn't bound to a namespace.
+    UnboundPrefix {
+        prefix: String,
+    },
+    /// An End event without a matching Start one.
+    ///
+    /// `position` is a byte offset right after the tag.
+    UnexpectedEnd {
+        name: String,
+        position: usize,
+    },
+    /// A document has another root element, which is allowed only in the fragment mode.
+    ///
+    /// `name` is a local name of the element and `position` is a byte offset after it.
+    MultipleRoots {
+        name: String,
+        position: usize,
+    },
+    /// The document ended with an open element.
+    ///
+    /// `name` is a local name of the innermost open element.
+    Unclosed {
+        name: String,
+    },
+    /// An xml-rs parsing error.
+    #[cfg(feature = "xml_rs")]
+    XmlRs(xml::reader::Error),
+    /// A roxmltree parsing error.
+    #[cfg(feature = "roxmltree")]
+    Roxmltree(roxmltree::Error),
+    /// An sxd-document parsing error.
+    #[cfg(feature = "sxd_document")]
+    SxdDocument(sxd_document::parser::Error),
+    /// A name or a value is not a valid UTF-8 string.
+    Utf8(Utf8Error),
+    /// A JSON input is malformed or doesn't match the expected schema.
+    ///
+    /// `position` is a byte offset.
+    Json {
+        position: usize,
+        message: String,
+    },
+    /// A JSON value passed to `json_to_xml` doesn't follow the convention.
+    ///
+    /// `path` is a JSON pointer to the offending value.
+    JsonStructure {
+        path: String,
+        message: String,
+    },
+    /// A path passed to `select` is malformed or unsupported.
+    InvalidPath {
+        path: String,
+        message: String,
+    },
+    /// Bytes are invalid in the encoding.
+    ///
+    /// `position` is a byte offset.
+    Decode {
+        encoding: Encoding,
+        position: usize,
+    },
+    /// The input starts with the gzip magic bytes, but wasn't decompressed.
+    ///
+    /// Only `open_input` decompresses the input, and only with the `gzip` feature.
+    Compressed,
+    /// A BOM or the byte layout contradicts the `encoding` of the XML declaration.
+    EncodingMismatch {
+        detected: Encoding,
+        declared: String,
+    },
+    /// A URI reference can't be resolved.
+    InvalidUri {
+        uri: String,
+        message: String,
+    },
+    /// An entity expands too deeply or into too much text.
+    ///
+    /// `entity` is the reference being expanded when the `EntityLimits` were exceeded.
+    EntityExpansionLimit {
+        entity: String,
+        message: String,
+    },
+    /// A document doesn't match the type passed to `from_reader`.
+    Deserialize(String),
+    /// A name passed to `BackendKind::from_str` isn't a backend enabled by features.
+    UnknownBackend(String),
+    /// An element nesting exceeded the limit.
+    DepthLimit(DepthLimitError),
+    /// An expression passed to `evaluate_xpath` is malformed or unsupported.
+    XPath(XPathError),
+    /// An error from a backend that isn't a part of the crate, like minidom or xmltree.
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// An error while processing a file.
+    InFile {
+        path: String,
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Wraps an error of a backend that isn't a part of the crate.
+    pub fn backend<E>(e: E) -> Self
+        where E: std::error::Error + Send + Sync + 'static
+    {
+        Error::Backend(Box::new(e))
+    }
+
+    /// Attaches a path of the processed file.
+    ///
+    /// `Error::Open` already has a path, so it's returned as is.
+    pub fn in_file(self, path: &str) -> Self {
+        match self {
+            Error::Open { .. } | Error::InFile { .. } => self,
+            _ => Error::InFile { path: path.to_string(), error: Box::new(self) },
+        }
+    }
+
+    /// Wraps a quick_xml parsing error with the current position of the `reader`.
+    ///
+    /// `buf` is the buffer of the failed read, which may already contain line breaks.
+    pub(crate) fn syntax<R: BufRead>(reader: &Reader<R>, lines: &LineCounter, buf: &[u8],
+                                     error: quick_xml::Error) -> Self {
+        let position = reader.buffer_position();
+        let (line, column) = lines.locate(buf, position);
+        Error::Syntax { line, column, position, error }
+    }
+
+    /// Locates a quick_xml decoding error at the start of the event being read.
+    ///
+    /// Decoding happens after a successful read, so the error itself has no position.
+    pub(crate) fn in_event(self, lines: &LineCounter) -> Self {
+        match self {
+            Error::QuickXml(error) => {
+                let (line, column) = lines.locate(&[], lines.buf_start);
+                Error::Syntax { line, column, position: lines.buf_start, error }
+            }
+            _ => self,
+        }
+    }
+}
+
+/// Tracks line breaks in the consumed input for `Error::Syntax`.
+///
+/// quick_xml reads each event into a buffer that starts right after the previous `<` or `>`,
+/// so offsets of line breaks are recovered from the buffers alone, without re-reading the input.
+/// Whitespace skipped by `Reader::trim_text` is kept in the buffer, so it's counted too.
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct LineCounter {
+    line_breaks: usize,
+    // A byte offset of the current line.
+    line_start: usize,
+    // A byte offset of the buffer being read.
+    buf_start: usize,
+    next_start: usize,
+}
+
+impl LineCounter {
+    /// Returns the line of the buffer being read, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line_breaks + 1
+    }
+
+    /// Counts line breaks of the previous event buffer. Must be called before each read.
+    pub fn consume(&mut self, buf: &[u8]) {
+        self.count(buf);
+        self.buf_start = self.next_start;
+    }
+
+    /// Records the position of a successfully read event.
+    ///
+    /// After a Text event quick_xml has already consumed the `<` of the next tag.
+    pub fn event_read(&mut self, position: usize, is_text: bool) {
+        self.next_start = if is_text { position + 1 } else { position };
+    }
+
+    /// Records a failed read of the `buf`, after which reading resumes past its delimiter.
+    pub fn error_skipped(&mut self, buf: &[u8]) {
+        self.next_start = self.buf_start + buf.len() + 1;
+    }
+
+    /// Returns the line and the column of the `position` within the `buf` being read.
+    fn locate(&self, buf: &[u8], position: usize) -> (usize, usize) {
+        let mut lines = *self;
+        lines.count(&buf[..buf.len().min(position.saturating_sub(self.buf_start))]);
+        (lines.line(), position.saturating_sub(lines.line_start) + 1)
+    }
+
+    fn count(&mut self, bytes: &[u8]) {
+        if let Some(last) = bytes.iter().rposition(|c| *c == b'\n') {
+            self.line_breaks += bytes.iter().filter(|c| **c == b'\n').count();
+            self.line_start = self.buf_start + last + 1;
+        }
+    }
+}
+
+/// Generates a `From` impl for a backend error.
+macro_rules! from_error {
+    ($err_type:ty, $err_name:ident) => (
+        impl From<$err_type> for Error {
+            fn from(value: $err_type) -> Error {
+                Error::$err_name(value)
+            }
+        }
+    )
+}
+
+from_error!(io::Error, Io);
+from_error!(quick_xml::Error, QuickXml);
+#[cfg(feature = "xml_rs")]
+from_error!(xml::reader::Error, XmlRs);
+#[cfg(feature = "roxmltree")]
+from_error!(roxmltree::Error, Roxmltree);
+#[cfg(feature = "sxd_document")]
+from_error!(sxd_document::parser::Error, SxdDocument);
+from_error!(Utf8Error, Utf8);
+from_error!(DepthLimitError, DepthLimit);
+from_error!(XPathError, XPath);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Open { ref path, ref error } => write!(f, "{}: {}", path, error),
+            Error::Io(ref e) => write!(f, "I/O error: {}", e),
+            Error::QuickXml(ref e) => write!(f, "XML error: {}", e),
+            Error::Syntax { line, column, ref error, .. } => {
+                write!(f, "parse error at line {}, column {}: {}", line, column, error)
+            }
+            Error::DuplicateAttribute { ref name, position } => {
+                write!(f, "duplicate attribute {} at byte {} of the tag", name, position)
+            }
+            Error::UnboundPrefix { ref prefix } => write!(f, "namespace prefix `{}` is not bound", prefix),
+            Error::UnexpectedEnd { ref name, position } => {
+                write!(f, "unexpected closing tag `{}` at byte {}", name, position)
+            }
+            Error::MultipleRoots { ref name, position } => {
+                write!(f, "another root element `{}` at byte {}", name, position)
+            }
+            Error::Unclosed { ref name } => write!(f, "element `{}` is not closed", name),
+            #[cfg(feature = "xml_rs")]
+            Error::XmlRs(ref e) => write!(f, "XML error: {}", e),
+            #[cfg(feature = "roxmltree")]
+            Error::Roxmltree(ref e) => write!(f, "XML error: {}", e),
+            #[cfg(feature = "sxd_document")]
+            Error::SxdDocument(ref e) => write!(f, "XML error: {}", e),
+            Error::Utf8(ref e) => write!(f, "UTF-8 error: {}", e),
+            Error::Json { position, ref message } => write!(f, "JSON error at byte {}: {}", position, message),
+            Error::JsonStructure { ref path, ref message } => {
+                write!(f, "invalid XML-shaped JSON at `{}`: {}", path, message)
+            }
+            Error::InvalidPath { ref path, ref message } => write!(f, "invalid path `{}`: {}", path, message),
+            Error::Decode { encoding, position } => write!(f, "invalid {} at byte {}", encoding, position),
+            Error::Compressed => {
+                write!(f, "the input is gzip-compressed, decompress it first, e.g. with `gunzip -c`, \
+                           or enable the `gzip` feature")
+            }
+            Error::EncodingMismatch { detected, ref declared } => {
+                write!(f, "the document is encoded in {}, but declares encoding=\"{}\"", detected, declared)
+            }
+            Error::InvalidUri { ref uri, ref message } => write!(f, "invalid URI `{}`: {}", uri, message),
+            Error::EntityExpansionLimit { ref entity, ref message } => {
+                write!(f, "expansion of the entity `&{};` exceeds the limit: {}", entity, message)
+            }
+            Error::Deserialize(ref message) => write!(f, "deserialization error: {}", message),
+            Error::UnknownBackend(ref name) => {
+                let names: Vec<_> = BackendKind::ALL.iter().map(|b| format!("`{}`", b)).collect();
+                write!(f, "unknown backend `{}`, expected one of {}", name, names.join(", "))
+            }
+            Error::DepthLimit(ref e) => write!(f, "{}", e),
+            Error::XPath(ref e) => write!(f, "{}", e),
+            Error::Backend(ref e) => write!(f, "{}", e),
+            Error::InFile { ref path, ref error } => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Open { ref error, .. } => Some(error),
+            Error::Io(ref e) => Some(e),
+            Error::QuickXml(ref e) => Some(e),
+            Error::Syntax { ref error, .. } => Some(error),
+            Error::DuplicateAttribute { .. } => None,
+            Error::UnboundPrefix { .. } => None,
+            Error::UnexpectedEnd { .. } => None,
+            Error::MultipleRoots { .. } => None,
+            Error::Unclosed { .. } => None,
+            #[cfg(feature = "xml_rs")]
+            Error::XmlRs(ref e) => Some(e),
+            #[cfg(feature = "roxmltree")]
+            Error::Roxmltree(ref e) => Some(e),
+            #[cfg(feature = "sxd_document")]
+            Error::SxdDocument(ref e) => Some(e),
+            Error::Utf8(ref e) => Some(e),
+            Error::Json { .. } => None,
+            Error::JsonStructure { .. } => None,
+            Error::InvalidPath { .. } => None,
+            Error::Decode { .. } => None,
+            Error::Compressed => None,
+            Error::EncodingMismatch { .. } => None,
+            Error::InvalidUri { .. } => None,
+            Error::EntityExpansionLimit { .. } => None,
+            Error::Deserialize(_) => None,
+            Error::UnknownBackend(_) => None,
+            Error::DepthLimit(ref e) => Some(e),
+            Error::XPath(ref e) => Some(e),
+            Error::Backend(ref e) => Some(e.as_ref()),
+            Error::InFile { ref error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dump_events, open_input, DumpOptions};
+
+    fn dump(text: &str) -> Result<()> {
+        dump_events(text.as_bytes(), &mut Vec::new(), &DumpOptions::default())
+    }
+
+    #[test]
+    fn reports_missing_file() {
+        let e = open_input("missing.xml").map(|_| ()).unwrap_err();
+        assert!(matches!(e, Error::Open { .. }));
+        assert!(e.to_string().starts_with("missing.xml: "), "{}", e);
+        // The path is already there.
+        assert_eq!(e.in_file("missing.xml").to_string().matches("missing.xml").count(), 1);
+    }
+
+    #[test]
+    fn reports_position_of_malformed_input() {
+        let e = dump("<a>\n  <b></c>\n</a>").unwrap_err();
+        match e {
+            Error::Syntax { line, column, .. } => assert_eq!((line, column), (2, 8)),
+            ref e => panic!("unexpected error: {:?}", e),
+        }
+
+        let e = e.in_file("bad.xml");
+        assert!(e.to_string().starts_with("bad.xml: parse error at line 2, column 8: "), "{}", e);
+    }
+}
@@ -0,0 +1,354 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::str;
+
+use quick_xml::events::{BytesStart, BytesText, Event as QuickXmlEvent};
+use quick_xml::Reader;
+
+use crate::entity::{resolve_references, EntityResolver};
+use crate::{EntityLimits, Error, EventVisitor, FilteringReader, QuickXmlReader, Result, TransformingReader,
+            WhitespaceMode};
+
+/// An attribute of a backend-agnostic `Event`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Attr<'a> {
+    /// A qualified name, as written in the document.
+    pub key: Cow<'a, [u8]>,
+    /// An unescaped value.
+    pub value: Cow<'a, str>,
+}
+
+/// A backend-agnostic XML event.
+///
+/// Namespaces are resolved, names are local and text is unescaped.
+/// `prefix` is the prefix of the qualified name, as written in the document.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event<'a> {
+    Start {
+        ns: Option<Cow<'a, [u8]>>,
+        prefix: Option<Cow<'a, [u8]>>,
+        name: Cow<'a, [u8]>,
+        attrs: Vec<Attr<'a>>,
+    },
+    End {
+        ns: Option<Cow<'a, [u8]>>,
+        prefix: Option<Cow<'a, [u8]>>,
+        name: Cow<'a, [u8]>,
+    },
+    Empty {
+        ns: Option<Cow<'a, [u8]>>,
+        prefix: Option<Cow<'a, [u8]>>,
+        name: Cow<'a, [u8]>,
+        attrs: Vec<Attr<'a>>,
+    },
+    Text(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    CData(Cow<'a, str>),
+    PI(Cow<'a, str>),
+    DocType(Cow<'a, str>),
+    Decl {
+        version: Option<Cow<'a, str>>,
+        encoding: Option<Cow<'a, str>>,
+        standalone: Option<Cow<'a, str>>,
+    },
+    Eof,
+}
+
+impl<'a> Attr<'a> {
+    /// Converts the attribute into an owned one.
+    pub fn into_owned(self) -> Attr<'static> {
+        Attr {
+            key: Cow::Owned(self.key.into_owned()),
+            value: Cow::Owned(self.value.into_owned()),
+        }
+    }
+}
+
+impl<'a> Event<'a> {
+    /// Converts the event into an owned one.
+    pub fn into_owned(self) -> Event<'static> {
+        fn owned<T: ToOwned + ?Sized>(v: Cow<T>) -> Cow<'static, T> {
+            Cow::Owned(v.into_owned())
+        }
+
+        fn owned_attrs(attrs: Vec<Attr>) -> Vec<Attr<'static>> {
+            attrs.into_iter().map(Attr::into_owned).collect()
+        }
+
+        match self {
+            Event::Start { ns, prefix, name, attrs } => Event::Start {
+                ns: ns.map(owned),
+                prefix: prefix.map(owned),
+                name: owned(name),
+                attrs: owned_attrs(attrs),
+            },
+            Event::End { ns, prefix, name } => Event::End {
+                ns: ns.map(owned),
+                prefix: prefix.map(owned),
+                name: owned(name),
+            },
+            Event::Empty { ns, prefix, name, attrs } => Event::Empty {
+                ns: ns.map(owned),
+                prefix: prefix.map(owned),
+                name: owned(name),
+                attrs: owned_attrs(attrs),
+            },
+            Event::Text(text) => Event::Text(owned(text)),
+            Event::Comment(text) => Event::Comment(owned(text)),
+            Event::CData(text) => Event::CData(owned(text)),
+            Event::PI(text) => Event::PI(owned(text)),
+            Event::DocType(text) => Event::DocType(owned(text)),
+            Event::Decl { version, encoding, standalone } => Event::Decl {
+                version: version.map(owned),
+                encoding: encoding.map(owned),
+                standalone: standalone.map(owned),
+            },
+            Event::Eof => Event::Eof,
+        }
+    }
+
+    /// Checks that the event is a Start, End or Empty one.
+    pub fn is_tag(&self) -> bool {
+        matches!(*self, Event::Start { .. } | Event::End { .. } | Event::Empty { .. })
+    }
+
+    /// Checks that the event is a text that contains only whitespace.
+    ///
+    /// Only XML whitespace counts, i.e. spaces, tabs and line breaks,
+    /// so text of Unicode spaces like U+00A0 is kept.
+    pub fn is_whitespace(&self) -> bool {
+        match *self {
+            Event::Text(ref text) => text.chars().all(|c| matches!(c, ' ' | '\t' | '\r' | '\n')),
+            _ => false,
+        }
+    }
+}
+
+/// A source of backend-agnostic events.
+pub trait XmlSource {
+    /// Reads the next event.
+    ///
+    /// Returns `Event::Eof` at the end of the document.
+    fn next_event(&mut self) -> Result<Event<'_>>;
+
+    /// Wraps the source into a `FilteringReader`, which skips events rejected by the `predicate`.
+    fn filter_events<F: Fn(&Event<'static>) -> bool>(self, predicate: F) -> FilteringReader<Self, F>
+        where Self: Sized
+    {
+        FilteringReader::new(self, predicate)
+    }
+
+    /// Wraps the source into a `TransformingReader`, which passes each event through the `transform`.
+    fn map_events<F: FnMut(Event<'static>) -> Event<'static>>(self, transform: F) -> TransformingReader<Self, F>
+        where Self: Sized
+    {
+        TransformingReader::new(self, transform)
+    }
+}
+
+impl<R: BufRead> XmlSource for QuickXmlReader<R> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        if self.whitespace == WhitespaceMode::Preserve && self.peeked.is_none() {
+            return self.read_event();
+        }
+
+        // Whitespace filtering requires a lookahead, so events are copied.
+        loop {
+            let event = match self.peeked.take() {
+                Some(event) => event,
+                None => self.read_event()?.into_owned(),
+            };
+
+            if event.is_whitespace() {
+                match self.whitespace {
+                    WhitespaceMode::Preserve => {}
+                    WhitespaceMode::StripAll => continue,
+                    WhitespaceMode::StripInterElement => {
+                        if self.prev_is_tag {
+                            let next = self.read_event()?.into_owned();
+                            if next.is_tag() {
+                                self.prev_is_tag = true;
+                                return Ok(next);
+                            }
+
+                            self.peeked = Some(next);
+                        }
+                    }
+                }
+            }
+
+            self.prev_is_tag = event.is_tag();
+            return Ok(event);
+        }
+    }
+}
+
+impl<R: BufRead> QuickXmlReader<R> {
+    // Only the namespace is borrowed, because quick_xml doesn't expose names
+    // with the buffer lifetime, so they are copied.
+    fn read_event(&mut self) -> Result<Event<'_>> {
+        let (ns, event) = read_raw_event!(self)?;
+
+        let entities = Entities {
+            values: &self.entities,
+            resolver: self.entity_resolver.as_deref(),
+            limits: &self.entity_limits,
+            expanded_len: &self.entity_expanded_len,
+        };
+        let lines = &self.lines;
+        lower_event(&self.reader, &entities, ns, event).map_err(|e| e.in_event(lines))
+    }
+}
+
+// Custom entities of a reader together with their resolver, limits and the running total of expansion.
+struct Entities<'e> {
+    values: &'e HashMap<Vec<u8>, Vec<u8>>,
+    resolver: Option<&'e EntityResolver>,
+    limits: &'e EntityLimits,
+    expanded_len: &'e Cell<usize>,
+}
+
+impl Entities<'_> {
+    // Without custom entities, quick_xml can unescape on its own.
+    fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.resolver.is_none()
+    }
+
+    fn unescape_and_decode<R: BufRead>(&self, r: &Reader<R>, raw: &[u8]) -> Result<String> {
+        Ok(r.decode(&resolve_references(raw, self.values, self.resolver, self.limits, self.expanded_len)?)?.to_string())
+    }
+}
+
+// Decoding errors, like an unknown entity, are located at the start of the event.
+fn lower_event<'a, R: BufRead>(reader: &Reader<R>, entities: &Entities, ns: Option<&'a [u8]>,
+                               event: QuickXmlEvent) -> Result<Event<'a>> {
+    let ns = ns.map(Cow::Borrowed);
+
+    let event = match event {
+        QuickXmlEvent::Start(ref e) => Event::Start {
+            ns,
+            prefix: name_prefix(e.name()).map(|p| Cow::Owned(p.to_vec())),
+            name: Cow::Owned(e.local_name().to_vec()),
+            attrs: lower_attributes(reader, entities, e)?,
+        },
+        QuickXmlEvent::Empty(ref e) => Event::Empty {
+            ns,
+            prefix: name_prefix(e.name()).map(|p| Cow::Owned(p.to_vec())),
+            name: Cow::Owned(e.local_name().to_vec()),
+            attrs: lower_attributes(reader, entities, e)?,
+        },
+        QuickXmlEvent::End(ref e) => Event::End {
+            ns,
+            prefix: name_prefix(e.name()).map(|p| Cow::Owned(p.to_vec())),
+            name: Cow::Owned(e.local_name().to_vec()),
+        },
+        QuickXmlEvent::Text(ref e) => Event::Text(unescape_text(reader, entities, e)?.into()),
+        // Only text and attribute values are escaped.
+        QuickXmlEvent::Comment(ref e) => Event::Comment(reader.decode(e)?.to_string().into()),
+        QuickXmlEvent::CData(ref e) => Event::CData(reader.decode(e)?.to_string().into()),
+        QuickXmlEvent::PI(ref e) => Event::PI(reader.decode(e)?.to_string().into()),
+        // Entity values in the internal subset are not escaped text.
+        QuickXmlEvent::DocType(ref e) => Event::DocType(reader.decode(e)?.to_string().into()),
+        QuickXmlEvent::Decl(ref e) => {
+            let version = match e.version() {
+                Ok(v) => Some(str::from_utf8(v.as_ref())?.to_string().into()),
+                Err(_) => None,
+            };
+
+            let encoding = match e.encoding() {
+                Some(Ok(v)) => Some(str::from_utf8(v.as_ref())?.to_string().into()),
+                _ => None,
+            };
+
+            let standalone = match e.standalone() {
+                Some(Ok(v)) => Some(str::from_utf8(v.as_ref())?.to_string().into()),
+                _ => None,
+            };
+
+            Event::Decl { version, encoding, standalone }
+        }
+        QuickXmlEvent::Eof => Event::Eof,
+    };
+
+    Ok(event)
+}
+
+/// Returns the prefix of a qualified name.
+pub(crate) fn name_prefix(name: &[u8]) -> Option<&[u8]> {
+    name.iter().position(|c| *c == b':').map(|idx| &name[..idx])
+}
+
+fn lower_attributes<'a, R: BufRead>(r: &Reader<R>, entities: &Entities, e: &BytesStart) -> Result<Vec<Attr<'a>>> {
+    let mut attrs = Vec::new();
+    for a in e.attributes() {
+        let a = a?;
+        let value = if entities.is_empty() {
+            a.unescape_and_decode_value(r)?
+        } else {
+            entities.unescape_and_decode(r, &a.value)?
+        };
+
+        attrs.push(Attr { key: Cow::Owned(a.key.to_vec()), value: Cow::Owned(value) });
+    }
+
+    Ok(attrs)
+}
+
+fn unescape_text<R: BufRead>(r: &Reader<R>, entities: &Entities, e: &BytesText) -> Result<String> {
+    if entities.is_empty() {
+        Ok(e.unescape_and_decode(r)?)
+    } else {
+        entities.unescape_and_decode(r, e)
+    }
+}
+
+/// Passes a single backend-agnostic event to the `visitor`.
+///
+/// `depth` is updated on Start/End events. It never goes below zero.
+pub fn visit(event: &Event, depth: &mut usize, visitor: &mut impl EventVisitor) -> Result<()> {
+    match *event {
+        Event::Start { ref ns, ref prefix, ref name, ref attrs } => {
+            let attrs = decode_attrs(attrs)?;
+            visitor.start(decode_optional(ns)?, decode_optional(prefix)?, str::from_utf8(name)?, &attrs, *depth)?;
+            *depth += 1;
+        }
+        Event::Empty { ref ns, ref prefix, ref name, ref attrs } => {
+            let attrs = decode_attrs(attrs)?;
+            visitor.empty(decode_optional(ns)?, decode_optional(prefix)?, str::from_utf8(name)?, &attrs, *depth)?;
+        }
+        Event::End { ref ns, ref prefix, ref name } => {
+            // An unmatched End event is reported at the zero depth.
+            *depth = depth.saturating_sub(1);
+            visitor.end(decode_optional(ns)?, decode_optional(prefix)?, str::from_utf8(name)?, *depth)?;
+        }
+        Event::Text(ref text) => visitor.text(text, *depth)?,
+        Event::Comment(ref text) => visitor.comment(text, *depth)?,
+        Event::CData(ref text) => visitor.cdata(text, *depth)?,
+        Event::PI(ref text) => visitor.pi(text, *depth)?,
+        Event::DocType(ref text) => visitor.doctype(text, *depth)?,
+        Event::Decl { ref version, ref encoding, ref standalone } => {
+            visitor.decl(version.as_deref(), encoding.as_deref(), standalone.as_deref(), *depth)?;
+        }
+        Event::Eof => {}
+    }
+
+    Ok(())
+}
+
+fn decode_optional<'a>(value: &'a Option<Cow<[u8]>>) -> Result<Option<&'a str>> {
+    match value {
+        Some(value) => Ok(Some(str::from_utf8(value)?)),
+        None => Ok(None),
+    }
+}
+
+fn decode_attrs(attrs: &[Attr]) -> Result<Vec<(String, String)>> {
+    let mut decoded = Vec::with_capacity(attrs.len());
+    for a in attrs {
+        decoded.push((str::from_utf8(&a.key)?.to_string(), a.value.to_string()));
+    }
+
+    Ok(decoded)
+}
@@ -0,0 +1,169 @@
+use crate::event::{Event, XmlSource};
+use crate::{skip_element, Result};
+
+/// A reader that skips events rejected by a predicate.
+///
+/// When a Start event is rejected, the whole element is skipped together with its content
+/// and the matching End event, so the remaining events stay balanced.
+/// End events are never passed to the predicate, since they follow their Start events,
+/// and `Event::Eof` is always returned.
+///
+/// Each event is copied before being passed to the predicate.
+pub struct FilteringReader<S: XmlSource, F: Fn(&Event<'static>) -> bool> {
+    source: S,
+    predicate: F,
+    depth: usize,
+}
+
+impl<S: XmlSource, F: Fn(&Event<'static>) -> bool> FilteringReader<S, F> {
+    /// Creates a new reader that returns only the events for which the `predicate` returns `true`.
+    pub fn new(source: S, predicate: F) -> Self {
+        FilteringReader {
+            source,
+            predicate,
+            depth: 0,
+        }
+    }
+
+    /// Returns the depth of the returned events, i.e. the number of open elements that were not skipped.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: XmlSource, F: Fn(&Event<'static>) -> bool> XmlSource for FilteringReader<S, F> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        loop {
+            let event = self.source.next_event()?.into_owned();
+
+            match event {
+                Event::End { .. } => {
+                    self.depth = self.depth.saturating_sub(1);
+                    return Ok(event);
+                }
+                Event::Eof => return Ok(event),
+                _ => {}
+            }
+
+            if (self.predicate)(&event) {
+                if let Event::Start { .. } = event {
+                    self.depth += 1;
+                }
+
+                return Ok(event);
+            }
+
+            if let Event::Start { .. } = event {
+                skip_element(&mut self.source)?;
+            }
+        }
+    }
+}
+
+/// A reader that rewrites events with a closure, like renaming elements or changing attribute values.
+///
+/// Every event, including End and `Event::Eof`, is passed to the closure,
+/// so an element renamed on Start has to be renamed on End too.
+/// Since `FilteringReader` and `TransformingReader` are sources themselves, they can be chained
+/// with `XmlSource::filter_events` and `XmlSource::map_events`.
+///
+/// Each event is copied before being passed to the closure.
+pub struct TransformingReader<S: XmlSource, F: FnMut(Event<'static>) -> Event<'static>> {
+    source: S,
+    transform: F,
+}
+
+impl<S: XmlSource, F: FnMut(Event<'static>) -> Event<'static>> TransformingReader<S, F> {
+    /// Creates a new reader that returns events rewritten by the `transform`.
+    pub fn new(source: S, transform: F) -> Self {
+        TransformingReader { source, transform }
+    }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: XmlSource, F: FnMut(Event<'static>) -> Event<'static>> XmlSource for TransformingReader<S, F> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        let event = self.source.next_event()?.into_owned();
+        Ok((self.transform)(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{collect_events, DumpEvent, QuickXmlReader};
+
+    const DOCUMENT: &str = concat!(
+        "<root><!-- note -->",
+        "<secret><a>1</a><secret><b/></secret></secret>",
+        "<item>2</item><secret/>",
+        "</root>",
+    );
+
+    // quick_xml reports empty text between adjacent tags, so it's skipped as well.
+    fn is_kept(e: &Event) -> bool {
+        match *e {
+            Event::Start { ref name, .. } | Event::Empty { ref name, .. } => name.as_ref() != b"secret",
+            Event::Text(ref text) => !text.is_empty(),
+            Event::Comment(_) => false,
+            _ => true,
+        }
+    }
+
+    fn names(events: &[DumpEvent]) -> Vec<String> {
+        events.iter().map(|e| match *e {
+            DumpEvent::StartElement { ref name, .. } => format!("<{}>", name),
+            DumpEvent::EmptyElement { ref name, .. } => format!("<{}/>", name),
+            DumpEvent::EndElement { ref name, .. } => format!("</{}>", name),
+            ref e => e.to_string().trim().to_string(),
+        }).collect()
+    }
+
+    #[test]
+    fn skips_whole_subtrees() {
+        let mut reader = QuickXmlReader::new(Reader::from_str(DOCUMENT)).filter_events(is_kept);
+        let events = collect_events(&mut reader).unwrap();
+        assert_eq!(names(&events), ["<root>", "<item>", "Text: \"2\"", "</item>", "</root>"]);
+        assert_eq!(reader.depth(), 0);
+    }
+
+    #[test]
+    fn tracks_depth_of_returned_events() {
+        let mut reader = QuickXmlReader::new(Reader::from_str(DOCUMENT)).filter_events(is_kept);
+        let mut depths = Vec::new();
+        loop {
+            if let Event::Eof = reader.next_event().unwrap() {
+                break;
+            }
+            depths.push(reader.depth());
+        }
+
+        assert_eq!(depths, [1, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn chains_with_transforming_reader() {
+        let rename = |e: Event<'static>| match e {
+            Event::Start { ns, prefix, attrs, .. } => {
+                Event::Start { ns, prefix, name: b"entry".to_vec().into(), attrs }
+            }
+            Event::End { ns, prefix, .. } => Event::End { ns, prefix, name: b"entry".to_vec().into() },
+            e => e,
+        };
+
+        let mut reader = QuickXmlReader::new(Reader::from_str(DOCUMENT)).filter_events(is_kept).map_events(rename);
+        let events = collect_events(&mut reader).unwrap();
+        assert_eq!(names(&events), ["<entry>", "<entry>", "Text: \"2\"", "</entry>", "</entry>"]);
+    }
+}
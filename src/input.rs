@@ -0,0 +1,188 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+#[cfg(feature = "gzip")]
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::{DecodingReader, Error, Result};
+
+/// Opens an input for reading.
+///
+/// `-` stands for the standard input.
+/// The input is converted to UTF-8, see `DecodingReader` for the supported encodings.
+/// The detected encoding is available with `DecodingReader::encoding`.
+/// With the `gzip` feature, gzip-compressed input is decompressed first.
+pub fn open_input(path: &str) -> Result<DecodingReader<Box<dyn BufRead>>> {
+    let input: Box<dyn BufRead> = if path == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file = File::open(path).map_err(|error| Error::Open { path: path.to_string(), error })?;
+        Box::new(BufReader::new(file))
+    };
+
+    DecodingReader::new(decompress(input)?)
+}
+
+// Gzip is detected by the magic bytes rather than the extension, so piped input works too.
+#[cfg(feature = "gzip")]
+fn decompress(mut input: Box<dyn BufRead>) -> Result<Box<dyn BufRead>> {
+    let mut magic = Vec::new();
+    input.by_ref().take(2).read_to_end(&mut magic)?;
+
+    let is_gzip = magic == [0x1f, 0x8b];
+    let input = io::Cursor::new(magic).chain(input);
+    if is_gzip {
+        // Like `gunzip`, reads all members of a concatenated file.
+        Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(input))))
+    } else {
+        Ok(Box::new(input))
+    }
+}
+
+// Gzip input is rejected by `DecodingReader` instead.
+#[cfg(not(feature = "gzip"))]
+fn decompress(input: Box<dyn BufRead>) -> Result<Box<dyn BufRead>> {
+    Ok(input)
+}
+
+/// Expands `*` and `?` wildcards in a path, like `data/*.xml`, into sorted paths of existing files.
+///
+/// Wildcards can be used in any component, but don't match `/` and a leading `.` of hidden files.
+/// Useful on platforms where the shell doesn't expand them, like Windows.
+/// A path without wildcards is returned as is, even when it doesn't exist.
+pub fn expand_glob(pattern: &str) -> Result<Vec<String>> {
+    if !has_wildcards(pattern) {
+        return Ok(vec![pattern.to_string()]);
+    }
+
+    let mut matches = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !has_wildcards(&part) {
+            for path in &mut matches {
+                path.push(component);
+            }
+            continue;
+        }
+
+        let mut next = Vec::new();
+        for dir in &matches {
+            let entries = match fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) {
+                Ok(entries) => entries,
+                // Not a directory, so nothing inside of it matches.
+                Err(_) => continue,
+            };
+
+            for entry in entries {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') && !part.starts_with('.') {
+                    continue;
+                }
+
+                if matches_wildcards(&part, &name) {
+                    next.push(dir.join(name));
+                }
+            }
+        }
+
+        next.sort();
+        matches = next;
+    }
+
+    Ok(matches.into_iter().filter(|path| path.is_file()).map(|path| path.to_string_lossy().into_owned()).collect())
+}
+
+fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+// `*` matches any number of characters and `?` a single one.
+fn matches_wildcards(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Positions after the last `*` and of the name part it currently covers.
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p + 1, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the `*` cover one more character.
+            p = star_p;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn read_input(path: &str) -> Result<String> {
+        let mut text = String::new();
+        open_input(path)?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompresses_gzip_input() {
+        let text = read_input("data/small.xml.gz").unwrap();
+        assert!(text.contains("<project name=\"project-name\">"));
+        assert_eq!(text, read_input("data/small.xml").unwrap());
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn rejects_gzip_input() {
+        match read_input("data/small.xml.gz") {
+            Err(Error::Compressed) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn expands_wildcards() {
+        assert_eq!(expand_glob("data/utf*.xml").unwrap(), ["data/utf16be.xml", "data/utf16le.xml", "data/utf32le.xml"]);
+        assert_eq!(expand_glob("data/utf?6??.xml").unwrap(), ["data/utf16be.xml", "data/utf16le.xml"]);
+        assert_eq!(expand_glob("d?t*/small.xml").unwrap(), ["data/small.xml"]);
+    }
+
+    #[test]
+    fn expands_to_nothing_without_matches() {
+        assert!(expand_glob("data/*.json").unwrap().is_empty());
+        assert!(expand_glob("missing/*.xml").unwrap().is_empty());
+        // Directories aren't inputs.
+        assert!(expand_glob("dat?").unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_literal_paths() {
+        assert_eq!(expand_glob("data/small.xml").unwrap(), ["data/small.xml"]);
+        assert_eq!(expand_glob("data/missing.xml").unwrap(), ["data/missing.xml"]);
+    }
+
+    #[test]
+    fn matches_wildcards_in_names() {
+        assert!(matches_wildcards("*", ""));
+        assert!(matches_wildcards("a*b*c", "abbbc"));
+        assert!(matches_wildcards("a*b*c", "a-b-c"));
+        assert!(!matches_wildcards("a*b*c", "a-b-cd"));
+        assert!(matches_wildcards("?.xml", "a.xml"));
+        assert!(!matches_wildcards("?.xml", ".xml"));
+        assert!(!matches_wildcards("?.xml", "ab.xml"));
+        assert!(matches_wildcards("план?.*", "план1.xml"));
+    }
+}
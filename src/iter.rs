@@ -0,0 +1,82 @@
+use std::io::BufRead;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::error::LineCounter;
+use crate::{Error, Result};
+
+/// A quick_xml event that doesn't borrow the reader buffer.
+pub type OwnedEvent = Event<'static>;
+
+/// An iterator over namespaced quick_xml events that owns the read buffers.
+///
+/// Each event is copied out of the reused buffer, which costs an allocation per event
+/// and per resolved namespace. The borrowed `Reader::read_namespaced_event` API avoids this,
+/// so it should be preferred in hot loops.
+///
+/// Yields events until `Event::Eof`, which is not yielded itself.
+/// Parsing errors are reported as `Error::Syntax`, after which the iterator is exhausted.
+pub struct EventIter<'a, R: BufRead> {
+    reader: &'a mut Reader<R>,
+    buf: Vec<u8>,
+    ns_buf: Vec<u8>,
+    lines: LineCounter,
+    done: bool,
+}
+
+impl<'a, R: BufRead> EventIter<'a, R> {
+    /// Creates a new iterator over the `reader` events.
+    pub fn new(reader: &'a mut Reader<R>) -> Self {
+        EventIter {
+            reader,
+            buf: Vec::new(),
+            ns_buf: Vec::new(),
+            lines: LineCounter::default(),
+            done: false,
+        }
+    }
+
+    /// Returns the underlying reader. Required for decoding.
+    pub fn reader(&self) -> &Reader<R> {
+        self.reader
+    }
+
+    /// Returns the line of the last read event.
+    pub fn line(&self) -> usize {
+        self.lines.line()
+    }
+
+    pub(crate) fn lines(&self) -> &LineCounter {
+        &self.lines
+    }
+}
+
+impl<'a, R: BufRead> Iterator for EventIter<'a, R> {
+    type Item = Result<(Option<Vec<u8>>, OwnedEvent)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        // Lines are counted on the next read, because the previous event was copied out of the buffer.
+        self.lines.consume(&self.buf);
+        self.buf.clear();
+
+        match self.reader.read_namespaced_event(&mut self.buf, &mut self.ns_buf) {
+            Ok((_, Event::Eof)) => {
+                self.done = true;
+                None
+            }
+            Ok((ns, event)) => {
+                self.lines.event_read(self.reader.buffer_position(), matches!(event, Event::Text(_)));
+                Some(Ok((ns.map(|ns| ns.to_vec()), event.into_owned())))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(Error::syntax(self.reader, &self.lines, &self.buf, e)))
+            }
+        }
+    }
+}
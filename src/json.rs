@@ -0,0 +1,633 @@
+use std::io::{self, Write};
+
+use crate::{Error, EventVisitor, Result};
+
+/// Writes the document as a JSON tree.
+///
+/// Each element becomes an object with `name`, `ns`, `attrs` and `children` keys,
+/// where `ns` is `null` for elements without a namespace and `attrs` is an object
+/// of attribute names and values.
+///
+/// `children` is an ordered array and not a map keyed by a tag name,
+/// so mixed content and repeated sibling names are preserved as is.
+/// Text and CDATA nodes become strings inside the `children` array, except for empty ones.
+///
+/// Only the root element is written. Comments, processing instructions,
+/// the declaration and DOCTYPE are skipped, since they have no place in this model.
+pub struct JsonVisitor<W: Write> {
+    writer: W,
+    // Whether the current `children` array already has an item.
+    has_children: Vec<bool>,
+}
+
+impl<W: Write> JsonVisitor<W> {
+    /// Creates a new visitor that writes to the `writer`.
+    pub fn new(writer: W) -> Self {
+        JsonVisitor {
+            writer,
+            has_children: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn begin_child(&mut self) -> io::Result<()> {
+        if let Some(has_children) = self.has_children.last_mut() {
+            if *has_children {
+                write!(self.writer, ",")?;
+            }
+            *has_children = true;
+        }
+
+        Ok(())
+    }
+
+    fn write_element_head(&mut self, ns: Option<&str>, local_name: &str,
+                          attributes: &[(String, String)]) -> io::Result<()> {
+        self.begin_child()?;
+
+        let w = &mut self.writer;
+        write!(w, "{{\"name\":")?;
+        write_json_string(w, local_name)?;
+        write!(w, ",\"ns\":")?;
+        write_optional_json_string(w, ns)?;
+
+        write!(w, ",\"attrs\":")?;
+        write_json_attributes(w, attributes)?;
+        write!(w, ",\"children\":[")
+    }
+
+    fn write_element_tail(&mut self) -> io::Result<()> {
+        write!(self.writer, "]}}")?;
+
+        if self.has_children.is_empty() {
+            writeln!(self.writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        // Text outside the root element can't be represented.
+        // quick_xml reports empty text between adjacent tags, which carries nothing.
+        if self.has_children.is_empty() || text.is_empty() {
+            return Ok(());
+        }
+
+        self.begin_child()?;
+        write_json_string(&mut self.writer, text)
+    }
+}
+
+impl<W: Write> EventVisitor for JsonVisitor<W> {
+    fn start(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.write_element_head(ns, local_name, attributes)?;
+        self.has_children.push(false);
+        Ok(())
+    }
+
+    fn empty(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.write_element_head(ns, local_name, attributes)?;
+        self.write_element_tail()?;
+        Ok(())
+    }
+
+    fn end(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str, _depth: usize) -> Result<()> {
+        self.has_children.pop();
+        self.write_element_tail()?;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.write_text(text)?;
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.write_text(text)?;
+        Ok(())
+    }
+}
+
+/// Writes each event as a JSON object on its own line, i.e. JSON Lines.
+///
+/// Each object has an `event` key with the event type and a `depth` key.
+/// Elements have `name` and `ns` keys, where `ns` is `null` for elements without a namespace.
+/// Start and Empty events also have `attrs`, an array of `[name, value]` pairs in document order.
+/// Text, CDATA, comments, processing instructions and DOCTYPE have a `value` key
+/// with the same unescaped text that `PrintVisitor` prints.
+pub struct JsonLinesVisitor<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesVisitor<W> {
+    /// Creates a new visitor that writes to the `writer`.
+    pub fn new(writer: W) -> Self {
+        JsonLinesVisitor { writer }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_element(&mut self, event: &str, ns: Option<&str>, local_name: &str,
+                     attributes: Option<&[(String, String)]>, depth: usize) -> io::Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"event\":\"{}\",\"name\":", event)?;
+        write_json_string(w, local_name)?;
+        write!(w, ",\"ns\":")?;
+        write_optional_json_string(w, ns)?;
+        write!(w, ",\"depth\":{}", depth)?;
+
+        if let Some(attributes) = attributes {
+            write!(w, ",\"attrs\":[")?;
+            for (i, (key, value)) in attributes.iter().enumerate() {
+                if i != 0 {
+                    write!(w, ",")?;
+                }
+
+                write!(w, "[")?;
+                write_json_string(w, key)?;
+                write!(w, ",")?;
+                write_json_string(w, value)?;
+                write!(w, "]")?;
+            }
+            write!(w, "]")?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    fn write_value(&mut self, event: &str, text: &str, depth: usize) -> io::Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"event\":\"{}\",\"depth\":{},\"value\":", event, depth)?;
+        write_json_string(w, text)?;
+        writeln!(w, "}}")
+    }
+}
+
+impl<W: Write> EventVisitor for JsonLinesVisitor<W> {
+    fn decl(&mut self, version: Option<&str>, encoding: Option<&str>, standalone: Option<&str>,
+            depth: usize) -> Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"event\":\"decl\",\"depth\":{},\"version\":", depth)?;
+        write_optional_json_string(w, version)?;
+        write!(w, ",\"encoding\":")?;
+        write_optional_json_string(w, encoding)?;
+        write!(w, ",\"standalone\":")?;
+        write_optional_json_string(w, standalone)?;
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    fn start(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.write_element("start", ns, local_name, Some(attributes), depth)?;
+        Ok(())
+    }
+
+    fn empty(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.write_element("empty", ns, local_name, Some(attributes), depth)?;
+        Ok(())
+    }
+
+    fn end(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str, depth: usize) -> Result<()> {
+        self.write_element("end", ns, local_name, None, depth)?;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("text", text, depth)?;
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("comment", text, depth)?;
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("cdata", text, depth)?;
+        Ok(())
+    }
+
+    fn pi(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("pi", text, depth)?;
+        Ok(())
+    }
+
+    fn doctype(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("doctype", text, depth)?;
+        Ok(())
+    }
+}
+
+/// Writes each event as a flat JSON object on its own line, i.e. newline-delimited JSON.
+///
+/// Each object has a `type` key with the event type and a `depth` key.
+/// Elements have `name` and `ns` keys, where `ns` is `null` for elements without a namespace.
+/// Unlike `JsonLinesVisitor`, attributes are inlined into Start and Empty objects
+/// as an `attrs` object of names and values, so a duplicate name keeps only the last value.
+/// Text, CDATA, comments, processing instructions and DOCTYPE have a `value` key.
+pub struct NdjsonVisitor<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonVisitor<W> {
+    /// Creates a new visitor that writes to the `writer`.
+    pub fn new(writer: W) -> Self {
+        NdjsonVisitor { writer }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_element(&mut self, kind: &str, ns: Option<&str>, local_name: &str,
+                     attributes: Option<&[(String, String)]>, depth: usize) -> io::Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"type\":\"{}\",\"name\":", kind)?;
+        write_json_string(w, local_name)?;
+        write!(w, ",\"depth\":{},\"ns\":", depth)?;
+        write_optional_json_string(w, ns)?;
+
+        if let Some(attributes) = attributes {
+            write!(w, ",\"attrs\":")?;
+            write_json_attributes(w, attributes)?;
+        }
+
+        writeln!(w, "}}")
+    }
+
+    fn write_value(&mut self, kind: &str, text: &str, depth: usize) -> io::Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"type\":\"{}\",\"depth\":{},\"value\":", kind, depth)?;
+        write_json_string(w, text)?;
+        writeln!(w, "}}")
+    }
+}
+
+impl<W: Write> EventVisitor for NdjsonVisitor<W> {
+    fn decl(&mut self, version: Option<&str>, encoding: Option<&str>, standalone: Option<&str>,
+            depth: usize) -> Result<()> {
+        let w = &mut self.writer;
+        write!(w, "{{\"type\":\"decl\",\"depth\":{},\"version\":", depth)?;
+        write_optional_json_string(w, version)?;
+        write!(w, ",\"encoding\":")?;
+        write_optional_json_string(w, encoding)?;
+        write!(w, ",\"standalone\":")?;
+        write_optional_json_string(w, standalone)?;
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    fn start(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.write_element("start", ns, local_name, Some(attributes), depth)?;
+        Ok(())
+    }
+
+    fn empty(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.write_element("empty", ns, local_name, Some(attributes), depth)?;
+        Ok(())
+    }
+
+    fn end(&mut self, ns: Option<&str>, _prefix: Option<&str>, local_name: &str, depth: usize) -> Result<()> {
+        self.write_element("end", ns, local_name, None, depth)?;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("text", text, depth)?;
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("comment", text, depth)?;
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("cdata", text, depth)?;
+        Ok(())
+    }
+
+    fn pi(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("pi", text, depth)?;
+        Ok(())
+    }
+
+    fn doctype(&mut self, text: &str, depth: usize) -> Result<()> {
+        self.write_value("doctype", text, depth)?;
+        Ok(())
+    }
+}
+
+// Writes attributes as an object. A duplicate name keeps only the last value.
+fn write_json_attributes(w: &mut dyn Write, attributes: &[(String, String)]) -> io::Result<()> {
+    write!(w, "{{")?;
+
+    let mut first = true;
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        if attributes[i + 1..].iter().any(|(k, _)| k == key) {
+            continue;
+        }
+
+        if !first {
+            write!(w, ",")?;
+        }
+        first = false;
+
+        write_json_string(w, key)?;
+        write!(w, ":")?;
+        write_json_string(w, value)?;
+    }
+
+    write!(w, "}}")
+}
+
+fn write_optional_json_string(w: &mut dyn Write, s: Option<&str>) -> io::Result<()> {
+    match s {
+        Some(s) => write_json_string(w, s),
+        None => write!(w, "null"),
+    }
+}
+
+/// Writes a quoted and escaped JSON string.
+pub(crate) fn write_json_string(w: &mut dyn Write, s: &str) -> io::Result<()> {
+    write!(w, "\"")?;
+
+    for c in s.chars() {
+        match c {
+            '"' => write!(w, "\\\"")?,
+            '\\' => write!(w, "\\\\")?,
+            '\n' => write!(w, "\\n")?,
+            '\r' => write!(w, "\\r")?,
+            '\t' => write!(w, "\\t")?,
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{}", c)?,
+        }
+    }
+
+    write!(w, "\"")
+}
+
+/// A parsed JSON value.
+///
+/// Object members keep the document order.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Returns a member of an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        match *self {
+            JsonValue::Object(ref members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns a string value.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match *self {
+            JsonValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+// The same limit as in serde_json, so deeply nested input fails instead of overflowing the stack.
+const MAX_JSON_DEPTH: usize = 128;
+
+/// Parses a JSON document.
+///
+/// Objects and arrays can be nested up to 128 levels deep.
+pub(crate) fn parse_json(text: &str) -> Result<JsonValue> {
+    let mut parser = JsonParser { text, pos: 0, depth: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos != text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+    // The number of objects and arrays being parsed.
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::Json { position: self.pos, message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn consume(&mut self, c: u8) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c as char)));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn consume_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue> {
+        if !self.text[self.pos..].starts_with(literal) {
+            return Err(self.error("unexpected character"));
+        }
+
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => self.parse_nested(Self::parse_object),
+            Some(b'[') => self.parse_nested(Self::parse_array),
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b'n') => self.consume_literal("null", JsonValue::Null),
+            Some(b't') => self.consume_literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.consume_literal("false", JsonValue::Bool(false)),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_nested(&mut self, parse: fn(&mut Self) -> Result<JsonValue>) -> Result<JsonValue> {
+        if self.depth == MAX_JSON_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue> {
+        self.consume(b'{')?;
+        let mut members = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.consume(b':')?;
+            members.push((key, self.parse_value()?));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue> {
+        self.consume(b'[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue> {
+        let start = self.pos;
+        while let Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E') | Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+
+        match self.text[start..self.pos].parse() {
+            Ok(n) => Ok(JsonValue::Number(n)),
+            Err(_) => Err(self.error("invalid number")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected a string"));
+        }
+        self.pos += 1;
+
+        let mut s = String::new();
+        loop {
+            let c = match self.text[self.pos..].chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += c.len_utf8();
+
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.peek() {
+                        Some(c) => c,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.pos += 1;
+
+                    match c {
+                        b'"' => s.push('"'),
+                        b'\\' => s.push('\\'),
+                        b'/' => s.push('/'),
+                        b'b' => s.push('\u{8}'),
+                        b'f' => s.push('\u{c}'),
+                        b'n' => s.push('\n'),
+                        b'r' => s.push('\r'),
+                        b't' => s.push('\t'),
+                        b'u' => s.push(self.parse_unicode_escape()?),
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    // Parses the `XXXX` part of `\uXXXX`, including a following low surrogate.
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let high = self.parse_hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return std::char::from_u32(high).ok_or_else(|| self.error("invalid escape"));
+        }
+
+        if !self.text[self.pos..].starts_with("\\u") {
+            return Err(self.error("unpaired surrogate"));
+        }
+        self.pos += 2;
+
+        let low = self.parse_hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate"));
+        }
+
+        let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        std::char::from_u32(c).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let hex = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("invalid escape"))?;
+        let n = u32::from_str_radix(hex, 16).map_err(|_| self.error("invalid escape"))?;
+        self.pos += 4;
+        Ok(n)
+    }
+}
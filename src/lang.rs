@@ -0,0 +1,121 @@
+use crate::event::Event;
+
+const XML_LANG: &[u8] = b"xml:lang";
+
+/// Languages of the current parse position, as established by `xml:lang` attributes.
+///
+/// An element without `xml:lang` inherits the language of its parent,
+/// while an empty `xml:lang=""` means that the language is unknown.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct LangStack {
+    // A language of each open element.
+    langs: Vec<Option<String>>,
+    // An Empty element is both opened and closed by a single event,
+    // so its language stays on the stack until the next event.
+    pending_pop: bool,
+}
+
+impl LangStack {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        LangStack::default()
+    }
+
+    /// Updates the stack with an event.
+    ///
+    /// Start and Empty events push a language, End events pop it.
+    pub fn update(&mut self, event: &Event) {
+        if self.pending_pop {
+            self.langs.pop();
+            self.pending_pop = false;
+        }
+
+        match *event {
+            Event::Start { ref attrs, .. } | Event::Empty { ref attrs, .. } => {
+                let lang = match attrs.iter().find(|a| a.key.as_ref() == XML_LANG) {
+                    Some(a) if a.value.is_empty() => None,
+                    Some(a) => Some(a.value.to_string()),
+                    None => self.current_lang().map(str::to_string),
+                };
+
+                self.langs.push(lang);
+                self.pending_pop = matches!(*event, Event::Empty { .. });
+            }
+            Event::End { .. } => {
+                self.langs.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the language of the current element, as written in the document.
+    ///
+    /// A just visited Empty element is still current.
+    pub fn current_lang(&self) -> Option<&str> {
+        self.langs.last().and_then(Option::as_deref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::event::XmlSource;
+    use crate::QuickXmlReader;
+
+    #[test]
+    fn tracks_nested_overrides() {
+        let text = std::fs::read_to_string("data/lang.xml").unwrap();
+        let mut reader = QuickXmlReader::new(Reader::from_str(&text));
+        let mut stack = LangStack::new();
+        let mut langs = Vec::new();
+
+        loop {
+            let e = reader.next_event().unwrap();
+            stack.update(&e);
+            match e {
+                Event::Eof => break,
+                Event::Text(ref text) if !text.trim().is_empty() => {
+                    langs.push((text.to_string(), stack.current_lang().map(str::to_string)));
+                }
+                Event::Empty { ref name, .. } => langs.push((format!("<{}/>", name.escape_ascii()), None)),
+                _ => {}
+            }
+        }
+
+        let expected = [
+            ("Alice in Wonderland", Some("en")),
+            ("Alice im Wunderland", Some("de")),
+            ("Bonjour", Some("fr-CA")),
+            ("Unknown", None),
+            ("<br/>", None),
+            ("language", None),
+            ("Back to English", Some("en")),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(t, l)| (t.to_string(), l.map(str::to_string))).collect();
+        assert_eq!(langs, expected);
+        assert_eq!(stack.current_lang(), None);
+    }
+
+    #[test]
+    fn keeps_empty_element_current() {
+        let mut stack = LangStack::new();
+        let mut reader = QuickXmlReader::new(Reader::from_str("<a xml:lang=\"en\"><b xml:lang=\"de\"/>text</a>"));
+        let mut langs = Vec::new();
+
+        loop {
+            let e = reader.next_event().unwrap();
+            stack.update(&e);
+            match e {
+                Event::Eof => break,
+                // quick_xml reports empty text between adjacent tags.
+                Event::Text(ref text) if text.is_empty() => {}
+                _ => langs.push(stack.current_lang().map(str::to_string)),
+            }
+        }
+
+        let expected = [Some("en"), Some("de"), Some("en"), None];
+        assert_eq!(langs, expected.iter().map(|l| l.map(str::to_string)).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,414 @@
+use std::io::BufRead;
+use std::str;
+
+use quick_xml::Reader;
+use quick_xml::events::{Event, BytesStart, BytesText};
+
+use crate::entity::DocumentEntities;
+
+mod attributes;
+#[cfg(feature = "async")]
+mod async_reader;
+#[macro_use]
+mod backend;
+mod base;
+mod canonical;
+#[cfg(feature = "serde_json")]
+mod convert;
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+mod dom;
+mod dump;
+mod encoding;
+mod entity;
+mod error;
+mod input;
+pub mod event;
+mod filter;
+mod iter;
+mod json;
+mod lang;
+mod limit;
+mod minify;
+mod model;
+mod multi;
+mod namespace;
+pub mod node;
+mod path;
+pub mod prelude;
+mod pretty;
+mod print;
+mod recorder;
+#[cfg(feature = "roxmltree")]
+mod roxml;
+mod sax;
+mod select;
+mod selector;
+mod serialize;
+mod split;
+mod stats;
+#[cfg(feature = "sxd_document")]
+mod sxd;
+mod text;
+mod tree;
+mod validate;
+mod visitor;
+mod whitespace;
+#[cfg(feature = "xml_rs")]
+mod xml_rs;
+mod xpath;
+
+pub use crate::attributes::AttributeMap;
+#[cfg(feature = "async")]
+pub use crate::async_reader::{parse_async, walk_async, AsyncXmlReader};
+pub use crate::backend::{create_reader_for_backend, BackendKind, DynXmlReader, XmlBackend, QuickXml, QuickXmlReader};
+#[cfg(feature = "roxmltree")]
+pub use crate::backend::Roxmltree;
+#[cfg(feature = "sxd_document")]
+pub use crate::backend::SxdDocument;
+#[cfg(feature = "xml_rs")]
+pub use crate::backend::XmlRs;
+pub use crate::base::BaseUriStack;
+pub use crate::canonical::CanonicalSerializer;
+#[cfg(feature = "serde_json")]
+pub use crate::convert::{json_to_xml, xml_to_json, xml_to_json_with_convention, JsonConvention};
+#[cfg(feature = "serde")]
+pub use crate::de::from_reader;
+pub use crate::diff::{diff_events, diff_events_with_options, DiffItem, DiffOptions};
+pub use crate::dom::{parse_dom, parse_dom_fragment, Element, Node};
+pub use crate::dump::{dump_events, DumpFormat, DumpOptions};
+pub use crate::encoding::{unescape, DecodingReader, Encoding};
+pub use crate::entity::{unescape_with_entities, unescape_with_limits, EntityLimits};
+pub use crate::error::{Error, Result};
+pub use crate::input::{expand_glob, open_input};
+pub use crate::event::XmlSource;
+pub use crate::filter::{FilteringReader, TransformingReader};
+pub use crate::iter::{EventIter, OwnedEvent};
+pub use crate::json::{JsonLinesVisitor, JsonVisitor, NdjsonVisitor};
+pub use crate::lang::LangStack;
+pub use crate::limit::{skip_element, DepthLimitedReader, DepthLimitError};
+pub use crate::minify::Minifier;
+pub use crate::model::{collect_events, quick_xml_events, DumpEvent};
+#[cfg(feature = "xml_rs")]
+pub use crate::model::xml_rs_events;
+pub use crate::multi::MultiDocumentReader;
+pub use crate::namespace::NamespaceRegistry;
+pub use crate::path::ElementPath;
+pub use crate::pretty::{LineEnding, PrettyPrinter};
+pub use crate::print::PrintVisitor;
+pub use crate::recorder::{load_from_json, save_to_json, Replay, XmlEventRecorder};
+#[cfg(feature = "roxmltree")]
+pub use crate::roxml::RoxmltreeReader;
+pub use crate::sax::{dispatch, SaxHandler};
+pub use crate::select::{select, Selected};
+pub use crate::selector::PathSelector;
+pub use crate::serialize::{write_canonical, write_events, write_minified, write_pretty, write_round_trip, EventWriter};
+pub use crate::split::{split_at_depth, DocumentSplitter};
+pub use crate::stats::{collect_statistics, DocumentStatistics};
+#[cfg(feature = "sxd_document")]
+pub use crate::sxd::SxdReader;
+pub use crate::text::{collect_text_content, TextVisitor, ValueVisitor};
+pub use crate::tree::write_tree;
+pub use crate::validate::validate_no_duplicate_attrs;
+pub use crate::visitor::EventVisitor;
+pub use crate::whitespace::WhitespaceMode;
+#[cfg(feature = "xml_rs")]
+pub use crate::xml_rs::{walk_xml_rs_events, xml_rs_config, XmlRsReader};
+pub use crate::xpath::{evaluate_xpath, XPathError, XPathNode, XPathResult};
+
+/// Reads all events from the `reader` and passes them to the `visitor`.
+///
+/// Names and namespaces are passed already resolved and decoded.
+/// Text and attribute values are unescaped, including the entities declared in the `DOCTYPE`.
+/// Parsing errors are reported as `Error::Syntax`, an extra End event as `Error::UnexpectedEnd`
+/// and an element that is still open at the end of the document as `Error::Unclosed`.
+pub fn walk_events<R: BufRead>(reader: &mut Reader<R>, visitor: &mut impl EventVisitor) -> Result<()> {
+    let mut events = EventIter::new(reader);
+    let mut depth = 0;
+    let mut open = Vec::new();
+    let mut entities = DocumentEntities::default();
+
+    // Not a `for` loop, since the reader is required for decoding.
+    while let Some(event) = events.next() {
+        let (ns, event) = event?;
+        declare_entities(&event, &mut entities);
+        visit_event_with_entities(events.reader(), &entities, ns.as_deref(), &event, &mut depth, visitor)
+            .map_err(|e| e.in_event(events.lines()))?;
+        track_open_elements(&event, &mut open);
+    }
+
+    check_unclosed(&open)
+}
+
+/// Reads all events from the `reader` and passes them to the `visitor`,
+/// skipping malformed sections.
+///
+/// When the `reader` returns an error, the error is stored and parsing resumes
+/// from the next `<`. Events that were already passed to the `visitor` are not reverted.
+///
+/// Returns all recovered errors. If there are more than `max_errors` of them,
+/// the last one is returned as an error instead.
+pub fn walk_events_recovering<R: BufRead>(
+    reader: &mut Reader<R>,
+    visitor: &mut impl EventVisitor,
+    max_errors: usize,
+) -> Result<Vec<Error>> {
+    let mut buf = Vec::new();
+    let mut ns_buf = Vec::new();
+
+    let mut depth = 0;
+    let mut open = Vec::new();
+    let mut lines = error::LineCounter::default();
+    let mut errors = Vec::new();
+    let mut entities = DocumentEntities::default();
+
+    loop {
+        // quick_xml resets its state to the text one on the next read,
+        // so a failed tag is skipped up to the next `<`.
+        lines.consume(&buf);
+        buf.clear();
+        let event = match reader.read_namespaced_event(&mut buf, &mut ns_buf) {
+            Ok(event) => event,
+            Err(e) => {
+                let e = Error::syntax(reader, &lines, &buf, e);
+                if errors.len() == max_errors {
+                    return Err(e);
+                }
+
+                errors.push(e);
+                lines.error_skipped(&buf);
+                continue;
+            }
+        };
+        lines.event_read(reader.buffer_position(), matches!(event, (_, Event::Text(_))));
+
+        declare_entities(&event.1, &mut entities);
+        match event {
+            (_, Event::Eof) => break,
+            (ns, ref e) => match visit_event_with_entities(reader, &entities, ns, e, &mut depth, visitor)
+                .map_err(|e| e.in_event(&lines))
+            {
+                Ok(()) => track_open_elements(e, &mut open),
+                Err(e @ Error::UnexpectedEnd { .. }) => {
+                    if errors.len() == max_errors {
+                        return Err(e);
+                    }
+
+                    errors.push(e);
+                }
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    if let Err(e) = check_unclosed(&open) {
+        if errors.len() == max_errors {
+            return Err(e);
+        }
+
+        errors.push(e);
+    }
+
+    Ok(errors)
+}
+
+/// Passes a single namespaced event to the `visitor`.
+///
+/// The `reader` is used only for decoding. `depth` is updated on Start/End events.
+/// Only the predefined entities are unescaped, since a single event doesn't know the `DOCTYPE`.
+///
+/// Returns `Error::UnexpectedEnd` for an End event at the zero depth.
+pub fn visit_event<R: BufRead>(
+    reader: &Reader<R>,
+    ns: Option<&[u8]>,
+    event: &Event,
+    depth: &mut usize,
+    visitor: &mut impl EventVisitor,
+) -> Result<()> {
+    visit_event_with_entities(reader, &DocumentEntities::default(), ns, event, depth, visitor)
+}
+
+fn declare_entities(event: &Event, entities: &mut DocumentEntities) {
+    if let Event::DocType(ref e) = *event {
+        entities.declare(e);
+    }
+}
+
+fn visit_event_with_entities<R: BufRead>(
+    reader: &Reader<R>,
+    entities: &DocumentEntities,
+    ns: Option<&[u8]>,
+    event: &Event,
+    depth: &mut usize,
+    visitor: &mut impl EventVisitor,
+) -> Result<()> {
+    match *event {
+        Event::Start(ref e) => {
+            let attrs = decode_attributes(reader, entities, e)?;
+            let (prefix, name) = decode_name(e.name())?;
+            visitor.start(decode_ns(ns)?, prefix, name, &attrs, *depth)?;
+            *depth += 1;
+        }
+        Event::Empty(ref e) => {
+            let attrs = decode_attributes(reader, entities, e)?;
+            let (prefix, name) = decode_name(e.name())?;
+            visitor.empty(decode_ns(ns)?, prefix, name, &attrs, *depth)?;
+        }
+        Event::End(ref e) => {
+            if *depth == 0 {
+                return Err(Error::UnexpectedEnd {
+                    name: str::from_utf8(e.local_name())?.to_string(),
+                    position: reader.buffer_position(),
+                });
+            }
+
+            *depth -= 1;
+            let (prefix, name) = decode_name(e.name())?;
+            visitor.end(decode_ns(ns)?, prefix, name, *depth)?;
+        }
+        Event::Comment(ref e) => {
+            visitor.comment(reader.decode(e)?, *depth)?;
+        }
+        Event::CData(ref e) => {
+            visitor.cdata(reader.decode(e)?, *depth)?;
+        }
+        Event::PI(ref e) => {
+            visitor.pi(reader.decode(e)?, *depth)?;
+        }
+        Event::DocType(ref e) => {
+            visitor.doctype(reader.decode(e)?, *depth)?;
+        }
+        Event::Decl(ref e) => {
+            let version = match e.version() {
+                Ok(v) => Some(str::from_utf8(v.as_ref())?.to_string()),
+                Err(_) => None,
+            };
+
+            let encoding = match e.encoding() {
+                Some(Ok(v)) => Some(str::from_utf8(v.as_ref())?.to_string()),
+                _ => None,
+            };
+
+            let standalone = match e.standalone() {
+                Some(Ok(v)) => Some(str::from_utf8(v.as_ref())?.to_string()),
+                _ => None,
+            };
+
+            visitor.decl(version.as_deref(), encoding.as_deref(), standalone.as_deref(), *depth)?;
+        }
+        Event::Text(ref e) => {
+            visitor.text(&unescape_and_decode(reader, entities, e)?, *depth)?;
+        }
+        Event::Eof => {}
+    }
+
+    Ok(())
+}
+
+fn track_open_elements(event: &Event, open: &mut Vec<Vec<u8>>) {
+    match *event {
+        Event::Start(ref e) => open.push(e.local_name().to_vec()),
+        Event::End(_) => {
+            open.pop();
+        }
+        _ => {}
+    }
+}
+
+fn check_unclosed(open: &[Vec<u8>]) -> Result<()> {
+    match open.last() {
+        Some(name) => Err(Error::Unclosed { name: String::from_utf8_lossy(name).into_owned() }),
+        None => Ok(()),
+    }
+}
+
+fn decode_ns(ns: Option<&[u8]>) -> Result<Option<&str>> {
+    match ns {
+        Some(ns) => Ok(Some(str::from_utf8(ns)?)),
+        None => Ok(None),
+    }
+}
+
+// Splits a qualified name into a prefix and a local name.
+fn decode_name(name: &[u8]) -> Result<(Option<&str>, &str)> {
+    match event::name_prefix(name) {
+        Some(prefix) => Ok((Some(str::from_utf8(prefix)?), str::from_utf8(&name[prefix.len() + 1..])?)),
+        None => Ok((None, str::from_utf8(name)?)),
+    }
+}
+
+fn decode_attributes<R: BufRead>(r: &Reader<R>, entities: &DocumentEntities,
+                                 e: &BytesStart) -> Result<Vec<(String, String)>> {
+    let mut attrs = Vec::new();
+    for a in e.attributes() {
+        let a = a?;
+        attrs.push((str::from_utf8(a.key)?.to_string(), unescape_and_decode(r, entities, &a.value)?));
+    }
+
+    Ok(attrs)
+}
+
+// Without declared entities, quick_xml can unescape on its own.
+fn unescape_and_decode<R: BufRead>(r: &Reader<R>, entities: &DocumentEntities,
+                                   raw: &[u8]) -> Result<String> {
+    if entities.is_empty() {
+        Ok(BytesText::from_escaped(raw).unescape_and_decode(r)?)
+    } else {
+        Ok(r.decode(&entities.unescape(raw)?)?.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Noop;
+
+    impl EventVisitor for Noop {}
+
+    fn extract_text<R: BufRead>(input: R) -> String {
+        let mut visitor = TextVisitor::new(Vec::new()).separator("|");
+        walk_events(&mut Reader::from_reader(input), &mut visitor).unwrap();
+        String::from_utf8(visitor.into_inner()).unwrap()
+    }
+
+    fn walk(text: &str) -> Result<()> {
+        let mut reader = Reader::from_str(text);
+        reader.check_end_names(false);
+        walk_events(&mut reader, &mut Noop)
+    }
+
+    #[test]
+    fn reports_extra_end_tag() {
+        match walk("<a></a></b>") {
+            Err(Error::UnexpectedEnd { ref name, .. }) => assert_eq!(name, "b"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn reports_missing_end_tag() {
+        match walk("<a><b></b>") {
+            Err(Error::Unclosed { ref name }) => assert_eq!(name, "a"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn accepts_well_formed_document() {
+        walk("<a><b/><c>text</c></a>").unwrap();
+    }
+
+    #[test]
+    fn reads_from_any_buf_read() {
+        let path = "data/small.xml";
+        let text = std::fs::read(path).unwrap();
+        let expected = extract_text(std::io::BufReader::new(std::fs::File::open(path).unwrap()));
+
+        assert!(!expected.is_empty());
+        assert_eq!(extract_text(text.as_slice()), expected);
+        assert_eq!(extract_text(std::io::Cursor::new(text.clone())), expected);
+        assert_eq!(extract_text(open_input(path).unwrap()), expected);
+    }
+}
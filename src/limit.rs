@@ -0,0 +1,153 @@
+use std::fmt;
+
+use crate::event::{Event, XmlSource};
+use crate::Result;
+
+/// An element nesting exceeded the limit set by `DepthLimitedReader`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DepthLimitError {
+    /// The limit.
+    pub max_depth: usize,
+    /// A local name of the element that exceeded the limit.
+    pub name: String,
+}
+
+impl fmt::Display for DepthLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "element '{}' exceeds the maximum depth of {}", self.name, self.max_depth)
+    }
+}
+
+impl std::error::Error for DepthLimitError {}
+
+/// A reader that stops parsing once the element nesting exceeds `max_depth`.
+///
+/// The root element has a depth of 1, so `max_depth = 0` rejects any element.
+/// Empty elements don't change the depth, but are checked just like the Start ones.
+pub struct DepthLimitedReader<S: XmlSource> {
+    source: S,
+    max_depth: usize,
+    depth: usize,
+}
+
+impl<S: XmlSource> DepthLimitedReader<S> {
+    /// Creates a new reader.
+    pub fn new(source: S, max_depth: usize) -> Self {
+        DepthLimitedReader {
+            source,
+            max_depth,
+            depth: 0,
+        }
+    }
+
+    /// Returns the current depth.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the underlying source.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: XmlSource> XmlSource for DepthLimitedReader<S> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        let event = self.source.next_event()?;
+
+        match event {
+            Event::Start { ref name, .. } | Event::Empty { ref name, .. } => {
+                if self.depth >= self.max_depth {
+                    return Err(DepthLimitError {
+                        max_depth: self.max_depth,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                    }.into());
+                }
+
+                if let Event::Start { .. } = event {
+                    self.depth += 1;
+                }
+            }
+            Event::End { .. } => {
+                self.depth = self.depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+
+        Ok(event)
+    }
+}
+
+/// Skips the rest of the current element.
+///
+/// Must be called right after the Start event of an element.
+/// Reads everything up to and including the matching End event,
+/// counting nested Start/End pairs, so the next event is the following sibling of the element.
+pub fn skip_element<S: XmlSource>(source: &mut S) -> Result<()> {
+    let mut depth = 0;
+
+    loop {
+        match source.next_event()? {
+            Event::Start { .. } => {
+                depth += 1;
+            }
+            Event::End { .. } => {
+                if depth == 0 {
+                    return Ok(());
+                }
+
+                depth -= 1;
+            }
+            Event::Eof => {
+                return Err(quick_xml::Error::UnexpectedEof("end of a skipped element".to_string()).into());
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{Error, QuickXmlReader};
+
+    fn read_all(text: &str, max_depth: usize) -> Result<usize> {
+        let mut reader = DepthLimitedReader::new(QuickXmlReader::new(Reader::from_str(text)), max_depth);
+        let mut max = 0;
+        loop {
+            if let Event::Eof = reader.next_event()? {
+                return Ok(max);
+            }
+
+            max = max.max(reader.depth());
+        }
+    }
+
+    #[test]
+    fn rejects_deeper_elements() {
+        let text = "<l1><l2><l3><l4><l5/></l4></l3></l2></l1>";
+        match read_all(text, 3) {
+            Err(Error::DepthLimit(e)) => assert_eq!(e, DepthLimitError { max_depth: 3, name: "l4".to_string() }),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_elements_within_the_limit() {
+        let text = "<l1><l2><l3><l4><l5/></l4></l3></l2></l1>";
+        assert_eq!(read_all(text, 5).unwrap(), 4);
+    }
+
+    #[test]
+    fn checks_empty_elements() {
+        assert!(read_all("<a><b/></a>", 1).is_err());
+        assert_eq!(read_all("<a/>", 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn tracks_depth_of_siblings() {
+        assert_eq!(read_all("<a><b><c/></b><b><c/></b></a>", 3).unwrap(), 2);
+    }
+}
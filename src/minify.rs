@@ -0,0 +1,227 @@
+use std::io::Write;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Writer;
+
+use crate::serialize::{is_blank, xml_space, ContentBuffer};
+use crate::{EventWriter, Result};
+
+/// Writes quick_xml events without insignificant whitespace.
+///
+/// Whitespace-only text is dropped, unless it's inside an element with `xml:space="preserve"`
+/// or an element with significant text, i.e. non-whitespace text or CDATA, so mixed content is kept as is.
+/// A nested `xml:space="default"` drops whitespace again.
+/// Significant text can follow any number of child elements, so the events of an element
+/// are buffered until its first significant text or its end.
+///
+/// Elements without content are collapsed into empty-element tags, so `<a></a>` becomes `<a/>`.
+/// Everything else, including the order of attributes, is written as is.
+pub struct Minifier<W: Write> {
+    writer: Writer<W>,
+    strip_comments: bool,
+    strip_pis: bool,
+    // A start tag that becomes an empty-element tag when the element ends right after it.
+    pending_start: Option<BytesStart<'static>>,
+    // Whether whitespace is kept inside of each open element.
+    verbatim: Vec<bool>,
+    // Events of an element whose content isn't known to be mixed yet.
+    pending: ContentBuffer,
+}
+
+impl<W: Write> Minifier<W> {
+    /// Creates a new minifier that writes to the `writer` and keeps comments and processing instructions.
+    pub fn new(writer: W) -> Self {
+        Minifier {
+            writer: Writer::new(writer),
+            strip_comments: false,
+            strip_pis: false,
+            pending_start: None,
+            verbatim: Vec::new(),
+            pending: ContentBuffer::default(),
+        }
+    }
+
+    /// Sets whether comments are removed.
+    pub fn strip_comments(mut self, strip: bool) -> Self {
+        self.strip_comments = strip;
+        self
+    }
+
+    /// Sets whether processing instructions are removed. The XML declaration is always kept.
+    pub fn strip_pis(mut self, strip: bool) -> Self {
+        self.strip_pis = strip;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    /// Writes a single event.
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        if !self.pending.is_empty() {
+            if self.pending.push(event) {
+                for (event, is_mixed) in self.pending.take() {
+                    self.write_decided(&event, is_mixed)?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        // Whitespace inside of a verbatim element is kept anyway.
+        if let Event::Start(ref e) = *event {
+            if !xml_space(e)?.unwrap_or(self.is_verbatim()) {
+                self.pending.push(event);
+                return Ok(());
+            }
+        }
+
+        self.write_decided(event, false)
+    }
+
+    // Writes an event whose parent is known to have mixed content or not.
+    // `is_mixed` is set for a Start event of an element with significant text.
+    fn write_decided(&mut self, event: &Event, is_mixed: bool) -> Result<()> {
+        // Removed nodes don't count as content, so their parent can still be collapsed.
+        let is_removed = match *event {
+            Event::Text(ref e) => e.escaped().is_empty() || (is_blank(e) && !self.is_verbatim()),
+            Event::Comment(_) => self.strip_comments,
+            Event::PI(_) => self.strip_pis,
+            Event::Eof => true,
+            _ => false,
+        };
+
+        if is_removed {
+            return Ok(());
+        }
+
+        if let Some(start) = self.pending_start.take() {
+            if let Event::End(_) = *event {
+                self.verbatim.pop();
+                self.writer.write_event(Event::Empty(start))?;
+                return Ok(());
+            }
+
+            self.writer.write_event(Event::Start(start))?;
+        }
+
+        match *event {
+            Event::Start(ref e) => {
+                let is_verbatim = is_mixed || xml_space(e)?.unwrap_or(self.is_verbatim());
+                self.verbatim.push(is_verbatim);
+                self.pending_start = Some(e.clone().into_owned());
+                return Ok(());
+            }
+            Event::End(_) => {
+                self.verbatim.pop();
+            }
+            _ => {}
+        }
+
+        self.writer.write_event(event)?;
+        Ok(())
+    }
+
+    fn is_verbatim(&self) -> bool {
+        self.verbatim.last() == Some(&true)
+    }
+}
+
+impl<W: Write> EventWriter for Minifier<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        Minifier::write_event(self, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{quick_xml_events, write_events, write_minified, DumpEvent};
+
+    fn minify(text: &str) -> String {
+        String::from_utf8(write_minified(&mut Reader::from_str(text), Vec::new()).unwrap()).unwrap()
+    }
+
+    fn minify_stripped(text: &[u8]) -> Vec<u8> {
+        let mut minifier = Minifier::new(Vec::new()).strip_comments(true).strip_pis(true);
+        write_events(&mut Reader::from_reader(text), &mut minifier).unwrap();
+        minifier.into_inner()
+    }
+
+    // Drops whitespace-only text and removed nodes, then collapses elements left without content.
+    fn significant_events(text: &[u8], is_stripped: bool) -> Vec<DumpEvent> {
+        let mut events: Vec<DumpEvent> = Vec::new();
+        for e in quick_xml_events(text).unwrap() {
+            match e {
+                DumpEvent::Text(ref text) if text.trim().is_empty() => {}
+                DumpEvent::Comment(_) | DumpEvent::PI { .. } if is_stripped => {}
+                DumpEvent::EndElement { .. } if matches!(events.last(), Some(DumpEvent::StartElement { .. })) => {
+                    if let Some(DumpEvent::StartElement { name, ns, prefix, attrs }) = events.pop() {
+                        events.push(DumpEvent::EmptyElement { name, ns, prefix, attrs });
+                    }
+                }
+                e => events.push(e),
+            }
+        }
+
+        events
+    }
+
+    #[test]
+    fn keeps_whitespace_in_preserved_elements() {
+        let text = concat!("<config>\n  <name>app</name>\n",
+                           "  <script xml:space=\"preserve\">\n    <line> a </line>\n  </script>\n",
+                           "</config>\n");
+        let expected = "<config><name>app</name><script xml:space=\"preserve\">\n    <line> a </line>\n  </script>\
+                        </config>";
+        assert_eq!(minify(text), expected);
+    }
+
+    #[test]
+    fn keeps_whitespace_in_nested_preserved_elements() {
+        let text = "<a>\n  <b>\n    <c xml:space=\"preserve\"> <d/> </c>\n  </b>\n</a>";
+        assert_eq!(minify(text), "<a><b><c xml:space=\"preserve\"> <d/> </c></b></a>");
+    }
+
+    #[test]
+    fn keeps_whitespace_in_mixed_content() {
+        assert_eq!(minify("<p><b>big</b> <i>world</i>!</p>"), "<p><b>big</b> <i>world</i>!</p>");
+        assert_eq!(minify("<p>\n  <b>big</b>\n  <c><![CDATA[x]]></c>\n</p>"), "<p><b>big</b><c><![CDATA[x]]></c></p>");
+
+        // Descendants of the mixed element keep whitespace too, while its parent doesn't.
+        let text = "<doc>\n  <p> <b> <i/> </b> world</p>\n</doc>";
+        assert_eq!(minify(text), "<doc><p> <b> <i/> </b> world</p></doc>");
+    }
+
+    #[test]
+    fn strips_comments_and_pis() {
+        let text = "<?xml version=\"1.0\"?>\n<?style a?>\n<a><!-- note --><b>x<!-- c --></b><?pi?></a>";
+        assert_eq!(minify_stripped(text.as_bytes()), b"<?xml version=\"1.0\"?><a><b>x</b></a>".as_ref());
+        assert_eq!(minify(text), "<?xml version=\"1.0\"?><?style a?><a><!-- note --><b>x<!-- c --></b><?pi?></a>");
+    }
+
+    #[test]
+    fn collapses_elements_without_content() {
+        assert_eq!(minify("<a>\n  <b></b>\n  <c>\n  </c>\n</a>"), "<a><b/><c/></a>");
+        assert_eq!(minify_stripped(b"<a><b><!-- c --></b></a>"), b"<a><b/></a>".as_ref());
+    }
+
+    #[test]
+    fn minifies_fixtures() {
+        for path in &["data/small.xml", "data/medium.xml", "data/namespaces.xml", "data/complex.svg"] {
+            let text = std::fs::read(path).unwrap();
+
+            let out = write_minified(&mut Reader::from_reader(text.as_slice()), Vec::new()).unwrap();
+            assert!(out.len() < text.len(), "{}", path);
+            assert_eq!(significant_events(&out, false), significant_events(&text, false), "{}", path);
+
+            let stripped = minify_stripped(&text);
+            assert!(stripped.len() <= out.len(), "{}", path);
+            assert_eq!(significant_events(&stripped, true), significant_events(&text, true), "{}", path);
+        }
+    }
+}
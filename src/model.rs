@@ -0,0 +1,247 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::BufRead;
+#[cfg(feature = "xml_rs")]
+use std::io::Read;
+use std::str;
+
+use quick_xml::Reader;
+
+use crate::event::{Attr, Event, XmlSource};
+#[cfg(feature = "xml_rs")]
+use crate::XmlRsReader;
+use crate::{EventVisitor, PrintVisitor, QuickXmlReader, Result};
+
+/// An owned backend-agnostic event, used to compare event streams of different backends.
+///
+/// Names are local, namespaces are resolved and text is unescaped.
+/// `Display` produces the same output as `PrintVisitor` at the zero depth.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DumpEvent {
+    StartElement {
+        name: String,
+        ns: Option<String>,
+        prefix: Option<String>,
+        attrs: Vec<(String, String)>,
+    },
+    EmptyElement {
+        name: String,
+        ns: Option<String>,
+        prefix: Option<String>,
+        attrs: Vec<(String, String)>,
+    },
+    EndElement {
+        name: String,
+        ns: Option<String>,
+        prefix: Option<String>,
+    },
+    Text(String),
+    CData(String),
+    Comment(String),
+    PI {
+        target: String,
+        content: Option<String>,
+    },
+    DocType(String),
+    Decl {
+        version: Option<String>,
+        encoding: Option<String>,
+        standalone: Option<String>,
+    },
+}
+
+impl DumpEvent {
+    /// Converts a backend-agnostic event into an owned one.
+    ///
+    /// Returns `None` for `Event::Eof`.
+    pub fn from_event(event: &Event) -> Result<Option<Self>> {
+        fn string(bytes: &[u8]) -> Result<String> {
+            Ok(str::from_utf8(bytes)?.to_string())
+        }
+
+        fn optional(value: &Option<Cow<[u8]>>) -> Result<Option<String>> {
+            match value {
+                Some(value) => Ok(Some(string(value)?)),
+                None => Ok(None),
+            }
+        }
+
+        fn attrs(attrs: &[Attr]) -> Result<Vec<(String, String)>> {
+            let mut decoded = Vec::with_capacity(attrs.len());
+            for a in attrs {
+                decoded.push((string(&a.key)?, a.value.to_string()));
+            }
+
+            Ok(decoded)
+        }
+
+        let event = match *event {
+            Event::Start { ref ns, ref prefix, ref name, attrs: ref a } => DumpEvent::StartElement {
+                name: string(name)?,
+                ns: optional(ns)?,
+                prefix: optional(prefix)?,
+                attrs: attrs(a)?,
+            },
+            Event::Empty { ref ns, ref prefix, ref name, attrs: ref a } => DumpEvent::EmptyElement {
+                name: string(name)?,
+                ns: optional(ns)?,
+                prefix: optional(prefix)?,
+                attrs: attrs(a)?,
+            },
+            Event::End { ref ns, ref prefix, ref name } => DumpEvent::EndElement {
+                name: string(name)?,
+                ns: optional(ns)?,
+                prefix: optional(prefix)?,
+            },
+            Event::Text(ref text) => DumpEvent::Text(text.to_string()),
+            Event::CData(ref text) => DumpEvent::CData(text.to_string()),
+            Event::Comment(ref text) => DumpEvent::Comment(text.to_string()),
+            Event::PI(ref text) => {
+                let text = text.trim_start();
+                match text.find(char::is_whitespace) {
+                    Some(idx) => DumpEvent::PI {
+                        target: text[..idx].to_string(),
+                        content: Some(text[idx..].trim_start().to_string()),
+                    },
+                    None => DumpEvent::PI { target: text.to_string(), content: None },
+                }
+            }
+            Event::DocType(ref text) => DumpEvent::DocType(text.to_string()),
+            Event::Decl { ref version, ref encoding, ref standalone } => DumpEvent::Decl {
+                version: version.as_ref().map(|v| v.to_string()),
+                encoding: encoding.as_ref().map(|v| v.to_string()),
+                standalone: standalone.as_ref().map(|v| v.to_string()),
+            },
+            Event::Eof => return Ok(None),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// Passes the event to the `visitor`.
+    ///
+    /// `depth` is updated on StartElement/EndElement events. It never goes below zero.
+    pub fn visit(&self, depth: &mut usize, visitor: &mut impl EventVisitor) -> Result<()> {
+        match *self {
+            DumpEvent::StartElement { ref name, ref ns, ref prefix, ref attrs } => {
+                visitor.start(ns.as_deref(), prefix.as_deref(), name, attrs, *depth)?;
+                *depth += 1;
+            }
+            DumpEvent::EmptyElement { ref name, ref ns, ref prefix, ref attrs } => {
+                visitor.empty(ns.as_deref(), prefix.as_deref(), name, attrs, *depth)?;
+            }
+            DumpEvent::EndElement { ref name, ref ns, ref prefix } => {
+                // An unmatched End event is reported at the zero depth.
+                *depth = depth.saturating_sub(1);
+                visitor.end(ns.as_deref(), prefix.as_deref(), name, *depth)?;
+            }
+            DumpEvent::Text(ref text) => visitor.text(text, *depth)?,
+            DumpEvent::CData(ref text) => visitor.cdata(text, *depth)?,
+            DumpEvent::Comment(ref text) => visitor.comment(text, *depth)?,
+            DumpEvent::PI { ref target, ref content } => match content {
+                Some(content) => visitor.pi(&format!("{} {}", target, content), *depth)?,
+                None => visitor.pi(target, *depth)?,
+            },
+            DumpEvent::DocType(ref text) => visitor.doctype(text, *depth)?,
+            DumpEvent::Decl { ref version, ref encoding, ref standalone } => {
+                visitor.decl(version.as_deref(), encoding.as_deref(), standalone.as_deref(), *depth)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DumpEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // An End event closes an element, so it starts one level deeper.
+        let mut depth = match *self {
+            DumpEvent::EndElement { .. } => 1,
+            _ => 0,
+        };
+
+        let mut buf = Vec::new();
+        self.visit(&mut depth, &mut PrintVisitor::new(&mut buf)).map_err(|_| fmt::Error)?;
+        let text = str::from_utf8(&buf).map_err(|_| fmt::Error)?;
+        f.write_str(text.trim_end_matches('\n'))
+    }
+}
+
+/// Reads all events from the `source` until `Event::Eof`.
+pub fn collect_events(source: &mut impl XmlSource) -> Result<Vec<DumpEvent>> {
+    let mut events = Vec::new();
+    while let Some(event) = DumpEvent::from_event(&source.next_event()?)? {
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Parses the document with quick_xml and returns all its events.
+pub fn quick_xml_events<R: BufRead>(reader: R) -> Result<Vec<DumpEvent>> {
+    collect_events(&mut QuickXmlReader::new(Reader::from_reader(reader)))
+}
+
+/// Parses the document with xml-rs and returns all its events.
+///
+/// Unlike quick_xml, xml-rs doesn't report DOCTYPE at all.
+#[cfg(feature = "xml_rs")]
+pub fn xml_rs_events<R: Read>(reader: R) -> Result<Vec<DumpEvent>> {
+    collect_events(&mut XmlRsReader::new(reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_quick_xml_events() {
+        let text = "<?xml version=\"1.0\"?><a xmlns=\"urn:a\" x=\"1\"><b>t &amp; u</b><!--c--><?pi x?></a>";
+        let expected = vec![
+            DumpEvent::Decl { version: Some("1.0".to_string()), encoding: None, standalone: None },
+            DumpEvent::StartElement {
+                name: "a".to_string(),
+                ns: Some("urn:a".to_string()),
+                prefix: None,
+                attrs: vec![("xmlns".to_string(), "urn:a".to_string()), ("x".to_string(), "1".to_string())],
+            },
+            DumpEvent::StartElement {
+                name: "b".to_string(),
+                ns: Some("urn:a".to_string()),
+                prefix: None,
+                attrs: vec![],
+            },
+            DumpEvent::Text("t & u".to_string()),
+            DumpEvent::EndElement { name: "b".to_string(), ns: Some("urn:a".to_string()), prefix: None },
+            DumpEvent::Comment("c".to_string()),
+            DumpEvent::PI { target: "pi".to_string(), content: Some("x".to_string()) },
+            DumpEvent::EndElement { name: "a".to_string(), ns: Some("urn:a".to_string()), prefix: None },
+        ];
+
+        let mut events = quick_xml_events(text.as_bytes()).unwrap();
+        events.retain(|e| *e != DumpEvent::Text(String::new()));
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn displays_like_print_visitor() {
+        let event = DumpEvent::EmptyElement {
+            name: "a".to_string(),
+            ns: None,
+            prefix: Some("p".to_string()),
+            attrs: vec![("x".to_string(), "1".to_string())],
+        };
+        assert_eq!(event.to_string(), "Empty: a (prefix: p)\n    Attribute: x=\"1\"");
+        assert_eq!(DumpEvent::Text("t".to_string()).to_string(), "  Text: \"t\"");
+    }
+
+    #[cfg(feature = "xml_rs")]
+    #[test]
+    fn backends_produce_the_same_events() {
+        let text = "<?xml version=\"1.0\"?><a xmlns:p=\"urn:p\"><p:b p:x=\"1\">t &amp; u</p:b><c/><!--d--></a>";
+        // quick_xml reports empty text between adjacent tags, while xml-rs doesn't.
+        let mut expected = quick_xml_events(text.as_bytes()).unwrap();
+        expected.retain(|e| *e != DumpEvent::Text(String::new()));
+        assert_eq!(xml_rs_events(text.as_bytes()).unwrap(), expected);
+    }
+}
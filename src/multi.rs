@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::event::{Event, XmlSource};
+use crate::{NamespaceRegistry, QuickXmlReader, Result};
+
+/// A reader of a stream with multiple concatenated documents, like a log or a message queue.
+///
+/// Each document is returned as a separate event sequence that ends with `Event::Eof`,
+/// so it can be passed to anything that reads a single document, like `collect_events`.
+/// `next_document` then moves to the next one.
+///
+/// A document ends when its root element is closed and the next declaration, DOCTYPE or element begins.
+/// Comments, processing instructions and whitespace after the root element belong to the preceding document.
+/// Namespaces, entities declared in the `DOCTYPE` and the length counted by `EntityLimits::max_expanded_len`
+/// are reset for each document, while the settings of the underlying reader, including the custom entities, are kept.
+///
+/// Each event is copied, since the first event of the next document is read ahead.
+pub struct MultiDocumentReader<R: BufRead> {
+    reader: QuickXmlReader<R>,
+    // Custom entities, without the ones declared in the DOCTYPE.
+    entities: HashMap<Vec<u8>, Vec<u8>>,
+    depth: usize,
+    has_root: bool,
+    // The first event of the next document.
+    next: Option<Event<'static>>,
+    state: State,
+    index: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum State {
+    InDocument,
+    DocumentEnded,
+    Finished,
+}
+
+impl<R: BufRead> MultiDocumentReader<R> {
+    /// Creates a new reader.
+    ///
+    /// The `reader` should be configured beforehand.
+    pub fn new(reader: QuickXmlReader<R>) -> Self {
+        MultiDocumentReader {
+            entities: reader.entities.clone(),
+            reader,
+            depth: 0,
+            has_root: false,
+            next: None,
+            state: State::InDocument,
+            index: 0,
+        }
+    }
+
+    /// Moves to the next document, skipping the rest of the current one.
+    ///
+    /// Returns `false` at the end of the stream.
+    pub fn next_document(&mut self) -> Result<bool> {
+        while self.state == State::InDocument {
+            self.next_event()?;
+        }
+
+        if self.state == State::Finished {
+            return Ok(false);
+        }
+
+        self.reader.entities = self.entities.clone();
+        self.reader.namespaces = NamespaceRegistry::new();
+        self.reader.prev_is_tag = false;
+        self.reader.entity_expanded_len.set(0);
+        self.depth = 0;
+        self.has_root = false;
+        self.state = State::InDocument;
+        self.index += 1;
+        Ok(true)
+    }
+
+    /// Returns the index of the current document, starting from 0.
+    pub fn document_index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> QuickXmlReader<R> {
+        self.reader
+    }
+}
+
+impl<R: BufRead> XmlSource for MultiDocumentReader<R> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        if self.state != State::InDocument {
+            return Ok(Event::Eof);
+        }
+
+        let event = match self.next.take() {
+            Some(event) => event,
+            None => self.reader.next_event()?.into_owned(),
+        };
+
+        match event {
+            Event::Start { .. } | Event::Empty { .. } | Event::Decl { .. } | Event::DocType(_)
+                if self.has_root && self.depth == 0 =>
+            {
+                self.next = Some(event);
+                self.state = State::DocumentEnded;
+                return Ok(Event::Eof);
+            }
+            Event::Start { .. } => {
+                self.depth += 1;
+                self.has_root = true;
+            }
+            Event::Empty { .. } => self.has_root = true,
+            Event::End { .. } => self.depth = self.depth.saturating_sub(1),
+            Event::Eof => self.state = State::Finished,
+            _ => {}
+        }
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{collect_events, DumpEvent, EntityLimits, Error};
+
+    fn read_documents(text: &str) -> Vec<Result<Vec<DumpEvent>>> {
+        let mut reader = MultiDocumentReader::new(QuickXmlReader::new(Reader::from_str(text)));
+        let mut documents = Vec::new();
+        loop {
+            assert_eq!(reader.document_index(), documents.len());
+
+            // quick_xml reports empty text between adjacent tags.
+            let events = collect_events(&mut reader)
+                .map(|events| events.into_iter().filter(|e| *e != DumpEvent::Text(String::new())).collect());
+            documents.push(events);
+
+            if !reader.next_document().unwrap() {
+                return documents;
+            }
+        }
+    }
+
+    #[test]
+    fn splits_concatenated_documents() {
+        let text = std::fs::read_to_string("data/concatenated.xml").unwrap();
+        let documents: Vec<_> = read_documents(&text).into_iter().map(Result::unwrap).collect();
+        assert_eq!(documents.len(), 3);
+
+        let roots: Vec<_> = documents.iter().map(|events| events.iter().find_map(|e| match *e {
+            DumpEvent::StartElement { ref attrs, .. } => Some(attrs.clone()),
+            _ => None,
+        })).collect();
+        let n = |v: &str| Some(vec![("n".to_string(), v.to_string())]);
+        assert_eq!(roots, [n("1"), n("2"), n("3")]);
+
+        assert!(documents[0].contains(&DumpEvent::Comment(" tail ".to_string())));
+        assert!(documents[0].iter().any(|e| match *e {
+            DumpEvent::EmptyElement { ref ns, .. } => ns.as_deref() == Some("urn:a"),
+            _ => false,
+        }));
+        assert!(documents[1].contains(&DumpEvent::Text("one".to_string())));
+        assert!(documents[2].contains(&DumpEvent::Text("text".to_string())));
+    }
+
+    #[test]
+    fn resets_doctype_entities() {
+        let text = "<!DOCTYPE a [<!ENTITY e \"one\">]><a>&e;</a><a>&e;</a>";
+        let documents = read_documents(text);
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0].as_ref().unwrap().contains(&DumpEvent::Text("one".to_string())));
+        assert!(documents[1].is_err());
+    }
+
+    #[test]
+    fn skips_rest_of_document() {
+        let mut reader = MultiDocumentReader::new(QuickXmlReader::new(Reader::from_str("<a><b/></a><c/>")));
+        reader.next_event().unwrap();
+        assert!(reader.next_document().unwrap());
+        assert_eq!(collect_events(&mut reader).unwrap(), [DumpEvent::EmptyElement {
+            name: "c".to_string(),
+            ns: None,
+            prefix: None,
+            attrs: Vec::new(),
+        }]);
+        assert!(!reader.next_document().unwrap());
+    }
+
+    #[test]
+    fn resets_expanded_entity_length() {
+        let mut inner = QuickXmlReader::new(Reader::from_str("<a>&e;</a><a>&e;</a><a>&e;&e;</a>"));
+        inner.set_entities(vec![(b"e".to_vec(), b"12345".to_vec())].into_iter().collect());
+        inner.set_entity_limits(EntityLimits::new().max_expanded_len(8));
+        let mut reader = MultiDocumentReader::new(inner);
+
+        assert!(collect_events(&mut reader).unwrap().contains(&DumpEvent::Text("12345".to_string())));
+        assert!(reader.next_document().unwrap());
+        assert!(collect_events(&mut reader).unwrap().contains(&DumpEvent::Text("12345".to_string())));
+        assert!(reader.next_document().unwrap());
+        match collect_events(&mut reader) {
+            Err(Error::EntityExpansionLimit { ref entity, .. }) => assert_eq!(entity, "e"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::str;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::Result;
+
+pub(crate) const XML_PREFIX: &str = "xml";
+pub(crate) const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Prefix to URI mappings accumulated during parsing.
+///
+/// quick_xml resolves namespaces internally and doesn't expose its bindings,
+/// so the registry tracks `xmlns` attributes itself.
+/// The default namespace has an empty prefix.
+#[derive(Clone, Default, Debug)]
+pub struct NamespaceRegistry {
+    // All bindings of the open elements, from the outermost to the innermost.
+    bindings: Vec<(String, String)>,
+    // A number of bindings before each open element.
+    scopes: Vec<usize>,
+    // End and Empty events close a scope, but it should stay visible
+    // while the event itself is being processed.
+    pending_pop: bool,
+}
+
+impl NamespaceRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        NamespaceRegistry::default()
+    }
+
+    /// Updates the registry with an event.
+    pub fn update(&mut self, event: &Event) -> Result<()> {
+        if self.pending_pop {
+            self.pop();
+            self.pending_pop = false;
+        }
+
+        match *event {
+            Event::Start(ref e) => {
+                self.push(e)?;
+            }
+            Event::Empty(ref e) => {
+                self.push(e)?;
+                self.pending_pop = true;
+            }
+            Event::End(_) => {
+                self.pending_pop = true;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Opens a new scope with the namespaces declared by the element.
+    pub fn push(&mut self, e: &BytesStart) -> Result<()> {
+        self.scopes.push(self.bindings.len());
+
+        for a in e.attributes() {
+            let a = a?;
+            let prefix = if a.key == b"xmlns" {
+                ""
+            } else if a.key.starts_with(b"xmlns:") {
+                str::from_utf8(&a.key[6..])?
+            } else {
+                continue;
+            };
+
+            let uri = str::from_utf8(&a.value)?;
+            self.bindings.push((prefix.to_string(), uri.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Closes the innermost scope.
+    pub fn pop(&mut self) {
+        if let Some(len) = self.scopes.pop() {
+            self.bindings.truncate(len);
+        }
+    }
+
+    /// Returns a URI bound to the `prefix`.
+    ///
+    /// An empty prefix resolves the default namespace.
+    /// An inner declaration overrides the outer one and `xmlns=""` resets the default namespace.
+    pub fn resolve_prefix(&self, prefix: &[u8]) -> Option<&str> {
+        if prefix == XML_PREFIX.as_bytes() {
+            return Some(XML_NS);
+        }
+
+        self.bindings.iter().rev()
+            .find(|(p, _)| p.as_bytes() == prefix)
+            .map(|(_, uri)| uri.as_str())
+            .filter(|uri| !uri.is_empty())
+    }
+
+    /// Returns all namespaces in scope as (prefix, URI) pairs.
+    ///
+    /// Shadowed declarations are skipped. The order is from the innermost to the outermost.
+    pub fn in_scope_namespaces(&self) -> impl Iterator<Item = (&str, &str)> {
+        let bindings = &self.bindings;
+        bindings.iter().enumerate().rev()
+            .filter(move |(i, (p, _))| !bindings[i + 1..].iter().any(|(p2, _)| p2 == p))
+            .map(|(_, (p, uri))| (p.as_str(), uri.as_str()))
+            .filter(|(_, uri)| !uri.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+
+    // A tag name, the URI bound to `p` and in-scope namespaces after the tag.
+    type Step = (String, Option<String>, Vec<(String, String)>);
+
+    // Passes all events to the registry and records a step after each tag.
+    fn trace(text: &str) -> Vec<Step> {
+        let mut reader = Reader::from_str(text);
+        let mut registry = NamespaceRegistry::new();
+        let mut buf = Vec::new();
+        let mut trace = Vec::new();
+
+        loop {
+            let event = reader.read_event(&mut buf).unwrap();
+            let name = match event {
+                Event::Start(ref e) | Event::Empty(ref e) => String::from_utf8(e.name().to_vec()).unwrap(),
+                Event::End(ref e) => format!("/{}", String::from_utf8(e.name().to_vec()).unwrap()),
+                Event::Eof => return trace,
+                _ => {
+                    buf.clear();
+                    continue;
+                }
+            };
+
+            registry.update(&event).unwrap();
+            let namespaces = registry.in_scope_namespaces().map(|(p, uri)| (p.to_string(), uri.to_string())).collect();
+            trace.push((name, registry.resolve_prefix(b"p").map(str::to_string), namespaces));
+            buf.clear();
+        }
+    }
+
+    #[test]
+    fn restores_shadowed_prefix() {
+        let text = "<p:a xmlns:p=\"urn:outer\"><p:b xmlns:p=\"urn:inner\"><p:c/></p:b><p:d/></p:a>";
+        let resolved: Vec<_> = trace(text).into_iter().map(|(name, uri, _)| (name, uri)).collect();
+
+        let entry = |name: &str, uri: &str| (name.to_string(), Some(uri.to_string()));
+        assert_eq!(resolved, [
+            entry("p:a", "urn:outer"),
+            entry("p:b", "urn:inner"),
+            entry("p:c", "urn:inner"),
+            // The scope is still open while its End event is processed.
+            entry("/p:b", "urn:inner"),
+            entry("p:d", "urn:outer"),
+            entry("/p:a", "urn:outer"),
+        ]);
+    }
+
+    #[test]
+    fn skips_shadowed_and_reset_namespaces() {
+        let text = "<a xmlns=\"urn:default\" xmlns:p=\"urn:outer\"><b xmlns=\"\" xmlns:p=\"urn:inner\"/><c/></a>";
+        let namespaces: Vec<_> = trace(text).into_iter().map(|(_, _, namespaces)| namespaces).collect();
+
+        let ns = |p: &str, uri: &str| (p.to_string(), uri.to_string());
+        assert_eq!(namespaces[0], [ns("p", "urn:outer"), ns("", "urn:default")]);
+        assert_eq!(namespaces[1], [ns("p", "urn:inner")]);
+        assert_eq!(namespaces[2], [ns("p", "urn:outer"), ns("", "urn:default")]);
+    }
+
+    #[test]
+    fn resolves_xml_prefix() {
+        let registry = NamespaceRegistry::new();
+        assert_eq!(registry.resolve_prefix(b"xml"), Some(XML_NS));
+        assert_eq!(registry.resolve_prefix(b""), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! An owned tree with namespace-aware attributes.
+//!
+//! Unlike `Element`, attributes are stored in an `AttributeMap`, so they are looked up
+//! by expanded names, and text, CDATA and comments are kept apart.
+
+use std::io::BufRead;
+
+use crate::dom::{Descendants, TreeElement};
+use crate::{dispatch, AttributeMap, QuickXmlReader, Result, SaxHandler};
+
+/// An element of the tree.
+///
+/// `name` is a local name and `namespace` is a resolved namespace URI.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct NsElement {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub attributes: AttributeMap,
+    pub children: Vec<NsNode>,
+}
+
+/// A child of an `NsElement`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum NsNode {
+    Element(NsElement),
+    /// An unescaped text.
+    Text(String),
+    CData(String),
+    Comment(String),
+}
+
+impl NsElement {
+    /// Returns the first child element with the local `name`.
+    pub fn child_by_name(&self, name: &str) -> Option<&NsElement> {
+        self.child_nodes().find(|n| n.name == name)
+    }
+
+    /// Returns child elements.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &NsElement> {
+        self.children.iter().filter_map(NsElement::as_element)
+    }
+
+    /// Returns all descendant elements in document order, not including the node itself.
+    pub fn descendants(&self) -> impl Iterator<Item = &NsElement> {
+        Descendants::new(self)
+    }
+}
+
+impl TreeElement for NsElement {
+    type Child = NsNode;
+
+    fn child_slice(&self) -> &[NsNode] {
+        &self.children
+    }
+
+    fn as_element(child: &NsNode) -> Option<&Self> {
+        match *child {
+            NsNode::Element(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Builds an `NsElement` tree from SAX callbacks.
+///
+/// Processing instructions and everything outside the root element are dropped.
+/// Whitespace is kept as is, use `QuickXmlReader::set_whitespace_mode` to strip it.
+#[derive(Default, Debug)]
+pub struct DomBuilder {
+    stack: Vec<NsElement>,
+    root: Option<NsElement>,
+}
+
+impl DomBuilder {
+    /// Creates a new builder.
+    pub fn new() -> Self {
+        DomBuilder::default()
+    }
+
+    /// Parses the whole document from the `reader` and returns its root element.
+    pub fn build<R: BufRead>(mut self, reader: &mut QuickXmlReader<R>) -> Result<NsElement> {
+        dispatch(reader, &mut self)?;
+        self.into_root()
+    }
+
+    /// Returns the root element.
+    ///
+    /// Returns an error when the document has no root element.
+    pub fn into_root(self) -> Result<NsElement> {
+        match self.root {
+            Some(root) => Ok(root),
+            None => Err(quick_xml::Error::UnexpectedEof("root element".to_string()).into()),
+        }
+    }
+
+    fn push_child(&mut self, child: NsNode) {
+        // Children outside the root element are dropped.
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children.push(child);
+        }
+    }
+}
+
+impl SaxHandler for DomBuilder {
+    fn start_element(&mut self, ns: Option<&str>, local: &str, attrs: &AttributeMap) -> Result<()> {
+        self.stack.push(NsElement {
+            name: local.to_string(),
+            namespace: ns.map(str::to_string),
+            attributes: attrs.clone(),
+            children: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    fn end_element(&mut self, _ns: Option<&str>, _local: &str) -> Result<()> {
+        let node = match self.stack.pop() {
+            Some(node) => node,
+            None => return Ok(()),
+        };
+
+        if self.stack.is_empty() {
+            // Only the first root is kept, since quick_xml allows several of them.
+            if self.root.is_none() {
+                self.root = Some(node);
+            }
+        } else {
+            self.push_child(NsNode::Element(node));
+        }
+
+        Ok(())
+    }
+
+    fn characters(&mut self, text: &str) -> Result<()> {
+        if !text.is_empty() {
+            self.push_child(NsNode::Text(text.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str) -> Result<()> {
+        self.push_child(NsNode::CData(text.to_string()));
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str) -> Result<()> {
+        self.push_child(NsNode::Comment(text.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{Error, WhitespaceMode};
+
+    const DOCUMENT: &str = concat!(
+        "<?pi before?><catalog xmlns=\"urn:c\" xmlns:x=\"urn:x\" x:id=\"c1\">",
+        "<item id=\"1\"><name>Widget</name><![CDATA[<raw>]]></item>",
+        "<!-- note --><x:item id=\"2\"><item id=\"3\"/></x:item>",
+        "</catalog><!-- after -->",
+    );
+
+    fn build(text: &str) -> Result<NsElement> {
+        DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_str(text)))
+    }
+
+    #[test]
+    fn builds_namespaced_tree() {
+        let root = build(DOCUMENT).unwrap();
+        assert_eq!(root.name, "catalog");
+        assert_eq!(root.namespace.as_deref(), Some("urn:c"));
+        assert_eq!(root.attributes.get_ns("urn:x", "id"), Some("c1"));
+
+        let item = root.child_nodes().next().unwrap();
+        assert_eq!(item.children[1], NsNode::CData("<raw>".to_string()));
+        assert_eq!(root.children[1], NsNode::Comment(" note ".to_string()));
+
+        let names: Vec<_> = root.child_nodes().map(|n| (n.namespace.as_deref(), n.name.as_str())).collect();
+        assert_eq!(names, vec![(Some("urn:c"), "item"), (Some("urn:x"), "item")]);
+    }
+
+    #[test]
+    fn finds_child_by_name() {
+        let root = build(DOCUMENT).unwrap();
+        let item = root.child_by_name("item").unwrap();
+        assert_eq!(item.attributes.get("id"), Some("1"));
+        assert_eq!(item.child_by_name("name").unwrap().children, vec![NsNode::Text("Widget".to_string())]);
+        assert!(root.child_by_name("name").is_none());
+    }
+
+    #[test]
+    fn iterates_descendants_in_document_order() {
+        let root = build(DOCUMENT).unwrap();
+        let names: Vec<_> = root.descendants()
+            .map(|n| format!("{}{}", n.name, n.attributes.get("id").unwrap_or("")))
+            .collect();
+        assert_eq!(names, vec!["item1", "name", "item2", "item3"]);
+        assert_eq!(root.child_by_name("item").unwrap().child_by_name("name").unwrap().descendants().count(), 0);
+    }
+
+    #[test]
+    fn strips_whitespace_with_reader_mode() {
+        let text = "<a>\n  <b> x </b>\n</a>";
+        assert_eq!(build(text).unwrap().children.len(), 3);
+
+        let mut reader = QuickXmlReader::new(Reader::from_str(text));
+        reader.set_whitespace_mode(WhitespaceMode::StripAll);
+        let root = DomBuilder::new().build(&mut reader).unwrap();
+        assert_eq!(root.children.len(), 1);
+    }
+
+    #[test]
+    fn requires_root_element() {
+        let res = build("<!-- only a comment -->");
+        assert!(matches!(res, Err(Error::QuickXml(quick_xml::Error::UnexpectedEof(_)))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use crate::event::Event;
+
+/// Tag-name ancestry of the current parse position.
+///
+/// Contains local names of all open elements, from the root to the current one,
+/// together with their 1-based positions among the preceding siblings with the same name.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct ElementPath {
+    names: Vec<String>,
+    indices: Vec<usize>,
+    // Numbers of children with each name, for the document and each open element.
+    counts: Vec<HashMap<String, usize>>,
+    // An Empty element is both opened and closed by a single event,
+    // so it stays in the path until the next event.
+    pending_pop: bool,
+}
+
+impl ElementPath {
+    /// Creates an empty path.
+    pub fn new() -> Self {
+        ElementPath::default()
+    }
+
+    /// Updates the path with an event.
+    ///
+    /// Start and Empty events push a name, End events pop it.
+    pub fn push_event(&mut self, event: &Event) {
+        match *event {
+            Event::Start { ref name, .. } => {
+                self.push(&String::from_utf8_lossy(name));
+            }
+            Event::Empty { ref name, .. } => {
+                self.push(&String::from_utf8_lossy(name));
+                self.pending_pop = true;
+            }
+            Event::End { .. } => {
+                self.pop();
+            }
+            _ => self.apply_pending_pop(),
+        }
+    }
+
+    // Used by visitors, which get names instead of events.
+    pub(crate) fn push(&mut self, name: &str) {
+        self.apply_pending_pop();
+
+        if self.counts.is_empty() {
+            self.counts.push(HashMap::new());
+        }
+
+        let count = self.counts.last_mut().unwrap().entry(name.to_string()).or_insert(0);
+        *count += 1;
+
+        self.indices.push(*count);
+        self.names.push(name.to_string());
+        self.counts.push(HashMap::new());
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.apply_pending_pop();
+
+        if self.names.pop().is_some() {
+            self.indices.pop();
+            self.counts.pop();
+        }
+    }
+
+    fn apply_pending_pop(&mut self) {
+        if self.pending_pop {
+            self.pending_pop = false;
+            self.pop();
+        }
+    }
+
+    /// Returns the number of open elements.
+    ///
+    /// A just visited Empty element is not counted.
+    pub fn depth(&self) -> usize {
+        if self.pending_pop {
+            self.names.len() - 1
+        } else {
+            self.names.len()
+        }
+    }
+
+    /// Returns local names of all elements in the path.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns 1-based positions of all elements in the path among their siblings with the same name.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Renders the path as `/root/body/section`.
+    ///
+    /// An empty path is rendered as `/`.
+    pub fn as_xpath_string(&self) -> String {
+        if self.names.is_empty() {
+            return "/".to_string();
+        }
+
+        let mut s = String::new();
+        for name in &self.names {
+            s.push('/');
+            s.push_str(name);
+        }
+
+        s
+    }
+
+    /// Renders the path with positions, like `/catalog[1]/book[2]/title[1]`.
+    ///
+    /// Positions are rendered on every step, including the root, even when there is a single sibling
+    /// with the name, because later siblings are not known while streaming. An empty path is rendered as `/`.
+    pub fn as_indexed_xpath_string(&self) -> String {
+        if self.names.is_empty() {
+            return "/".to_string();
+        }
+
+        let mut s = String::new();
+        for (name, index) in self.names.iter().zip(&self.indices) {
+            s.push('/');
+            s.push_str(name);
+            s.push_str(&format!("[{}]", index));
+        }
+
+        s
+    }
+}
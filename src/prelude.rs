@@ -0,0 +1,36 @@
+//! Backend-agnostic aliases for the backend selected by a Cargo feature.
+//!
+//! `quick_xml` is the default. Enabling `xml_rs`, `roxmltree` or `sxd_document` switches the aliases
+//! to that backend, even when the default features are still enabled. When several of them are enabled,
+//! `xml_rs` takes precedence, then `roxmltree`, then `sxd_document`.
+//!
+//! Application code written against `Backend`, `XmlReader`, `XmlEvent` and `XmlError`
+//! switches backends with a feature, without changes. `XmlBackend::is_end` detects
+//! the end of the document, since backends report it differently.
+
+pub use crate::XmlBackend;
+
+/// The selected backend.
+#[cfg(not(any(feature = "xml_rs", feature = "roxmltree", feature = "sxd_document")))]
+pub type Backend = crate::QuickXml;
+
+/// The selected backend.
+#[cfg(feature = "xml_rs")]
+pub type Backend = crate::XmlRs;
+
+/// The selected backend.
+#[cfg(all(feature = "roxmltree", not(feature = "xml_rs")))]
+pub type Backend = crate::Roxmltree;
+
+/// The selected backend.
+#[cfg(all(feature = "sxd_document", not(any(feature = "xml_rs", feature = "roxmltree"))))]
+pub type Backend = crate::SxdDocument;
+
+/// A reader of the selected backend.
+pub type XmlReader = <Backend as XmlBackend>::Reader;
+
+/// An event of the selected backend.
+pub type XmlEvent = <Backend as XmlBackend>::Event;
+
+/// An error of the selected backend.
+pub type XmlError = <Backend as XmlBackend>::Error;
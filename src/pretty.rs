@@ -0,0 +1,312 @@
+use std::io::Write;
+
+use quick_xml::events::Event;
+
+use crate::serialize::{is_blank, xml_space, ContentBuffer};
+use crate::{EventWriter, Result};
+
+const DECLARATION: &[u8] = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>";
+
+/// A line ending used by `PrettyPrinter`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum LineEnding {
+    /// `\n`. The default.
+    #[default]
+    Lf,
+    /// `\r\n`.
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// Writes quick_xml events as indented XML.
+///
+/// Events are written without re-escaping, so entities, comments and CDATA sections are kept as is.
+/// Each tag, comment and processing instruction starts on a new line,
+/// and whitespace-only text between them is dropped.
+///
+/// Content of an element with significant text, i.e. non-whitespace text or CDATA,
+/// is written verbatim instead, so an element with text only is written as `<a>text</a>`
+/// and mixed content keeps its spacing. Content of an element with `xml:space="preserve"`
+/// is written verbatim as well, until a descendant resets it with `xml:space="default"`.
+///
+/// Significant text can follow any number of child elements, so the events of an element
+/// are buffered until its first significant text or its end. An element without text,
+/// like the root of a data document, is therefore buffered completely.
+pub struct PrettyPrinter<W: Write> {
+    writer: W,
+    indent: String,
+    line_ending: LineEnding,
+    add_declaration: bool,
+    depth: usize,
+    is_first_line: bool,
+    // Whether the last written event is a start tag, so an end tag can follow it on the same line.
+    after_start: bool,
+    // Whether the content of each open element is written verbatim.
+    verbatim: Vec<bool>,
+    // Events of an element whose content isn't known to be mixed yet.
+    pending: ContentBuffer,
+}
+
+impl<W: Write> PrettyPrinter<W> {
+    /// Creates a new printer that writes to the `writer`,
+    /// using two spaces for indentation and `\n` line endings.
+    pub fn new(writer: W) -> Self {
+        PrettyPrinter {
+            writer,
+            indent: "  ".to_string(),
+            line_ending: LineEnding::default(),
+            add_declaration: false,
+            depth: 0,
+            is_first_line: true,
+            after_start: false,
+            verbatim: Vec::new(),
+            pending: ContentBuffer::default(),
+        }
+    }
+
+    /// Sets an indentation string for a single level.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = indent.to_string();
+        self
+    }
+
+    /// Sets a line ending.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Writes a UTF-8 XML declaration when the document doesn't start with one. Disabled by default.
+    pub fn add_declaration(mut self, add: bool) -> Self {
+        self.add_declaration = add;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a single event.
+    ///
+    /// `Event::Eof` writes the final line ending.
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        if !self.pending.is_empty() {
+            if self.pending.push(event) {
+                self.flush_pending()?;
+            }
+
+            return Ok(());
+        }
+
+        // The content of a verbatim element is written as is, so it's known in advance.
+        if let Event::Start(ref e) = *event {
+            if !xml_space(e)?.unwrap_or(self.is_verbatim()) {
+                self.pending.push(event);
+                return Ok(());
+            }
+        }
+
+        self.write_decided(event, false)
+    }
+
+    fn flush_pending(&mut self) -> Result<()> {
+        for (event, is_mixed) in self.pending.take() {
+            self.write_decided(&event, is_mixed)?;
+        }
+
+        Ok(())
+    }
+
+    // Writes an event whose layout is already known. `is_mixed` is set for a Start event of an element
+    // with significant text.
+    fn write_decided(&mut self, event: &Event, is_mixed: bool) -> Result<()> {
+        if self.is_first_line && self.add_declaration && !is_declaration_or_whitespace(event) {
+            self.write_line_start()?;
+            self.writer.write_all(DECLARATION)?;
+        }
+
+        let is_verbatim = self.is_verbatim();
+        let after_start = self.after_start;
+        self.after_start = matches!(*event, Event::Start(_));
+
+        match *event {
+            Event::Start(ref e) => {
+                self.write_line_start_unless(is_verbatim)?;
+                self.write_wrapped(b"<", e, b">")?;
+                self.depth += 1;
+                self.verbatim.push(is_mixed || xml_space(e)?.unwrap_or(is_verbatim));
+            }
+            Event::Empty(ref e) => {
+                self.write_line_start_unless(is_verbatim)?;
+                self.write_wrapped(b"<", e, b"/>")?;
+            }
+            Event::End(ref e) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.verbatim.pop();
+                self.write_line_start_unless(is_verbatim || after_start)?;
+                self.write_wrapped(b"</", e, b">")?;
+            }
+            Event::Text(ref e) => {
+                // Significant text is written only into verbatim elements or outside of any element.
+                if !is_blank(e) || is_verbatim {
+                    self.writer.write_all(e)?;
+                } else {
+                    // Dropped whitespace doesn't separate the tags.
+                    self.after_start = after_start;
+                }
+            }
+            Event::CData(ref e) => {
+                self.write_wrapped(b"<![CDATA[", e, b"]]>")?;
+            }
+            Event::Comment(ref e) => {
+                self.write_line_start_unless(is_verbatim)?;
+                self.write_wrapped(b"<!--", e, b"-->")?;
+            }
+            Event::PI(ref e) => {
+                self.write_line_start_unless(is_verbatim)?;
+                self.write_wrapped(b"<?", e, b"?>")?;
+            }
+            Event::Decl(ref e) => {
+                self.write_line_start()?;
+                self.write_wrapped(b"<?", e, b"?>")?;
+            }
+            Event::DocType(ref e) => {
+                self.write_line_start()?;
+                self.write_wrapped(b"<!DOCTYPE", e, b">")?;
+            }
+            Event::Eof => {
+                self.writer.write_all(self.line_ending.as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_verbatim(&self) -> bool {
+        self.verbatim.last() == Some(&true)
+    }
+
+    fn write_line_start_unless(&mut self, is_verbatim: bool) -> Result<()> {
+        if is_verbatim {
+            Ok(())
+        } else {
+            self.write_line_start()
+        }
+    }
+
+    fn write_line_start(&mut self) -> Result<()> {
+        if !self.is_first_line {
+            self.writer.write_all(self.line_ending.as_bytes())?;
+        }
+        self.is_first_line = false;
+
+        for _ in 0..self.depth {
+            self.writer.write_all(self.indent.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn write_wrapped(&mut self, before: &[u8], value: &[u8], after: &[u8]) -> Result<()> {
+        self.writer.write_all(before)?;
+        self.writer.write_all(value)?;
+        self.writer.write_all(after)?;
+        Ok(())
+    }
+}
+
+// quick_xml reports an empty text before the declaration.
+fn is_declaration_or_whitespace(event: &Event) -> bool {
+    match *event {
+        Event::Decl(_) => true,
+        Event::Text(ref e) => is_blank(e),
+        _ => false,
+    }
+}
+
+impl<W: Write> EventWriter for PrettyPrinter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        PrettyPrinter::write_event(self, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{quick_xml_events, write_events, DumpEvent};
+
+    fn print(text: &str, printer: PrettyPrinter<Vec<u8>>) -> String {
+        let mut printer = printer;
+        write_events(&mut Reader::from_str(text), &mut printer).unwrap();
+        String::from_utf8(printer.into_inner()).unwrap()
+    }
+
+    fn significant_events(text: &str) -> Vec<DumpEvent> {
+        let mut events = quick_xml_events(text.as_bytes()).unwrap();
+        events.retain(|e| !matches!(*e, DumpEvent::Text(ref text) if text.trim().is_empty()));
+        events
+    }
+
+    #[test]
+    fn round_trips_all_events() {
+        let text = "<?xml version=\"1.0\"?><!DOCTYPE a><a><!-- c --><?pi x?><b x=\"&lt;\">t &amp; u</b>\
+                    <c><![CDATA[<d>]]></c><e/></a>";
+        let out = print(text, PrettyPrinter::new(Vec::new()));
+        assert_eq!(significant_events(&out), significant_events(text));
+    }
+
+    #[test]
+    fn writes_indent_and_line_endings() {
+        let printer = PrettyPrinter::new(Vec::new()).indent("\t").line_ending(LineEnding::CrLf);
+        assert_eq!(print("<a><b><c/></b></a>", printer), "<a>\r\n\t<b>\r\n\t\t<c/>\r\n\t</b>\r\n</a>\r\n");
+    }
+
+    #[test]
+    fn keeps_text_only_elements_on_one_line() {
+        assert_eq!(print("<a>\n<b>text</b>\n</a>", PrettyPrinter::new(Vec::new())), "<a>\n  <b>text</b>\n</a>\n");
+    }
+
+    #[test]
+    fn adds_declaration() {
+        let out = print("<a/>", PrettyPrinter::new(Vec::new()).add_declaration(true));
+        assert_eq!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<a/>\n");
+
+        let out = print("<?xml version=\"1.0\"?><a/>", PrettyPrinter::new(Vec::new()).add_declaration(true));
+        assert_eq!(out, "<?xml version=\"1.0\"?>\n<a/>\n");
+    }
+
+    #[test]
+    fn keeps_mixed_content() {
+        let printer = || PrettyPrinter::new(Vec::new());
+        assert_eq!(print("<p><b>big</b> <i>world</i>!</p>", printer()), "<p><b>big</b> <i>world</i>!</p>\n");
+
+        // Only the mixed element is written verbatim, its siblings are still indented.
+        let text = "<doc><p><b>big</b> <i>world</i>!</p><list><item/></list></doc>";
+        let expected = "<doc>\n  <p><b>big</b> <i>world</i>!</p>\n  <list>\n    <item/>\n  </list>\n</doc>\n";
+        assert_eq!(print(text, printer()), expected);
+    }
+
+    #[test]
+    fn writes_unclosed_elements_at_eof() {
+        assert_eq!(print("<a><b/>", PrettyPrinter::new(Vec::new())), "<a>\n  <b/>\n");
+    }
+
+    #[test]
+    fn writes_preserved_content_verbatim() {
+        let text = "<a><b xml:space=\"preserve\"> <c/> <d xml:space=\"default\"> <e/></d></b></a>";
+        let expected = concat!("<a>\n  <b xml:space=\"preserve\"> <c/> <d xml:space=\"default\">\n",
+                               "      <e/>\n    </d></b>\n</a>\n");
+        assert_eq!(print(text, PrettyPrinter::new(Vec::new())), expected);
+    }
+}
@@ -0,0 +1,305 @@
+use std::io::{self, Write};
+
+use crate::namespace::{XML_NS, XML_PREFIX};
+use crate::{ElementPath, EventVisitor, Result};
+
+/// Writes events as an indented tree.
+///
+/// Namespace declarations are written separately from other attributes.
+/// Prefixed attributes are resolved using the declarations seen so far.
+/// Each level is indented with two spaces by default.
+#[derive(Clone, Debug)]
+pub struct PrintVisitor<W: Write> {
+    writer: W,
+    layout: Layout,
+    // (prefix, URI) pairs in scope and the number of them before each open element.
+    bindings: Vec<(String, String)>,
+    scopes: Vec<usize>,
+}
+
+impl<W: Write> PrintVisitor<W> {
+    /// Creates a new visitor that writes to the `writer`.
+    pub fn new(writer: W) -> Self {
+        PrintVisitor {
+            writer,
+            layout: Layout { indent: "  ".to_string(), path: None },
+            bindings: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Sets a string written once per indentation level, like `\t`.
+    ///
+    /// Attributes and text are indented by an extra level relative to their element.
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.layout.indent = indent.to_string();
+        self
+    }
+
+    /// Sets whether each line starts with the path of its element, like `/catalog[1]/book[2]/title[1]`.
+    ///
+    /// See `ElementPath::as_indexed_xpath_string` for the format.
+    pub fn paths(mut self, enabled: bool) -> Self {
+        self.layout.path = if enabled { Some(ElementPath::new()) } else { None };
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_element(&mut self, title: &str, ns: Option<&str>, prefix: Option<&str>, local_name: &str,
+                     attributes: &[(String, String)], depth: usize) -> io::Result<()> {
+        self.scopes.push(self.bindings.len());
+        for (key, value) in attributes {
+            if let Some(prefix) = declared_prefix(key) {
+                self.bindings.push((prefix.to_string(), value.clone()));
+            }
+        }
+
+        let w = &mut self.writer;
+        let layout = &self.layout;
+        write_tag_name(w, layout, title, ns, prefix, local_name, depth)?;
+
+        for (key, value) in attributes {
+            if let Some(prefix) = declared_prefix(key) {
+                write_indent(w, layout, depth + 2)?;
+                let prefix = if prefix.is_empty() { "(default)" } else { prefix };
+                writeln!(w, "Namespace declaration: {} -> {}", prefix, value)?;
+            }
+        }
+
+        for (key, value) in attributes {
+            if declared_prefix(key).is_some() {
+                continue;
+            }
+
+            write_indent(w, layout, depth + 2)?;
+            match key.find(':') {
+                Some(idx) => {
+                    let (prefix, local) = (&key[..idx], &key[idx + 1..]);
+                    write!(w, "Attribute: {}=\"{}\" (prefix: {}", local, value, prefix)?;
+                    match resolve_prefix(&self.bindings, prefix) {
+                        Some(uri) => writeln!(w, ", ns: {})", uri)?,
+                        None => writeln!(w, ")")?,
+                    }
+                }
+                None => writeln!(w, "Attribute: {}=\"{}\"", key, value)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn close_scope(&mut self) {
+        if let Some(len) = self.scopes.pop() {
+            self.bindings.truncate(len);
+        }
+    }
+
+    fn push_path(&mut self, local_name: &str) {
+        if let Some(ref mut path) = self.layout.path {
+            path.push(local_name);
+        }
+    }
+
+    fn pop_path(&mut self) {
+        if let Some(ref mut path) = self.layout.path {
+            path.pop();
+        }
+    }
+}
+
+impl<W: Write> EventVisitor for PrintVisitor<W> {
+    fn decl(&mut self, version: Option<&str>, encoding: Option<&str>, standalone: Option<&str>,
+            depth: usize) -> Result<()> {
+        let w = &mut self.writer;
+        let layout = &self.layout;
+
+        write_indent(w, layout, depth)?;
+        writeln!(w, "Declaration")?;
+
+        if let Some(v) = version {
+            write_indent(w, layout, depth + 1)?;
+            writeln!(w, "version=\"{}\"", v)?;
+        }
+
+        if let Some(v) = encoding {
+            write_indent(w, layout, depth + 1)?;
+            writeln!(w, "encoding=\"{}\"", v)?;
+        }
+
+        if let Some(v) = standalone {
+            write_indent(w, layout, depth + 1)?;
+            writeln!(w, "standalone=\"{}\"", v)?;
+        }
+
+        Ok(())
+    }
+
+    fn start(&mut self, ns: Option<&str>, prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.push_path(local_name);
+        self.write_element("Start", ns, prefix, local_name, attributes, depth)?;
+        Ok(())
+    }
+
+    fn empty(&mut self, ns: Option<&str>, prefix: Option<&str>, local_name: &str,
+             attributes: &[(String, String)], depth: usize) -> Result<()> {
+        self.push_path(local_name);
+        self.write_element("Empty", ns, prefix, local_name, attributes, depth)?;
+        self.close_scope();
+        self.pop_path();
+        Ok(())
+    }
+
+    fn end(&mut self, ns: Option<&str>, prefix: Option<&str>, local_name: &str, depth: usize) -> Result<()> {
+        write_tag_name(&mut self.writer, &self.layout, "End", ns, prefix, local_name, depth)?;
+        self.close_scope();
+        self.pop_path();
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, depth: usize) -> Result<()> {
+        write_text(&mut self.writer, &self.layout, "Text", text, depth + 1)?;
+        Ok(())
+    }
+
+    fn comment(&mut self, text: &str, depth: usize) -> Result<()> {
+        write_text(&mut self.writer, &self.layout, "Comment", text, depth)?;
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, depth: usize) -> Result<()> {
+        write_text(&mut self.writer, &self.layout, "CDATA", text, depth)?;
+        Ok(())
+    }
+
+    fn pi(&mut self, text: &str, depth: usize) -> Result<()> {
+        write_text(&mut self.writer, &self.layout, "Processing Instruction", text, depth)?;
+        Ok(())
+    }
+
+    fn doctype(&mut self, text: &str, depth: usize) -> Result<()> {
+        write_text(&mut self.writer, &self.layout, "Document Type", text, depth)?;
+        Ok(())
+    }
+}
+
+fn write_tag_name(w: &mut dyn Write, layout: &Layout, title: &str, ns: Option<&str>, prefix: Option<&str>,
+                  tag_name: &str, depth: usize) -> io::Result<()> {
+    write_indent(w, layout, depth)?;
+
+    match (prefix, ns) {
+        (Some(prefix), Some(ns)) => writeln!(w, "{}: {} (prefix: {}, ns: {})", title, tag_name, prefix, ns),
+        (Some(prefix), None) => writeln!(w, "{}: {} (prefix: {})", title, tag_name, prefix),
+        (None, Some(ns)) => writeln!(w, "{}: {} (ns: {})", title, tag_name, ns),
+        (None, None) => writeln!(w, "{}: {}", title, tag_name),
+    }
+}
+
+// How each line starts.
+#[derive(Clone, Debug)]
+struct Layout {
+    indent: String,
+    // Tracked only when paths are written.
+    path: Option<ElementPath>,
+}
+
+// Returns the declared prefix of an `xmlns` or `xmlns:*` attribute, empty for the default namespace.
+fn declared_prefix(key: &str) -> Option<&str> {
+    if key == "xmlns" {
+        Some("")
+    } else {
+        key.strip_prefix("xmlns:")
+    }
+}
+
+fn resolve_prefix<'a>(bindings: &'a [(String, String)], prefix: &str) -> Option<&'a str> {
+    if prefix == XML_PREFIX {
+        return Some(XML_NS);
+    }
+
+    bindings.iter().rev().find(|(p, _)| p == prefix).map(|(_, uri)| uri.as_str()).filter(|uri| !uri.is_empty())
+}
+
+fn write_text(w: &mut dyn Write, layout: &Layout, title: &str, text: &str, depth: usize) -> io::Result<()> {
+    write_indent(w, layout, depth)?;
+    writeln!(w, "{}: {:?}", title, text)
+}
+
+fn write_indent(w: &mut dyn Write, layout: &Layout, depth: usize) -> io::Result<()> {
+    if let Some(ref path) = layout.path {
+        write!(w, "{} ", path.as_indexed_xpath_string())?;
+    }
+
+    for _ in 0..depth {
+        w.write_all(layout.indent.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::walk_events;
+
+    fn print(text: &str, visitor: PrintVisitor<Vec<u8>>) -> String {
+        let mut visitor = visitor;
+        let mut reader = Reader::from_str(text);
+        reader.trim_text(true);
+        walk_events(&mut reader, &mut visitor).unwrap();
+        String::from_utf8(visitor.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn writes_to_a_buffer() {
+        let text = "<?xml version=\"1.0\"?><a x=\"1\"><b>text</b><!-- note --></a>";
+        let expected = "Declaration\n  version=\"1.0\"\nStart: a\n    Attribute: x=\"1\"\n  Start: b\n      \
+                        Text: \"text\"\n  End: b\n  Comment: \" note \"\nEnd: a\n";
+        assert_eq!(print(text, PrintVisitor::new(Vec::new())), expected);
+    }
+
+    #[test]
+    fn writes_custom_indent() {
+        let expected = "Start: a\n\tEmpty: b\nEnd: a\n";
+        assert_eq!(print("<a><b/></a>", PrintVisitor::new(Vec::new()).indent("\t")), expected);
+    }
+
+    #[test]
+    fn writes_namespaces() {
+        let text = "<p:a xmlns:p=\"urn:p\" p:x=\"1\"/>";
+        let expected = "Empty: a (prefix: p, ns: urn:p)\n    Namespace declaration: p -> urn:p\n    \
+                        Attribute: x=\"1\" (prefix: p, ns: urn:p)\n";
+        assert_eq!(print(text, PrintVisitor::new(Vec::new())), expected);
+    }
+
+    #[test]
+    fn writes_paths() {
+        let expected = "/a[1] Start: a\n/a[1]/b[1]   Empty: b\n/a[1] End: a\n";
+        assert_eq!(print("<a><b/></a>", PrintVisitor::new(Vec::new()).paths(true)), expected);
+    }
+
+    #[test]
+    fn writes_redefined_prefixes() {
+        let text = std::fs::read_to_string("data/prefixes.xml").unwrap();
+        let output = print(&text, PrintVisitor::new(Vec::new()));
+        let lines: Vec<_> = output.lines().map(str::trim).collect();
+
+        for line in &[
+            "Start: Envelope (prefix: soap, ns: http://schemas.xmlsoap.org/soap/envelope/)",
+            "Namespace declaration: (default) -> http://example.com/orders",
+            "Attribute: mustUnderstand=\"1\" (prefix: soap, ns: http://schemas.xmlsoap.org/soap/envelope/)",
+            "Start: Order (ns: http://example.com/orders)",
+            "Start: Item (prefix: soap, ns: http://example.com/items)",
+            "Namespace declaration: soap -> http://example.com/items",
+            "Attribute: lang=\"en\" (prefix: xml, ns: http://www.w3.org/XML/1998/namespace)",
+        ] {
+            assert!(lines.contains(line), "missing `{}` in:\n{}", line, output);
+        }
+    }
+}
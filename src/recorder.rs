@@ -0,0 +1,354 @@
+use std::io::{Read, Write};
+
+use crate::event::{Event, XmlSource};
+use crate::json::{parse_json, write_json_string, JsonValue};
+use crate::{DumpEvent, Error, Result};
+
+/// A source that records all events passing through it.
+///
+/// Events are recorded as owned `DumpEvent`s, so they can be replayed
+/// after the underlying source is consumed. `Event::Eof` is not recorded.
+pub struct XmlEventRecorder<S: XmlSource> {
+    source: S,
+    events: Vec<DumpEvent>,
+}
+
+impl<S: XmlSource> XmlEventRecorder<S> {
+    /// Creates a new recorder.
+    pub fn new(source: S) -> Self {
+        XmlEventRecorder {
+            source,
+            events: Vec::new(),
+        }
+    }
+
+    /// Reads all remaining events from the source.
+    pub fn record_all(&mut self) -> Result<()> {
+        while !matches!(self.next_event()?, Event::Eof) {}
+        Ok(())
+    }
+
+    /// Returns all recorded events.
+    pub fn events(&self) -> &[DumpEvent] {
+        &self.events
+    }
+
+    /// Returns an iterator over the recorded events.
+    pub fn replay(&self) -> Replay<'_> {
+        Replay::new(&self.events)
+    }
+
+    /// Returns the recorded events.
+    pub fn into_events(self) -> Vec<DumpEvent> {
+        self.events
+    }
+}
+
+impl<S: XmlSource> XmlSource for XmlEventRecorder<S> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        let event = self.source.next_event()?;
+        if let Some(e) = DumpEvent::from_event(&event)? {
+            self.events.push(e);
+        }
+
+        Ok(event)
+    }
+}
+
+/// An iterator over recorded events that supports seeking.
+#[derive(Clone, Debug)]
+pub struct Replay<'a> {
+    events: &'a [DumpEvent],
+    pos: usize,
+}
+
+impl<'a> Replay<'a> {
+    /// Creates a new iterator over the `events`.
+    pub fn new(events: &'a [DumpEvent]) -> Self {
+        Replay { events, pos: 0 }
+    }
+
+    /// Returns the index of the next event.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves to the event with the `index`.
+    ///
+    /// An index past the end exhausts the iterator.
+    pub fn seek(&mut self, index: usize) {
+        self.pos = index.min(self.events.len());
+    }
+}
+
+impl<'a> Iterator for Replay<'a> {
+    type Item = &'a DumpEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.get(self.pos)?;
+        self.pos += 1;
+        Some(event)
+    }
+}
+
+/// Writes events as a JSON array with one event object per line.
+///
+/// Each object has a `type` key and the fields of the corresponding `DumpEvent` variant.
+/// Missing values are written as `null` and attributes as an array of `[name, value]` pairs.
+pub fn save_to_json<W: Write>(events: &[DumpEvent], mut writer: W) -> Result<()> {
+    let w = &mut writer as &mut dyn Write;
+
+    write!(w, "[")?;
+    for (i, event) in events.iter().enumerate() {
+        if i != 0 {
+            write!(w, ",")?;
+        }
+        write!(w, "\n  ")?;
+
+        match *event {
+            DumpEvent::StartElement { ref name, ref ns, ref prefix, ref attrs } => {
+                write_element(w, "start", name, ns.as_deref(), prefix.as_deref(), Some(attrs))?;
+            }
+            DumpEvent::EmptyElement { ref name, ref ns, ref prefix, ref attrs } => {
+                write_element(w, "empty", name, ns.as_deref(), prefix.as_deref(), Some(attrs))?;
+            }
+            DumpEvent::EndElement { ref name, ref ns, ref prefix } => {
+                write_element(w, "end", name, ns.as_deref(), prefix.as_deref(), None)?;
+            }
+            DumpEvent::Text(ref text) => write_text(w, "text", text)?,
+            DumpEvent::CData(ref text) => write_text(w, "cdata", text)?,
+            DumpEvent::Comment(ref text) => write_text(w, "comment", text)?,
+            DumpEvent::DocType(ref text) => write_text(w, "doctype", text)?,
+            DumpEvent::PI { ref target, ref content } => {
+                write!(w, "{{\"type\":\"pi\",\"target\":")?;
+                write_json_string(w, target)?;
+                write!(w, ",\"content\":")?;
+                write_optional_string(w, content.as_deref())?;
+                write!(w, "}}")?;
+            }
+            DumpEvent::Decl { ref version, ref encoding, ref standalone } => {
+                write!(w, "{{\"type\":\"decl\",\"version\":")?;
+                write_optional_string(w, version.as_deref())?;
+                write!(w, ",\"encoding\":")?;
+                write_optional_string(w, encoding.as_deref())?;
+                write!(w, ",\"standalone\":")?;
+                write_optional_string(w, standalone.as_deref())?;
+                write!(w, "}}")?;
+            }
+        }
+    }
+    writeln!(w, "\n]")?;
+
+    Ok(())
+}
+
+/// Reads events written by `save_to_json`.
+pub fn load_from_json<R: Read>(mut reader: R) -> Result<Vec<DumpEvent>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    let items = match parse_json(&text)? {
+        JsonValue::Array(items) => items,
+        _ => return Err(schema_error("expected an array of events")),
+    };
+
+    let mut events = Vec::with_capacity(items.len());
+    for item in &items {
+        events.push(parse_event(item)?);
+    }
+
+    Ok(events)
+}
+
+fn write_element(w: &mut dyn Write, kind: &str, name: &str, ns: Option<&str>, prefix: Option<&str>,
+                 attrs: Option<&[(String, String)]>) -> Result<()> {
+    write!(w, "{{\"type\":\"{}\",\"name\":", kind)?;
+    write_json_string(w, name)?;
+    write!(w, ",\"ns\":")?;
+    write_optional_string(w, ns)?;
+    write!(w, ",\"prefix\":")?;
+    write_optional_string(w, prefix)?;
+
+    if let Some(attrs) = attrs {
+        write!(w, ",\"attrs\":[")?;
+        for (i, (key, value)) in attrs.iter().enumerate() {
+            if i != 0 {
+                write!(w, ",")?;
+            }
+
+            write!(w, "[")?;
+            write_json_string(w, key)?;
+            write!(w, ",")?;
+            write_json_string(w, value)?;
+            write!(w, "]")?;
+        }
+        write!(w, "]")?;
+    }
+
+    write!(w, "}}")?;
+    Ok(())
+}
+
+fn write_text(w: &mut dyn Write, kind: &str, text: &str) -> Result<()> {
+    write!(w, "{{\"type\":\"{}\",\"text\":", kind)?;
+    write_json_string(w, text)?;
+    write!(w, "}}")?;
+    Ok(())
+}
+
+fn write_optional_string(w: &mut dyn Write, s: Option<&str>) -> Result<()> {
+    match s {
+        Some(s) => write_json_string(w, s)?,
+        None => write!(w, "null")?,
+    }
+
+    Ok(())
+}
+
+fn parse_event(item: &JsonValue) -> Result<DumpEvent> {
+    let kind = item.get("type").and_then(JsonValue::as_str)
+        .ok_or_else(|| schema_error("an event without a type"))?;
+
+    let event = match kind {
+        "start" => DumpEvent::StartElement {
+            name: required_string(item, "name")?,
+            ns: optional_string(item, "ns")?,
+            prefix: optional_string(item, "prefix")?,
+            attrs: attributes(item)?,
+        },
+        "empty" => DumpEvent::EmptyElement {
+            name: required_string(item, "name")?,
+            ns: optional_string(item, "ns")?,
+            prefix: optional_string(item, "prefix")?,
+            attrs: attributes(item)?,
+        },
+        "end" => DumpEvent::EndElement {
+            name: required_string(item, "name")?,
+            ns: optional_string(item, "ns")?,
+            prefix: optional_string(item, "prefix")?,
+        },
+        "text" => DumpEvent::Text(required_string(item, "text")?),
+        "cdata" => DumpEvent::CData(required_string(item, "text")?),
+        "comment" => DumpEvent::Comment(required_string(item, "text")?),
+        "doctype" => DumpEvent::DocType(required_string(item, "text")?),
+        "pi" => DumpEvent::PI {
+            target: required_string(item, "target")?,
+            content: optional_string(item, "content")?,
+        },
+        "decl" => DumpEvent::Decl {
+            version: optional_string(item, "version")?,
+            encoding: optional_string(item, "encoding")?,
+            standalone: optional_string(item, "standalone")?,
+        },
+        _ => return Err(schema_error(&format!("unknown event type '{}'", kind))),
+    };
+
+    Ok(event)
+}
+
+fn required_string(item: &JsonValue, key: &str) -> Result<String> {
+    match item.get(key) {
+        Some(JsonValue::String(s)) => Ok(s.clone()),
+        _ => Err(schema_error(&format!("'{}' must be a string", key))),
+    }
+}
+
+fn optional_string(item: &JsonValue, key: &str) -> Result<Option<String>> {
+    match item.get(key) {
+        Some(JsonValue::String(s)) => Ok(Some(s.clone())),
+        Some(JsonValue::Null) | None => Ok(None),
+        _ => Err(schema_error(&format!("'{}' must be a string or null", key))),
+    }
+}
+
+fn attributes(item: &JsonValue) -> Result<Vec<(String, String)>> {
+    let items = match item.get("attrs") {
+        Some(JsonValue::Array(items)) => items,
+        None => return Ok(Vec::new()),
+        _ => return Err(schema_error("'attrs' must be an array")),
+    };
+
+    let mut attrs = Vec::with_capacity(items.len());
+    for a in items {
+        match *a {
+            JsonValue::Array(ref pair) => match pair.as_slice() {
+                [JsonValue::String(key), JsonValue::String(value)] => attrs.push((key.clone(), value.clone())),
+                _ => return Err(schema_error("an attribute must be a [name, value] pair")),
+            },
+            _ => return Err(schema_error("an attribute must be a [name, value] pair")),
+        }
+    }
+
+    Ok(attrs)
+}
+
+// The JSON itself is valid at this point, so there is no meaningful position.
+fn schema_error(message: &str) -> Error {
+    Error::Json { position: 0, message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::QuickXmlReader;
+
+    const DOCUMENT: &str = concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE root>\n",
+        "<root xmlns:p=\"urn:p\" id=\"1\"><!-- \"quoted\" -->",
+        "<p:item p:x=\"a&amp;b\">text\n</p:item><empty/><![CDATA[<raw>]]><?pi data?></root>",
+    );
+
+    fn record(text: &str) -> XmlEventRecorder<QuickXmlReader<&[u8]>> {
+        let mut recorder = XmlEventRecorder::new(QuickXmlReader::new(Reader::from_str(text)));
+        recorder.record_all().unwrap();
+        recorder
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let recorder = record(DOCUMENT);
+        assert!(recorder.events().len() > 10);
+
+        let mut json = Vec::new();
+        save_to_json(recorder.events(), &mut json).unwrap();
+        let loaded = load_from_json(json.as_slice()).unwrap();
+        assert_eq!(loaded, recorder.events());
+
+        let replayed: Vec<_> = Replay::new(&loaded).cloned().collect();
+        assert_eq!(replayed, recorder.into_events());
+    }
+
+    #[test]
+    fn replays_from_any_position() {
+        let recorder = record("<a><b/><c/></a>");
+        let events = recorder.events();
+        let mut replay = recorder.replay();
+
+        assert_eq!(replay.next(), events.first());
+        assert_eq!(replay.position(), 1);
+
+        replay.seek(events.len() - 1);
+        assert_eq!(replay.next(), events.last());
+        assert_eq!(replay.next(), None);
+
+        replay.seek(0);
+        assert_eq!(replay.clone().count(), events.len());
+
+        replay.seek(events.len() + 10);
+        assert_eq!(replay.position(), events.len());
+        assert_eq!(replay.next(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        for json in &["{}", "[{\"name\":\"a\"}]", "[{\"type\":\"start\",\"name\":1}]", "[{\"type\":\"what\"}]"] {
+            match load_from_json(json.as_bytes()) {
+                Err(Error::Json { .. }) => {}
+                r => panic!("unexpected result for {}: {:?}", json, r),
+            }
+        }
+    }
+}
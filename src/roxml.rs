@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+use std::io::Read;
+use std::vec;
+
+use crate::event::{Attr, Event, XmlSource};
+use crate::{open_input, Result};
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// A roxmltree reader that lowers the parsed tree into backend-agnostic events.
+///
+/// roxmltree is a DOM, so the whole document is parsed at once and the events
+/// are synthesized by walking the tree. An element without children is reported as Empty.
+///
+/// roxmltree doesn't keep the declaration, DOCTYPE, CDATA sections and prefixes of element names,
+/// so CDATA is reported as Text and references to entities are already expanded.
+/// Namespace declarations are reported before other attributes.
+pub struct RoxmltreeReader {
+    events: vec::IntoIter<Event<'static>>,
+}
+
+impl RoxmltreeReader {
+    /// Parses the document.
+    pub fn parse(text: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(text)?;
+        let mut events = Vec::new();
+        walk(doc.root(), &mut events);
+        Ok(RoxmltreeReader { events: events.into_iter() })
+    }
+
+    /// Reads and parses a file, like `Roxmltree::open`.
+    ///
+    /// `-` stands for the standard input.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut text = String::new();
+        open_input(path)?.read_to_string(&mut text)?;
+        RoxmltreeReader::parse(&text)
+    }
+}
+
+// Events are already owned, so they are moved out.
+impl XmlSource for RoxmltreeReader {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        Ok(self.events.next().unwrap_or(Event::Eof))
+    }
+}
+
+fn walk(parent: roxmltree::Node, events: &mut Vec<Event<'static>>) {
+    for node in parent.children() {
+        match node.node_type() {
+            roxmltree::NodeType::Element => {
+                // Namespaces are already resolved, including the inherited and default ones.
+                let ns: Option<Cow<[u8]>> = node.tag_name().namespace().map(|ns| ns.as_bytes().to_vec().into());
+                let name: Cow<[u8]> = node.tag_name().name().as_bytes().to_vec().into();
+                let attrs = collect_attributes(node);
+
+                if node.has_children() {
+                    events.push(Event::Start { ns: ns.clone(), prefix: None, name: name.clone(), attrs });
+                    walk(node, events);
+                    events.push(Event::End { ns, prefix: None, name });
+                } else {
+                    events.push(Event::Empty { ns, prefix: None, name, attrs });
+                }
+            }
+            roxmltree::NodeType::Text => events.push(Event::Text(node.text().unwrap_or("").to_string().into())),
+            roxmltree::NodeType::Comment => events.push(Event::Comment(node.text().unwrap_or("").to_string().into())),
+            roxmltree::NodeType::PI => {
+                if let Some(pi) = node.pi() {
+                    let text = match pi.value {
+                        Some(value) => format!("{} {}", pi.target, value),
+                        None => pi.target.to_string(),
+                    };
+                    events.push(Event::PI(text.into()));
+                }
+            }
+            roxmltree::NodeType::Root => {}
+        }
+    }
+}
+
+fn collect_attributes(node: roxmltree::Node) -> Vec<Attr<'static>> {
+    let mut attrs = namespace_declarations(node);
+
+    for a in node.attributes() {
+        // The `xml` prefix is bound implicitly and can't be redeclared.
+        let prefix = match a.namespace() {
+            Some(XML_NS) => Some("xml"),
+            Some(ns) => node.lookup_prefix(ns),
+            None => None,
+        };
+
+        let key = match prefix {
+            Some(prefix) => format!("{}:{}", prefix, a.name()),
+            None => a.name().to_string(),
+        };
+
+        attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: a.value().to_string().into() });
+    }
+
+    attrs
+}
+
+// roxmltree stores in-scope namespaces instead of `xmlns` attributes,
+// so declarations are restored by comparing them with the parent ones.
+fn namespace_declarations(node: roxmltree::Node) -> Vec<Attr<'static>> {
+    let inherited = |name: Option<&str>, uri: &str| {
+        node.parent_element()
+            .map(|p| p.namespaces().iter().any(|ns| ns.name() == name && ns.uri() == uri))
+            .unwrap_or(false)
+    };
+
+    let mut attrs = Vec::new();
+    for ns in node.namespaces() {
+        if ns.uri() == XML_NS || inherited(ns.name(), ns.uri()) {
+            continue;
+        }
+
+        let key = match ns.name() {
+            Some(name) => format!("xmlns:{}", name),
+            None => "xmlns".to_string(),
+        };
+        attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: ns.uri().to_string().into() });
+    }
+
+    attrs
+}
@@ -0,0 +1,97 @@
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::str;
+
+use crate::event::{Event, XmlSource};
+use crate::{AttributeMap, LangStack, QuickXmlReader, Result};
+
+/// SAX-style callbacks, driven by `dispatch`.
+///
+/// All methods do nothing by default. Unlike `EventVisitor`, there is no depth
+/// and an Empty element is reported as a pair of `start_element` and `end_element` calls.
+pub trait SaxHandler {
+    fn start_document(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_document(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn start_element(&mut self, _ns: Option<&str>, _local: &str, _attrs: &AttributeMap) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_element(&mut self, _ns: Option<&str>, _local: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Receives an already unescaped text.
+    fn characters(&mut self, _text: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Receives text together with its `xml:lang`, as tracked by `LangStack`. Passed to `characters` by default.
+    ///
+    /// CDATA is still passed to `cdata`, without a language.
+    fn characters_with_lang(&mut self, text: &str, _lang: Option<&str>) -> Result<()> {
+        self.characters(text)
+    }
+
+    /// Receives CDATA content. Passed to `characters` by default.
+    fn cdata(&mut self, text: &str) -> Result<()> {
+        self.characters(text)
+    }
+
+    fn comment(&mut self, _text: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn processing_instruction(&mut self, _text: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads all events from the `reader` and passes them to the `handler`.
+///
+/// The declaration and DOCTYPE are skipped.
+pub fn dispatch<R: BufRead, H: SaxHandler>(reader: &mut QuickXmlReader<R>, handler: &mut H) -> Result<()> {
+    let mut langs = LangStack::new();
+    handler.start_document()?;
+
+    loop {
+        // Owned, since the namespaces are read from the `reader` too.
+        let event = reader.next_event()?.into_owned();
+        langs.update(&event);
+        match event {
+            Event::Start { ref ns, ref name, ref attrs, .. } | Event::Empty { ref ns, ref name, ref attrs, .. } => {
+                let attrs = AttributeMap::from_attrs(attrs, reader.namespaces())?;
+                let ns = decode_ns(ns)?;
+                let name = str::from_utf8(name)?;
+
+                handler.start_element(ns, name, &attrs)?;
+                if let Event::Empty { .. } = event {
+                    handler.end_element(ns, name)?;
+                }
+            }
+            Event::End { ref ns, ref name, .. } => {
+                handler.end_element(decode_ns(ns)?, str::from_utf8(name)?)?;
+            }
+            Event::Text(ref text) => handler.characters_with_lang(text, langs.current_lang())?,
+            Event::CData(ref text) => handler.cdata(text)?,
+            Event::Comment(ref text) => handler.comment(text)?,
+            Event::PI(ref text) => handler.processing_instruction(text)?,
+            Event::DocType(_) | Event::Decl { .. } => {}
+            Event::Eof => break,
+        }
+    }
+
+    handler.end_document()
+}
+
+fn decode_ns<'a>(ns: &'a Option<Cow<[u8]>>) -> Result<Option<&'a str>> {
+    match *ns {
+        Some(ref ns) => Ok(Some(str::from_utf8(ns)?)),
+        None => Ok(None),
+    }
+}
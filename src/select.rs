@@ -0,0 +1,184 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Element, Node, PathSelector, Result};
+
+/// A result of `select`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Selected<'a> {
+    Element(&'a Element),
+    /// A value of a selected attribute.
+    Attribute(&'a str),
+}
+
+/// Selects elements or attribute values from the tree with a `PathSelector`, see `PathSelector::select`.
+///
+/// An invalid path is reported as `Error::InvalidPath`.
+pub fn select<'a>(root: &'a Element, path: &str) -> Result<Vec<Selected<'a>>> {
+    Ok(PathSelector::parse(path)?.select(root))
+}
+
+impl PathSelector {
+    /// Selects elements or attribute values from the tree.
+    ///
+    /// Unlike `matches`, nested elements are selected together with their matching ancestors.
+    /// Selected elements are returned in the document order.
+    pub fn select<'a>(&self, root: &'a Element) -> Vec<Selected<'a>> {
+        let mut contexts = vec![Context::Document(root)];
+
+        for step in &self.steps {
+            if step.descendants {
+                let mut all = Vec::new();
+                for context in &contexts {
+                    descendants_or_self(*context, &mut all);
+                }
+                contexts = unique(all);
+            }
+
+            let mut next = Vec::new();
+            for context in &contexts {
+                let matched = context.children()
+                    .into_iter()
+                    .filter(|e| self.matches_step(step, (e.ns.as_deref(), &e.name)));
+
+                match step.index {
+                    Some(index) => next.extend(matched.skip(index - 1).take(1).map(Context::Element)),
+                    None => next.extend(matched.map(Context::Element)),
+                }
+            }
+            contexts = unique(next);
+        }
+
+        // Children of nested contexts are collected per parent, so a descendant one can come first.
+        if self.steps.iter().any(|s| s.descendants) {
+            let mut all = Vec::new();
+            descendants_or_self(Context::Document(root), &mut all);
+            let order: HashMap<_, _> = all.into_iter().enumerate().map(|(i, c)| (c.id(), i)).collect();
+            contexts.sort_by_key(|c| order[&c.id()]);
+        }
+
+        contexts.into_iter()
+            .filter_map(|c| match c {
+                Context::Element(e) => match self.attribute {
+                    Some(ref name) => e.attribute(name).map(Selected::Attribute),
+                    None => Some(Selected::Element(e)),
+                },
+                Context::Document(_) => None,
+            })
+            .collect()
+    }
+}
+
+// The document is the parent of the root element, so `/root` can select the root.
+#[derive(Clone, Copy)]
+enum Context<'a> {
+    Document(&'a Element),
+    Element(&'a Element),
+}
+
+impl<'a> Context<'a> {
+    fn children(self) -> Vec<&'a Element> {
+        match self {
+            Context::Document(root) => vec![root],
+            Context::Element(e) => e.children.iter()
+                .filter_map(|n| match *n {
+                    Node::Element(ref e) => Some(e),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    fn id(self) -> (bool, *const Element) {
+        match self {
+            Context::Document(root) => (true, root),
+            Context::Element(e) => (false, e),
+        }
+    }
+}
+
+fn descendants_or_self<'a>(context: Context<'a>, out: &mut Vec<Context<'a>>) {
+    out.push(context);
+    for child in context.children() {
+        descendants_or_self(Context::Element(child), out);
+    }
+}
+
+// Nested contexts of a descendant step select the same elements several times.
+fn unique(contexts: Vec<Context<'_>>) -> Vec<Context<'_>> {
+    let mut seen = HashSet::new();
+    contexts.into_iter().filter(|c| seen.insert(c.id())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{parse_dom, Error};
+
+    const DOCUMENT: &str = concat!(
+        "<catalog xmlns:dc=\"urn:dc\">",
+        "<item id=\"1\"><name>Widget</name></item>",
+        "<group><item id=\"2\"><item id=\"3\"/></item></group>",
+        "<dc:item id=\"4\"/>",
+        "</catalog>",
+    );
+
+    fn ids(root: &Element, path: &str) -> Vec<String> {
+        select(root, path).unwrap()
+            .into_iter()
+            .map(|s| match s {
+                Selected::Element(e) => e.attribute("id").unwrap_or(&e.name).to_string(),
+                Selected::Attribute(value) => format!("@{}", value),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn selects_children() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        assert_eq!(ids(&root, "/catalog"), vec!["catalog"]);
+        assert_eq!(ids(&root, "/catalog/item"), vec!["1", "4"]);
+        assert_eq!(ids(&root, "/catalog/item/name"), vec!["name"]);
+        assert_eq!(ids(&root, "/catalog/*/item"), vec!["2"]);
+        assert_eq!(ids(&root, "/item"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn selects_nested_descendants_once() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        assert_eq!(ids(&root, "//item"), vec!["1", "2", "3", "4"]);
+        assert_eq!(ids(&root, "/catalog//item"), vec!["1", "2", "3", "4"]);
+        assert_eq!(ids(&root, "//group//"), vec!["2", "3"]);
+    }
+
+    #[test]
+    fn selects_by_index() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        assert_eq!(ids(&root, "/catalog/item[2]"), vec!["4"]);
+        assert_eq!(ids(&root, "/catalog/item[3]"), Vec::<String>::new());
+        // The first `item` child of each parent.
+        assert_eq!(ids(&root, "//item[1]"), vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn selects_attributes() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        assert_eq!(ids(&root, "//item/@id"), vec!["@1", "@2", "@3", "@4"]);
+        assert_eq!(ids(&root, "/catalog/@id"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn selects_bound_namespaces() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        let selected = PathSelector::parse("//dc:item").unwrap().bind("dc", "urn:dc").select(&root);
+        assert_eq!(selected.len(), 1);
+        assert!(matches!(selected[0], Selected::Element(e) if e.attribute("id") == Some("4")));
+    }
+
+    #[test]
+    fn rejects_invalid_path() {
+        let root = parse_dom(&mut Reader::from_str(DOCUMENT)).unwrap();
+        assert!(matches!(select(&root, "catalog"), Err(Error::InvalidPath { .. })));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use crate::{Error, Result};
+
+/// A simple path that selects elements, like `/catalog/item/name`.
+///
+/// A path is absolute and consists of child (`/`) and descendant (`//`) steps,
+/// where each step is a name or `*`. A trailing `//` selects all descendants,
+/// so `/catalog//` selects everything inside of the root `catalog`.
+///
+/// Matching is namespace-agnostic by default, so `dc:title` matches any `title`.
+/// Once a prefix is bound with `bind`, its steps match only elements in that namespace.
+///
+/// The same path is used both while streaming, with `matches`, and on a tree, with `select`.
+/// Only the latter supports a 1-based `[n]` index after a step and a trailing `@name` step
+/// that selects attribute values, like `//item[2]/@id`. Just like in XPath, `//item[1]`
+/// selects the first `item` child of each parent.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathSelector {
+    path: String,
+    pub(crate) steps: Vec<Step>,
+    // A qualified name of the trailing attribute step.
+    pub(crate) attribute: Option<String>,
+    bindings: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Step {
+    pub(crate) descendants: bool,
+    prefix: Option<String>,
+    // `None` is `*`.
+    name: Option<String>,
+    pub(crate) index: Option<usize>,
+}
+
+impl PathSelector {
+    /// Compiles a path. Malformed paths are reported as `Error::InvalidPath`.
+    pub fn parse(path: &str) -> Result<Self> {
+        let error = |message: &str| Error::InvalidPath { path: path.to_string(), message: message.to_string() };
+
+        if !path.starts_with('/') {
+            return Err(error("only absolute paths are supported"));
+        }
+
+        let mut steps = Vec::new();
+        let mut attribute = None;
+        let mut rest = path;
+        while !rest.is_empty() {
+            let descendants = rest.starts_with("//");
+            rest = rest.trim_start_matches('/');
+
+            if attribute.is_some() {
+                return Err(error("an attribute must be the last step"));
+            }
+
+            let end = rest.find('/').unwrap_or(rest.len());
+            let (step, tail) = rest.split_at(end);
+            rest = tail;
+
+            if step.is_empty() {
+                if descendants {
+                    // A trailing `//` is any descendant.
+                    steps.push(Step { descendants, prefix: None, name: None, index: None });
+                }
+                continue;
+            }
+
+            if let Some(name) = step.strip_prefix('@') {
+                // Attributes are matched by qualified names, so a prefix is kept as is.
+                if descendants || !name.split(':').all(is_name) {
+                    return Err(error("an attribute step must be a name after `/`"));
+                }
+
+                attribute = Some(name.to_string());
+                continue;
+            }
+
+            steps.push(parse_step(step, descendants).map_err(error)?);
+        }
+
+        if steps.is_empty() {
+            return Err(error("a path must have at least one step"));
+        }
+
+        Ok(PathSelector { path: path.to_string(), steps, attribute, bindings: HashMap::new() })
+    }
+
+    /// Binds a `prefix` of the path to a namespace `uri`.
+    pub fn bind(mut self, prefix: &str, uri: &str) -> Self {
+        self.bindings.insert(prefix.to_string(), uri.to_string());
+        self
+    }
+
+    /// Checks that an element matches the path.
+    ///
+    /// `path` contains namespaces and local names of the element and all of its ancestors,
+    /// starting from the root. Indexes and an attribute step require the siblings
+    /// and the element itself, so such paths are never matched, see `check_streamable`.
+    pub fn matches(&self, path: &[(Option<&str>, &str)]) -> bool {
+        self.is_streamable() && self.matches_from(0, path)
+    }
+
+    /// Checks that the path can be matched while streaming, i.e. has no indexes and no attribute step.
+    ///
+    /// Returns `Error::InvalidPath` otherwise.
+    pub fn check_streamable(&self) -> Result<()> {
+        if self.is_streamable() {
+            Ok(())
+        } else {
+            Err(Error::InvalidPath {
+                path: self.path.clone(),
+                message: "indexes and attributes can't be selected while streaming".to_string(),
+            })
+        }
+    }
+
+    fn is_streamable(&self) -> bool {
+        self.attribute.is_none() && self.steps.iter().all(|s| s.index.is_none())
+    }
+
+    fn matches_from(&self, step: usize, path: &[(Option<&str>, &str)]) -> bool {
+        let s = match self.steps.get(step) {
+            Some(s) => s,
+            None => return path.is_empty(),
+        };
+
+        // A descendant step can skip any number of elements.
+        let candidates = if s.descendants { path.len() } else { path.len().min(1) };
+        (0..candidates).any(|skip| self.matches_step(s, path[skip]) && self.matches_from(step + 1, &path[skip + 1..]))
+    }
+
+    pub(crate) fn matches_step(&self, step: &Step, (ns, name): (Option<&str>, &str)) -> bool {
+        let is_name_matched = step.name.as_ref().is_none_or(|n| n == name);
+        let is_ns_matched = match step.prefix.as_ref().and_then(|p| self.bindings.get(p)) {
+            Some(uri) => ns == Some(uri.as_str()),
+            None => true,
+        };
+
+        is_name_matched && is_ns_matched
+    }
+}
+
+fn parse_step(step: &str, descendants: bool) -> std::result::Result<Step, &'static str> {
+    let (step, index) = match step.find('[') {
+        Some(pos) => {
+            let predicate = step[pos + 1..].strip_suffix(']').ok_or("a predicate must end with `]`")?;
+            match predicate.parse::<usize>() {
+                Ok(index) if index > 0 => (&step[..pos], Some(index)),
+                _ => return Err("only positive index predicates are supported"),
+            }
+        }
+        None => (step, None),
+    };
+
+    let (prefix, name) = match step.split_once(':') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, step),
+    };
+
+    if !prefix.is_none_or(is_name) || (name != "*" && !is_name(name)) {
+        return Err("a step must be a name or `*`");
+    }
+
+    Ok(Step {
+        descendants,
+        prefix: prefix.map(str::to_string),
+        name: Some(name).filter(|n| *n != "*").map(str::to_string),
+        index,
+    })
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(path: &str, element: &[&str]) -> bool {
+        let element: Vec<_> = element.iter().map(|name| (None, *name)).collect();
+        PathSelector::parse(path).unwrap().matches(&element)
+    }
+
+    fn parse_error(path: &str) -> String {
+        match PathSelector::parse(path) {
+            Err(Error::InvalidPath { message, .. }) => message,
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn matches_child_steps() {
+        assert!(matches("/catalog/item", &["catalog", "item"]));
+        assert!(matches("/catalog/*", &["catalog", "item"]));
+        assert!(!matches("/catalog/item", &["catalog"]));
+        assert!(!matches("/catalog/item", &["catalog", "item", "name"]));
+        assert!(!matches("/item", &["catalog", "item"]));
+    }
+
+    #[test]
+    fn matches_descendant_steps() {
+        assert!(matches("//item", &["item"]));
+        assert!(matches("//item", &["catalog", "group", "item"]));
+        assert!(matches("/catalog//name", &["catalog", "item", "name"]));
+        assert!(!matches("/catalog//name", &["other", "item", "name"]));
+        assert!(matches("/catalog//", &["catalog", "item", "name"]));
+        assert!(!matches("/catalog//", &["catalog"]));
+    }
+
+    #[test]
+    fn matches_bound_namespaces() {
+        let path = [(Some("urn:dc"), "metadata"), (Some("urn:dc"), "title")];
+        let other = [(Some("urn:dc"), "metadata"), (Some("urn:other"), "title")];
+
+        let selector = PathSelector::parse("/metadata/dc:title").unwrap();
+        assert!(selector.matches(&path) && selector.matches(&other));
+
+        let selector = selector.bind("dc", "urn:dc");
+        assert!(selector.matches(&path));
+        assert!(!selector.matches(&other));
+    }
+
+    #[test]
+    fn never_streams_indexes_and_attributes() {
+        for path in &["/catalog/item[1]", "/catalog/item/@id"] {
+            let selector = PathSelector::parse(path).unwrap();
+            assert!(!selector.matches(&[(None, "catalog"), (None, "item")]));
+            assert!(matches!(selector.check_streamable(), Err(Error::InvalidPath { .. })));
+        }
+
+        assert!(PathSelector::parse("//item").unwrap().check_streamable().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        assert_eq!(parse_error("catalog"), "only absolute paths are supported");
+        assert_eq!(parse_error("/"), "a path must have at least one step");
+        assert_eq!(parse_error("/a/@id/b"), "an attribute must be the last step");
+        assert_eq!(parse_error("/a//@id"), "an attribute step must be a name after `/`");
+        assert_eq!(parse_error("/a[1"), "a predicate must end with `]`");
+        assert_eq!(parse_error("/a[0]"), "only positive index predicates are supported");
+        assert_eq!(parse_error("/a[last()]"), "only positive index predicates are supported");
+        assert_eq!(parse_error("/a b"), "a step must be a name or `*`");
+    }
+}
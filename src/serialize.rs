@@ -0,0 +1,262 @@
+use std::io::{BufRead, Write};
+
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::{CanonicalSerializer, Minifier, PrettyPrinter, Result};
+
+/// A writer of quick_xml events.
+///
+/// Implemented by `PrettyPrinter`, `CanonicalSerializer` and `Minifier`,
+/// so the output format can be chosen at runtime.
+pub trait EventWriter {
+    /// Writes a single event.
+    fn write_event(&mut self, event: &Event) -> Result<()>;
+}
+
+/// Reads all events from the `reader` and writes them back as indented XML.
+///
+/// Events are written as is, so entities, CDATA sections, comments and processing
+/// instructions are preserved exactly as they were in the input.
+/// Whitespace-only text is dropped and replaced by the new indentation,
+/// except in mixed content. See `PrettyPrinter` for details.
+///
+/// Returns the `writer` back.
+pub fn write_pretty<R: BufRead, W: Write>(reader: &mut Reader<R>, writer: W, indent_size: usize) -> Result<W> {
+    // The printer decides which whitespace is insignificant.
+    reader.trim_text(false);
+
+    let mut printer = PrettyPrinter::new(writer).indent(&" ".repeat(indent_size));
+    write_events(reader, &mut printer)?;
+    Ok(printer.into_inner())
+}
+
+/// Reads all events from the `reader` and writes them back as Canonical XML 1.0.
+///
+/// See `CanonicalSerializer` for details.
+///
+/// Returns the `writer` back.
+pub fn write_canonical<R: BufRead, W: Write>(reader: &mut Reader<R>, writer: W, with_comments: bool) -> Result<W> {
+    reader.trim_text(false);
+
+    let mut serializer = CanonicalSerializer::new(writer).with_comments(with_comments);
+    write_events(reader, &mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Reads all events from the `reader` and passes them to the `writer`, including `Event::Eof`.
+pub fn write_events<R: BufRead>(reader: &mut Reader<R>, writer: &mut impl EventWriter) -> Result<()> {
+    let mut buf = Vec::new();
+
+    loop {
+        let e = reader.read_event(&mut buf)?;
+        writer.write_event(&e)?;
+        if let Event::Eof = e {
+            break;
+        }
+        buf.clear();
+    }
+
+    Ok(())
+}
+
+/// Reads all events from the `reader` and writes them back unchanged using quick_xml's `Writer`.
+///
+/// The declaration, comments, processing instructions, CDATA sections, entities and empty-element tags
+/// are preserved, so re-parsing the output produces the same events. Whitespace inside of tags,
+/// like between attributes, and the quotes of attribute values are not preserved.
+///
+/// Returns the `writer` back.
+pub fn write_round_trip<R: BufRead, W: Write>(reader: &mut Reader<R>, writer: W) -> Result<W> {
+    reader.trim_text(false);
+    reader.expand_empty_elements(false);
+
+    let mut writer = Writer::new(writer);
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Eof => break,
+            e => writer.write_event(e)?,
+        };
+        buf.clear();
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// Reads all events from the `reader` and writes them back without insignificant whitespace.
+///
+/// See `Minifier` for details. Comments and processing instructions are kept.
+///
+/// Returns the `writer` back.
+pub fn write_minified<R: BufRead, W: Write>(reader: &mut Reader<R>, writer: W) -> Result<W> {
+    reader.trim_text(false);
+
+    let mut minifier = Minifier::new(writer);
+    write_events(reader, &mut minifier)?;
+    Ok(minifier.into_inner())
+}
+
+/// Returns whether the element has `xml:space="preserve"`.
+///
+/// `None` indicates that the attribute is not set.
+pub(crate) fn xml_space(e: &BytesStart) -> Result<Option<bool>> {
+    for a in e.attributes() {
+        let a = a?;
+        if a.key == b"xml:space" {
+            return Ok(Some(&*a.value == b"preserve"));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Buffers the events of an element until it's known whether the element has mixed content,
+/// i.e. significant text or CDATA as a direct child.
+///
+/// Such text can follow any number of child elements, so the whole element is buffered
+/// until its first significant text or its end.
+#[derive(Default)]
+pub(crate) struct ContentBuffer {
+    // Events starting with the Start event of the buffered element.
+    events: Vec<Event<'static>>,
+    // A number of open elements in `events`.
+    depth: usize,
+}
+
+impl ContentBuffer {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Buffers an event, starting with a Start one.
+    ///
+    /// Returns `true` when the buffered element has ended or turned out to have mixed content,
+    /// or at the end of the document, so the events must be taken and written.
+    pub fn push(&mut self, event: &Event) -> bool {
+        self.events.push(event.clone().into_owned());
+        match *event {
+            Event::Start(_) => self.depth += 1,
+            Event::End(_) => self.depth -= 1,
+            Event::Text(ref e) if is_blank(e) => {}
+            Event::Text(_) | Event::CData(_) => return self.depth == 1,
+            Event::Eof => return true,
+            _ => {}
+        }
+
+        self.depth == 0
+    }
+
+    /// Takes the buffered events, marking Start events of elements with mixed content.
+    ///
+    /// Elements that are still open at the end of the buffer are marked only
+    /// when their significant text is already buffered.
+    pub fn take(&mut self) -> Vec<(Event<'static>, bool)> {
+        let mut events: Vec<_> = std::mem::take(&mut self.events).into_iter().map(|e| (e, false)).collect();
+        self.depth = 0;
+
+        let mut open = Vec::new();
+        for i in 0..events.len() {
+            match events[i].0 {
+                Event::Start(_) => open.push(i),
+                Event::End(_) => {
+                    open.pop();
+                }
+                Event::Text(ref e) if is_blank(e) => {}
+                Event::Text(_) | Event::CData(_) => {
+                    if let Some(&start) = open.last() {
+                        events[start].1 = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+/// Returns whether the text contains only whitespace.
+pub(crate) fn is_blank(text: &BytesText) -> bool {
+    text.escaped().iter().all(|c| c.is_ascii_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dump_events, quick_xml_events, DumpEvent, DumpOptions};
+
+    // Whitespace-only text is replaced by the indentation, so it's ignored.
+    fn significant_events(text: &[u8]) -> Vec<DumpEvent> {
+        let mut events = quick_xml_events(text).unwrap();
+        events.retain(|e| !matches!(*e, DumpEvent::Text(ref text) if text.trim().is_empty()));
+        events
+    }
+
+    // Unlike `quick_xml_events`, the dump expands entities declared in the `DOCTYPE`.
+    // `records.xml` has many roots, so fragments are allowed.
+    fn dump(text: &[u8]) -> String {
+        let mut out = Vec::new();
+        dump_events(text, &mut out, &DumpOptions { fragment: true, ..DumpOptions::default() }).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    const DOCUMENT: &str = "<?xml version=\"1.0\"?>\n<!-- head -->\n<a x=\"1 &amp; 2\"><b>Tom &amp; Jerry</b>\
+                            <c><![CDATA[<raw> & text]]></c><?pi data?><d/></a>";
+
+    #[test]
+    fn pretty_output_round_trips() {
+        let out = write_pretty(&mut Reader::from_str(DOCUMENT), Vec::new(), 2).unwrap();
+        assert_eq!(significant_events(&out), significant_events(DOCUMENT.as_bytes()));
+    }
+
+    #[test]
+    fn pretty_output_keeps_cdata_and_entities() {
+        let out = write_pretty(&mut Reader::from_str(DOCUMENT), Vec::new(), 2).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("<![CDATA[<raw> & text]]>"), "{}", out);
+        assert!(out.contains("Tom &amp; Jerry"), "{}", out);
+        assert!(out.contains("x=\"1 &amp; 2\""), "{}", out);
+    }
+
+    #[test]
+    fn pretty_output_is_indented() {
+        let out = write_pretty(&mut Reader::from_str("<a><b><c/></b></a>"), Vec::new(), 2).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "<a>\n  <b>\n    <c/>\n  </b>\n</a>\n");
+    }
+
+    #[test]
+    fn round_trip_keeps_events_of_fixtures() {
+        let fixtures = [
+            "data/small.xml",
+            "data/medium.xml",
+            "data/namespaces.xml",
+            "data/prefixes.xml",
+            "data/records.xml",
+            "data/complex.svg",
+        ];
+
+        for path in &fixtures {
+            let text = std::fs::read(path).unwrap();
+            let out = write_round_trip(&mut Reader::from_reader(text.as_slice()), Vec::new()).unwrap();
+            assert_eq!(dump(&out), dump(&text), "{}", path);
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_empty_elements() {
+        let out = write_round_trip(&mut Reader::from_str(DOCUMENT), Vec::new()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("<d/>"), "{}", out);
+        assert!(out.starts_with("<?xml version=\"1.0\"?>\n<!-- head -->\n"), "{}", out);
+    }
+
+    #[test]
+    fn round_trip_keeps_declared_entities() {
+        let text = "<!DOCTYPE a [<!ENTITY name \"Usagi\">]>\n<a x=\"&name;\">Hello, &name; &amp; co</a>";
+        let out = write_round_trip(&mut Reader::from_str(text), Vec::new()).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), text);
+        assert_eq!(dump(&out), dump(text.as_bytes()));
+    }
+}
@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use quick_xml::events::attributes::Attribute;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::{OwnedEvent, Result};
+
+/// An iterator over subtrees of a document at a fixed depth, created by `split_at_depth`.
+pub struct DocumentSplitter<'a, R: BufRead> {
+    reader: &'a mut Reader<R>,
+    depth: usize,
+    buf: Vec<u8>,
+    // Raw namespace declarations of each open element above the split depth.
+    scopes: Vec<Vec<(Vec<u8>, Vec<u8>)>>,
+    done: bool,
+}
+
+/// Splits the document into independent sub-documents, one per element at the `depth`.
+///
+/// The root element has a depth of 0, so `depth = 1` yields each child of the root,
+/// like records of `<root><record/><record/></root>`.
+/// Each sub-document contains the events of a single element, from its Start event
+/// to the matching End one, or a single Empty event.
+/// Namespace declarations of its ancestors are copied onto its start tag,
+/// so the sub-document can be parsed on its own.
+/// Everything outside of these elements, like text between them, is skipped.
+///
+/// Events are taken as is, so entities declared in the `DOCTYPE` stay unexpanded.
+/// The iterator stops after the first error.
+pub fn split_at_depth<R: BufRead>(reader: &mut Reader<R>, depth: usize) -> DocumentSplitter<'_, R> {
+    DocumentSplitter {
+        reader,
+        depth,
+        buf: Vec::new(),
+        scopes: Vec::new(),
+        done: false,
+    }
+}
+
+impl<R: BufRead> Iterator for DocumentSplitter<'_, R> {
+    type Item = Result<Vec<OwnedEvent>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_subtree() {
+            Ok(Some(events)) => Some(Ok(events)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<R: BufRead> DocumentSplitter<'_, R> {
+    fn read_subtree(&mut self) -> Result<Option<Vec<OwnedEvent>>> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_event(&mut self.buf)? {
+                Event::Start(ref e) if self.scopes.len() == self.depth => {
+                    let start = with_namespaces(e, &self.scopes)?;
+                    let mut events = vec![Event::Start(start)];
+                    self.read_element(&mut events)?;
+                    return Ok(Some(events));
+                }
+                Event::Empty(ref e) if self.scopes.len() == self.depth => {
+                    return Ok(Some(vec![Event::Empty(with_namespaces(e, &self.scopes)?)]));
+                }
+                Event::Start(ref e) => self.scopes.push(namespace_declarations(e)?),
+                Event::End(_) => {
+                    self.scopes.pop();
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    // Reads the rest of the element up to and including its End event.
+    fn read_element(&mut self, events: &mut Vec<OwnedEvent>) -> Result<()> {
+        let mut depth = 0;
+
+        loop {
+            self.buf.clear();
+            let event = self.reader.read_event(&mut self.buf)?.into_owned();
+
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == 0 => {
+                    events.push(event);
+                    return Ok(());
+                }
+                Event::End(_) => depth -= 1,
+                Event::Eof => {
+                    return Err(quick_xml::Error::UnexpectedEof("end of a split element".to_string()).into());
+                }
+                _ => {}
+            }
+
+            events.push(event);
+        }
+    }
+}
+
+fn namespace_declarations(e: &BytesStart) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut declarations = Vec::new();
+    for a in e.attributes() {
+        let a = a?;
+        if a.key == b"xmlns" || a.key.starts_with(b"xmlns:") {
+            declarations.push((a.key.to_vec(), a.value.into_owned()));
+        }
+    }
+
+    Ok(declarations)
+}
+
+// Adds declarations of the ancestors that are not redeclared by the element or a closer ancestor.
+fn with_namespaces(e: &BytesStart, scopes: &[Vec<(Vec<u8>, Vec<u8>)>]) -> Result<BytesStart<'static>> {
+    let mut declared: Vec<Vec<u8>> = namespace_declarations(e)?.into_iter().map(|(key, _)| key).collect();
+    let mut start = e.to_owned();
+
+    for (key, value) in scopes.iter().rev().flatten() {
+        if declared.contains(key) {
+            continue;
+        }
+
+        // Values are already escaped, so they are added as is.
+        start.push_attribute(Attribute { key, value: Cow::Borrowed(value) });
+        declared.push(key.clone());
+    }
+
+    Ok(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Writer;
+
+    use super::*;
+    use crate::{quick_xml_events, DumpEvent};
+
+    const DOCUMENT: &str = concat!(
+        "<feed xmlns=\"urn:feed\" xmlns:m=\"urn:meta\">",
+        "<group xmlns:m=\"urn:group\">",
+        "<entry id=\"1\"><m:tag>a</m:tag></entry>",
+        "<entry xmlns=\"urn:own\" id=\"2\"/>",
+        "</group>",
+        "<group><entry id=\"3\">text</entry></group>",
+        "</feed>",
+    );
+
+    fn write(events: &[OwnedEvent]) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new());
+        for e in events {
+            writer.write_event(e).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    // Namespaces of elements in the re-parsed sub-document.
+    fn namespaces(text: &[u8]) -> Vec<(String, Option<String>)> {
+        quick_xml_events(text).unwrap().into_iter().filter_map(|e| match e {
+            DumpEvent::StartElement { name, ns, .. } | DumpEvent::EmptyElement { name, ns, .. } => Some((name, ns)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn splits_into_parsable_documents() {
+        let documents: Vec<_> = split_at_depth(&mut Reader::from_str(DOCUMENT), 2)
+            .map(|events| write(&events.unwrap()))
+            .collect();
+        assert_eq!(documents.len(), 3);
+
+        let ns = |name: &str, ns: &str| (name.to_string(), Some(ns.to_string()));
+        assert_eq!(namespaces(&documents[0]), [ns("entry", "urn:feed"), ns("tag", "urn:group")]);
+        assert_eq!(namespaces(&documents[1]), [ns("entry", "urn:own")]);
+        assert_eq!(namespaces(&documents[2]), [ns("entry", "urn:feed")]);
+
+        let text = String::from_utf8(documents[2].clone()).unwrap();
+        assert!(text.ends_with(">text</entry>"), "{}", text);
+        assert!(!text.contains("urn:group"), "{}", text);
+    }
+
+    #[test]
+    fn splits_children_of_root() {
+        let documents: Vec<_> = split_at_depth(&mut Reader::from_str(DOCUMENT), 1)
+            .map(|events| write(&events.unwrap()))
+            .collect();
+        assert_eq!(documents.len(), 2);
+
+        let ns = |name: &str, ns: &str| (name.to_string(), Some(ns.to_string()));
+        let expected = [
+            ns("group", "urn:feed"),
+            ns("entry", "urn:feed"),
+            ns("tag", "urn:group"),
+            ns("entry", "urn:own"),
+        ];
+        assert_eq!(namespaces(&documents[0]), expected);
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let mut reader = Reader::from_str("<a><b>");
+        let mut splitter = split_at_depth(&mut reader, 1);
+        assert!(splitter.next().unwrap().is_err());
+        assert!(splitter.next().is_none());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use quick_xml::Reader;
+
+use crate::event::{Event, XmlSource};
+use crate::json::write_json_string;
+use crate::{QuickXmlReader, Result};
+
+/// Shape metadata of a document, collected from its events.
+///
+/// Tags are counted by local names. The root element has a depth of 1.
+/// Text and CDATA are counted in bytes of their unescaped UTF-8 representation.
+/// Empty and whitespace-only text, like indentation, is not counted at all.
+/// `namespaces` contains resolved namespace URIs of all elements.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct DocumentStatistics {
+    pub tag_counts: HashMap<String, usize>,
+    pub max_depth: usize,
+    pub total_attributes: usize,
+    pub total_text_bytes: usize,
+    pub comment_count: usize,
+    pub text_count: usize,
+    pub cdata_count: usize,
+    pub pi_count: usize,
+    pub namespaces: HashSet<String>,
+    depth: usize,
+}
+
+impl DocumentStatistics {
+    /// Creates empty statistics.
+    pub fn new() -> Self {
+        DocumentStatistics::default()
+    }
+
+    /// Updates the statistics with an event.
+    ///
+    /// Namespace declarations are counted as attributes.
+    pub fn update(&mut self, event: &Event) {
+        match *event {
+            Event::Start { ref ns, ref name, ref attrs, .. } | Event::Empty { ref ns, ref name, ref attrs, .. } => {
+                if let Some(ns) = ns {
+                    let ns = String::from_utf8_lossy(ns);
+                    if !self.namespaces.contains(ns.as_ref()) {
+                        self.namespaces.insert(ns.into_owned());
+                    }
+                }
+
+                *self.tag_counts.entry(String::from_utf8_lossy(name).into_owned()).or_insert(0) += 1;
+                self.total_attributes += attrs.len();
+                self.max_depth = self.max_depth.max(self.depth + 1);
+
+                if let Event::Start { .. } = *event {
+                    self.depth += 1;
+                }
+            }
+            Event::End { .. } => {
+                self.depth = self.depth.saturating_sub(1);
+            }
+            Event::Text(ref text) if !text.trim().is_empty() => {
+                self.total_text_bytes += text.len();
+                self.text_count += 1;
+            }
+            Event::CData(ref text) => {
+                self.total_text_bytes += text.len();
+                self.cdata_count += 1;
+            }
+            Event::Comment(_) => {
+                self.comment_count += 1;
+            }
+            Event::PI(_) => {
+                self.pi_count += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the total number of elements.
+    pub fn element_count(&self) -> usize {
+        self.tag_counts.values().sum()
+    }
+
+    /// Writes the statistics as a JSON object.
+    ///
+    /// `tags` is an object of tag names and counts, sorted just like in the `Display` output.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        let w = &mut writer as &mut dyn Write;
+
+        write!(w, "{{\"elements\":{},\"max_depth\":{},\"attributes\":{},\"text_bytes\":{},",
+               self.element_count(), self.max_depth, self.total_attributes, self.total_text_bytes)?;
+        write!(w, "\"texts\":{},\"comments\":{},\"cdata\":{},\"pis\":{},\"namespaces\":{},\"tags\":{{",
+               self.text_count, self.comment_count, self.cdata_count, self.pi_count, self.namespaces.len())?;
+
+        for (i, (name, count)) in self.sorted_tags().into_iter().enumerate() {
+            if i != 0 {
+                write!(w, ",")?;
+            }
+
+            write_json_string(w, name)?;
+            write!(w, ":{}", count)?;
+        }
+        writeln!(w, "}}}}")?;
+
+        Ok(())
+    }
+
+    // The most frequent tags first, then by name, so the output is stable.
+    fn sorted_tags(&self) -> Vec<(&String, &usize)> {
+        let mut tags: Vec<_> = self.tag_counts.iter().collect();
+        tags.sort_by(|(n1, c1), (n2, c2)| c2.cmp(c1).then(n1.cmp(n2)));
+        tags
+    }
+}
+
+/// Collects statistics of the document from the `reader` in a single streaming pass.
+pub fn collect_statistics<R: BufRead>(reader: R) -> Result<DocumentStatistics> {
+    let mut reader = QuickXmlReader::new(Reader::from_reader(reader));
+    let mut stats = DocumentStatistics::new();
+
+    loop {
+        let e = reader.next_event()?;
+        if let Event::Eof = e {
+            break;
+        }
+
+        stats.update(&e);
+    }
+
+    Ok(stats)
+}
+
+impl fmt::Display for DocumentStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Elements:   {}", self.element_count())?;
+        writeln!(f, "Max depth:  {}", self.max_depth)?;
+        writeln!(f, "Attributes: {}", self.total_attributes)?;
+        writeln!(f, "Text bytes: {}", self.total_text_bytes)?;
+        writeln!(f, "Texts:      {}", self.text_count)?;
+        writeln!(f, "Comments:   {}", self.comment_count)?;
+        writeln!(f, "CDATA:      {}", self.cdata_count)?;
+        writeln!(f, "PIs:        {}", self.pi_count)?;
+        writeln!(f, "Namespaces: {}", self.namespaces.len())?;
+
+        if self.tag_counts.is_empty() {
+            return Ok(());
+        }
+
+        let tags = self.sorted_tags();
+
+        let width = tags.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0).max("Tag".len());
+        writeln!(f)?;
+        writeln!(f, "{:<width$}  Count", "Tag", width = width)?;
+        for (name, count) in tags {
+            writeln!(f, "{:<width$}  {}", name, count, width = width)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_counts() {
+        let text = concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<root xmlns=\"urn:a\" xmlns:b=\"urn:b\" id=\"1\">\n",
+            "  <!-- comment -->\n",
+            "  <item>text</item>\n",
+            "  <item><b:inner><![CDATA[data]]></b:inner></item>\n",
+            "  <?pi value?>\n",
+            "  <item x=\"1\" y=\"2\"/>\n",
+            "</root>\n",
+        );
+        let stats = collect_statistics(text.as_bytes()).unwrap();
+
+        assert_eq!(stats.element_count(), 5);
+        assert_eq!(stats.tag_counts["item"], 3);
+        assert_eq!(stats.tag_counts["inner"], 1);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.total_attributes, 5);
+        assert_eq!(stats.total_text_bytes, "text".len() + "data".len());
+        assert_eq!(stats.text_count, 1);
+        assert_eq!(stats.cdata_count, 1);
+        assert_eq!(stats.comment_count, 1);
+        assert_eq!(stats.pi_count, 1);
+    }
+
+    #[test]
+    fn collects_distinct_namespaces() {
+        let text = "<a:root xmlns:a=\"urn:a\" xmlns:b=\"urn:b\"><b:x/><a:y/><b:z/><plain/></a:root>";
+        let stats = collect_statistics(text.as_bytes()).unwrap();
+
+        let mut namespaces: Vec<_> = stats.namespaces.iter().map(String::as_str).collect();
+        namespaces.sort_unstable();
+        assert_eq!(namespaces, ["urn:a", "urn:b"]);
+    }
+
+    #[test]
+    fn sorts_tags_by_count() {
+        let stats = collect_statistics("<r><b/><a/><b/><c/><a/><b/></r>".as_bytes()).unwrap();
+        let mut json = Vec::new();
+        stats.write_json(&mut json).unwrap();
+
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.ends_with("\"tags\":{\"b\":3,\"a\":2,\"c\":1,\"r\":1}}\n"), "{}", json);
+    }
+}
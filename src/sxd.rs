@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+use std::io::Read;
+use std::vec;
+
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element, ParentOfChild};
+
+use crate::event::{Attr, Event, XmlSource};
+use crate::{open_input, Result};
+
+/// An sxd-document reader that lowers the parsed tree into backend-agnostic events.
+///
+/// sxd-document is a DOM, so the whole document is parsed at once and the events
+/// are synthesized by walking the tree. An element without children is reported as Empty.
+///
+/// sxd-document doesn't keep the declaration, DOCTYPE and CDATA sections,
+/// so CDATA is reported as Text. Text split by references is joined back.
+/// Namespace declarations are reported before other attributes, sorted by prefix.
+/// sxd-document ignores a default namespace declared on a prefixed element,
+/// so its unprefixed descendants have no namespace.
+pub struct SxdReader {
+    events: vec::IntoIter<Event<'static>>,
+}
+
+impl SxdReader {
+    /// Parses the document.
+    pub fn parse(text: &str) -> Result<Self> {
+        let package = sxd_document::parser::parse(text)?;
+        let mut events = Vec::new();
+        for child in package.as_document().root().children() {
+            match child {
+                ChildOfRoot::Element(e) => walk(e, &mut events),
+                ChildOfRoot::Comment(c) => events.push(Event::Comment(c.text().to_string().into())),
+                ChildOfRoot::ProcessingInstruction(pi) => events.push(pi_event(pi.target(), pi.value())),
+            }
+        }
+
+        Ok(SxdReader { events: events.into_iter() })
+    }
+
+    /// Reads and parses a file, like `SxdDocument::open`.
+    ///
+    /// `-` stands for the standard input.
+    pub fn open(path: &str) -> Result<Self> {
+        let mut text = String::new();
+        open_input(path)?.read_to_string(&mut text)?;
+        SxdReader::parse(&text)
+    }
+}
+
+// Events are already owned, so they are moved out.
+impl XmlSource for SxdReader {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        Ok(self.events.next().unwrap_or(Event::Eof))
+    }
+}
+
+fn walk(element: Element, events: &mut Vec<Event<'static>>) {
+    let ns: Option<Cow<[u8]>> = element.name().namespace_uri().map(|ns| ns.as_bytes().to_vec().into());
+    let prefix: Option<Cow<[u8]>> = element.preferred_prefix().map(|p| p.as_bytes().to_vec().into());
+    let name: Cow<[u8]> = element.name().local_part().as_bytes().to_vec().into();
+    let attrs = collect_attributes(element);
+
+    let children = element.children();
+    if children.is_empty() {
+        events.push(Event::Empty { ns, prefix, name, attrs });
+        return;
+    }
+
+    events.push(Event::Start { ns: ns.clone(), prefix: prefix.clone(), name: name.clone(), attrs });
+
+    for child in children {
+        match child {
+            ChildOfElement::Element(e) => walk(e, events),
+            ChildOfElement::Text(t) => push_text(t.text(), events),
+            ChildOfElement::Comment(c) => events.push(Event::Comment(c.text().to_string().into())),
+            ChildOfElement::ProcessingInstruction(pi) => events.push(pi_event(pi.target(), pi.value())),
+        }
+    }
+
+    events.push(Event::End { ns, prefix, name });
+}
+
+fn collect_attributes(element: Element) -> Vec<Attr<'static>> {
+    let mut attrs = namespace_declarations(element);
+
+    for a in element.attributes() {
+        let key = match a.preferred_prefix() {
+            Some(prefix) => format!("{}:{}", prefix, a.name().local_part()),
+            None => a.name().local_part().to_string(),
+        };
+
+        attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: a.value().to_string().into() });
+    }
+
+    attrs
+}
+
+// sxd-document registers prefixes on elements instead of keeping `xmlns` attributes,
+// so declarations are restored by comparing them with the parent ones.
+fn namespace_declarations(element: Element) -> Vec<Attr<'static>> {
+    let inherited = match element.parent() {
+        Some(ParentOfChild::Element(parent)) => parent.namespaces_in_scope(),
+        _ => Vec::new(),
+    };
+
+    let mut declared: Vec<_> = element.namespaces_in_scope().into_iter()
+        .filter(|ns| ns.prefix() != "xml")
+        .filter(|ns| !inherited.iter().any(|p| p.prefix() == ns.prefix() && p.uri() == ns.uri()))
+        .map(|ns| (format!("xmlns:{}", ns.prefix()), ns.uri()))
+        .collect();
+    declared.sort();
+
+    let mut attrs = Vec::new();
+    if let Some(uri) = element.default_namespace_uri() {
+        attrs.push(Attr { key: Cow::Borrowed(b"xmlns"), value: uri.to_string().into() });
+    }
+
+    for (key, uri) in declared {
+        attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: uri.to_string().into() });
+    }
+
+    attrs
+}
+
+// sxd-document keeps each reference and CDATA section as a separate text node.
+fn push_text(text: &str, events: &mut Vec<Event<'static>>) {
+    match events.last_mut() {
+        Some(Event::Text(prev)) => prev.to_mut().push_str(text),
+        _ => events.push(Event::Text(text.to_string().into())),
+    }
+}
+
+fn pi_event(target: &str, value: Option<&str>) -> Event<'static> {
+    match value {
+        Some(value) => Event::PI(format!("{} {}", target, value).into()),
+        None => Event::PI(target.to_string().into()),
+    }
+}
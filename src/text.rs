@@ -0,0 +1,215 @@
+use std::io::Write;
+
+use crate::event::{Event, XmlSource};
+use crate::{EventVisitor, Result};
+
+/// Writes only the text and CDATA of the document, like a crude XML-to-text.
+///
+/// Comments, processing instructions and the declaration are skipped.
+/// The `separator` is written between two text chunks that are separated by a tag,
+/// so `<p>a</p><p>b</p>` with `\n` becomes `a\nb`. It's empty by default.
+pub struct TextVisitor<W: Write> {
+    writer: W,
+    separator: String,
+    // Whether a tag was passed since the last written chunk.
+    at_boundary: bool,
+    is_first: bool,
+}
+
+impl<W: Write> TextVisitor<W> {
+    /// Creates a new visitor that writes to the `writer`.
+    pub fn new(writer: W) -> Self {
+        TextVisitor {
+            writer,
+            separator: String::new(),
+            at_boundary: false,
+            is_first: true,
+        }
+    }
+
+    /// Sets a separator between text chunks of different elements.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_text(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        if self.at_boundary && !self.is_first {
+            self.writer.write_all(self.separator.as_bytes())?;
+        }
+
+        self.writer.write_all(text.as_bytes())?;
+        self.at_boundary = false;
+        self.is_first = false;
+        Ok(())
+    }
+}
+
+impl<W: Write> EventVisitor for TextVisitor<W> {
+    fn start(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             _attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.at_boundary = true;
+        Ok(())
+    }
+
+    fn empty(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             _attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.at_boundary = true;
+        Ok(())
+    }
+
+    fn end(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str, _depth: usize) -> Result<()> {
+        self.at_boundary = true;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.write_text(text)
+    }
+
+    fn cdata(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.write_text(text)
+    }
+}
+
+/// Writes one line per top-level element: its text content or the value of an attribute.
+///
+/// The text content concatenates all descendant text and CDATA, like `string()` in XPath.
+/// Elements without the attribute are skipped. Meant for streams filtered by a path,
+/// like the ones of `dump_events` with `DumpOptions::select`, where every matched element is top-level.
+pub struct ValueVisitor<W: Write> {
+    writer: W,
+    attribute: Option<String>,
+    // A number of open elements, so nested ones are not written separately.
+    depth: usize,
+    text: String,
+}
+
+impl<W: Write> ValueVisitor<W> {
+    /// Creates a new visitor that writes text content to the `writer`.
+    pub fn new(writer: W) -> Self {
+        ValueVisitor {
+            writer,
+            attribute: None,
+            depth: 0,
+            text: String::new(),
+        }
+    }
+
+    /// Writes the value of an attribute with the qualified `name` instead of text content.
+    pub fn attribute(mut self, name: &str) -> Self {
+        self.attribute = Some(name.to_string());
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_line(&mut self, value: &str) -> Result<()> {
+        self.writer.write_all(value.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn open(&mut self, attributes: &[(String, String)]) -> Result<()> {
+        if self.depth == 0 {
+            let value = self.attribute.as_ref().and_then(|name| attributes.iter().find(|(key, _)| key == name));
+            if let Some((_, value)) = value {
+                self.write_line(value)?;
+            }
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 && self.attribute.is_none() {
+            let text = std::mem::take(&mut self.text);
+            self.write_line(&text)?;
+        }
+
+        Ok(())
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.depth > 0 && self.attribute.is_none() {
+            self.text.push_str(text);
+        }
+    }
+}
+
+impl<W: Write> EventVisitor for ValueVisitor<W> {
+    fn start(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.open(attributes)
+    }
+
+    fn empty(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        self.open(attributes)?;
+        self.close()
+    }
+
+    fn end(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str, _depth: usize) -> Result<()> {
+        self.close()
+    }
+
+    fn text(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_text(text);
+        Ok(())
+    }
+
+    fn cdata(&mut self, text: &str, _depth: usize) -> Result<()> {
+        self.push_text(text);
+        Ok(())
+    }
+}
+
+/// Concatenates all descendant text and CDATA of the current element.
+///
+/// Must be called right after the Start event of an element with the `end_tag` local name.
+/// Reads everything up to and including the matching End event.
+/// Nested elements are skipped, but their text is collected too.
+/// Nested elements with the same name are handled correctly.
+pub fn collect_text_content<S: XmlSource>(source: &mut S, end_tag: &[u8]) -> Result<String> {
+    let mut text = String::new();
+    let mut depth = 0;
+
+    loop {
+        match source.next_event()? {
+            Event::Start { ref name, .. } if name.as_ref() == end_tag => {
+                depth += 1;
+            }
+            Event::End { ref name, .. } if name.as_ref() == end_tag => {
+                if depth == 0 {
+                    break;
+                }
+
+                depth -= 1;
+            }
+            Event::Text(ref s) | Event::CData(ref s) => {
+                text.push_str(s);
+            }
+            Event::Eof => {
+                let name = String::from_utf8_lossy(end_tag);
+                return Err(quick_xml::Error::UnexpectedEof(format!("</{}>", name)).into());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(text)
+}
@@ -0,0 +1,153 @@
+use std::io::Write;
+
+use crate::{Element, Node, Result};
+
+/// Writes the tree like the `tree` command does.
+///
+/// ```text
+/// catalog
+/// ├── item id="1"
+/// │   ├── name: "Widget"
+/// │   └── # a comment
+/// └── item/ id="2"
+/// ```
+///
+/// An element that contains only text is written on a single line as `name: "text"`.
+/// Empty elements are marked with `/`, comments with `#` and processing instructions with `?`.
+/// Whitespace-only text is skipped.
+pub fn write_tree<W: Write>(root: &Element, mut writer: W) -> Result<()> {
+    let w = &mut writer as &mut dyn Write;
+    write_element_line(w, root)?;
+    write_children(w, root, &mut String::new())
+}
+
+fn write_children(w: &mut dyn Write, element: &Element, prefix: &mut String) -> Result<()> {
+    // A text-only element is already written on its own line.
+    if text_only(element).is_some() {
+        return Ok(());
+    }
+
+    let children: Vec<_> = element.children.iter().filter(|n| !is_blank(n)).collect();
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i + 1 == children.len();
+        write!(w, "{}{}", prefix, if is_last { "└── " } else { "├── " })?;
+
+        match **child {
+            Node::Element(ref e) => {
+                write_element_line(w, e)?;
+
+                let len = prefix.len();
+                prefix.push_str(if is_last { "    " } else { "│   " });
+                write_children(w, e, prefix)?;
+                prefix.truncate(len);
+            }
+            Node::Text(ref text) => writeln!(w, "{:?}", text.trim())?,
+            Node::Comment(ref text) => writeln!(w, "# {}", text.trim())?,
+            Node::PI(ref text) => writeln!(w, "? {}", text)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_element_line(w: &mut dyn Write, element: &Element) -> Result<()> {
+    write!(w, "{}", element.name)?;
+    if element.children.iter().all(is_blank) {
+        write!(w, "/")?;
+    }
+
+    for (key, value) in &element.attributes {
+        write!(w, " {}={:?}", key, value)?;
+    }
+
+    match text_only(element) {
+        Some(text) => writeln!(w, ": {:?}", text.trim())?,
+        None => writeln!(w)?,
+    }
+
+    Ok(())
+}
+
+// Returns the text of an element that has a single non-blank child and it's a text.
+fn text_only(element: &Element) -> Option<&str> {
+    let mut children = element.children.iter().filter(|n| !is_blank(n));
+    match (children.next(), children.next()) {
+        (Some(Node::Text(text)), None) => Some(text),
+        _ => None,
+    }
+}
+
+fn is_blank(node: &Node) -> bool {
+    match *node {
+        Node::Text(ref text) => text.trim().is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::parse_dom;
+
+    fn tree(text: &str) -> String {
+        let root = parse_dom(&mut Reader::from_str(text)).unwrap();
+        let mut out = Vec::new();
+        write_tree(&root, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_nested_elements() {
+        let text = "\
+<catalog>
+  <item id=\"1\">
+    <name>Widget</name>
+    <!-- a comment -->
+  </item>
+  <item id=\"2\"/>
+</catalog>";
+        let expected = "\
+catalog
+├── item id=\"1\"
+│   ├── name: \"Widget\"
+│   └── # a comment
+└── item/ id=\"2\"
+";
+        assert_eq!(tree(text), expected);
+    }
+
+    #[test]
+    fn writes_deep_branches() {
+        let text = "<a><b><c/><d>x</d></b><e><f/></e></a>";
+        let expected = "\
+a
+├── b
+│   ├── c/
+│   └── d: \"x\"
+└── e
+    └── f/
+";
+        assert_eq!(tree(text), expected);
+    }
+
+    #[test]
+    fn writes_mixed_content() {
+        let text = "<p class=\"intro\" lang=\"en\">Hello, <b>big</b> world!<?pi data?></p>";
+        let expected = "\
+p class=\"intro\" lang=\"en\"
+├── \"Hello,\"
+├── b: \"big\"
+├── \"world!\"
+└── ? pi data
+";
+        assert_eq!(tree(text), expected);
+    }
+
+    #[test]
+    fn escapes_attributes_and_text() {
+        let text = "<a title='say \"hi\"'>line\nbreak</a>";
+        assert_eq!(tree(text), "a title=\"say \\\"hi\\\"\": \"line\\nbreak\"\n");
+    }
+}
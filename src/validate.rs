@@ -0,0 +1,79 @@
+use std::str;
+
+use quick_xml::events::BytesStart;
+
+use crate::{Error, Result};
+
+/// Checks that the element has no two attributes with the same expanded name.
+///
+/// Prefixes are resolved using only the namespaces declared by the element itself,
+/// so `a:x` and `b:x` are duplicates when `a` and `b` are bound to the same URI on this element.
+/// Attributes with unbound prefixes are compared by their qualified names.
+///
+/// Returns `Error::DuplicateAttribute` for the second occurrence, with its offset
+/// from the start of the tag name.
+pub fn validate_no_duplicate_attrs(e: &BytesStart) -> Result<()> {
+    // quick_xml checks only qualified names and doesn't report the name itself,
+    // so its own checks are disabled.
+    let attrs = e.attributes().with_checks(false).collect::<std::result::Result<Vec<_>, _>>()?;
+    let resolve_prefix = |prefix: &[u8]| {
+        attrs.iter()
+            .find(|a| a.key.strip_prefix(b"xmlns:") == Some(prefix))
+            .map(|a| &*a.value)
+    };
+
+    let mut seen = Vec::new();
+    for a in &attrs {
+        let name = match a.key.iter().position(|c| *c == b':') {
+            Some(idx) if &a.key[..idx] != b"xmlns" => match resolve_prefix(&a.key[..idx]) {
+                Some(uri) => (Some(uri), &a.key[idx + 1..]),
+                None => (None, a.key),
+            },
+            _ => (None, a.key),
+        };
+
+        if seen.contains(&name) {
+            return Err(Error::DuplicateAttribute {
+                name: str::from_utf8(a.key)?.to_string(),
+                position: a.key.as_ptr() as usize - e.as_ptr() as usize,
+            });
+        }
+
+        seen.push(name);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(content: &str) -> Result<()> {
+        let name_len = content.find(' ').unwrap_or(content.len());
+        validate_no_duplicate_attrs(&BytesStart::borrowed(content.as_bytes(), name_len))
+    }
+
+    #[test]
+    fn reports_duplicate_name_and_offset() {
+        match validate("a x=\"1\" y=\"2\" x=\"3\"") {
+            Err(Error::DuplicateAttribute { name, position }) => {
+                assert_eq!(name, "x");
+                assert_eq!(position, 14);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_duplicate_expanded_names() {
+        let content = "a xmlns:p=\"urn:x\" xmlns:q=\"urn:x\" p:x=\"1\" q:x=\"2\"";
+        assert!(matches!(validate(content), Err(Error::DuplicateAttribute { ref name, .. }) if name == "q:x"));
+    }
+
+    #[test]
+    fn accepts_distinct_names() {
+        assert!(validate("a x=\"1\" y=\"2\"").is_ok());
+        assert!(validate("a xmlns:p=\"urn:p\" x=\"1\" p:x=\"2\"").is_ok());
+    }
+}
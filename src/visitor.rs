@@ -0,0 +1,49 @@
+use crate::Result;
+
+/// A receiver of the events produced by `walk_events`.
+///
+/// All methods do nothing by default, so an implementation
+/// can override only the events it is interested in.
+///
+/// `prefix` is the prefix of the element name, as written in the document.
+/// Backends that don't report prefixes pass `None`.
+pub trait EventVisitor {
+    fn decl(&mut self, _version: Option<&str>, _encoding: Option<&str>, _standalone: Option<&str>,
+            _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn start(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             _attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn empty(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str,
+             _attributes: &[(String, String)], _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self, _ns: Option<&str>, _prefix: Option<&str>, _local_name: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn text(&mut self, _text: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn comment(&mut self, _text: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn cdata(&mut self, _text: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn pi(&mut self, _text: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+
+    fn doctype(&mut self, _text: &str, _depth: usize) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+/// Specifies how whitespace-only text is handled.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum WhitespaceMode {
+    /// Keep all text. The default.
+    #[default]
+    Preserve,
+    /// Drop all whitespace-only text.
+    StripAll,
+    /// Drop whitespace-only text only when it's between two tags,
+    /// like indentation between elements.
+    StripInterElement,
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::{collect_events, DumpEvent, QuickXmlReader};
+
+    const DOCUMENT: &str = "<a>\n  <b>text</b> <i> </i><!-- c --> <d/>tail \n</a>";
+
+    // quick_xml reports empty text between adjacent markup, which isn't interesting here.
+    fn texts(mode: WhitespaceMode) -> Vec<String> {
+        let mut reader = QuickXmlReader::new(Reader::from_str(DOCUMENT));
+        reader.set_whitespace_mode(mode);
+        collect_events(&mut reader).unwrap().into_iter()
+            .filter_map(|e| match e {
+                DumpEvent::Text(text) if !text.is_empty() => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn preserve_keeps_all_text() {
+        assert_eq!(texts(WhitespaceMode::default()), ["\n  ", "text", " ", " ", " ", "tail \n"]);
+    }
+
+    #[test]
+    fn strip_all_drops_whitespace() {
+        assert_eq!(texts(WhitespaceMode::StripAll), ["text", "tail \n"]);
+    }
+
+    #[test]
+    fn strip_inter_element_keeps_whitespace_next_to_other_nodes() {
+        assert_eq!(texts(WhitespaceMode::StripInterElement), ["text", " ", "tail \n"]);
+    }
+
+    #[test]
+    fn strip_all_keeps_unicode_spaces() {
+        let mut reader = QuickXmlReader::new(Reader::from_str("<a><b>\u{a0}</b> <c>\u{2003}\t</c></a>"));
+        reader.set_whitespace_mode(WhitespaceMode::StripAll);
+        let texts: Vec<_> = collect_events(&mut reader).unwrap().into_iter()
+            .filter_map(|e| match e {
+                DumpEvent::Text(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, ["\u{a0}", "\u{2003}\t"]);
+    }
+}
@@ -0,0 +1,273 @@
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+use xml::common::{Position, XmlVersion};
+use xml::namespace::{Namespace, NS_XML_PREFIX, NS_XMLNS_PREFIX};
+use xml::reader::{EventReader, ParserConfig2, XmlEvent};
+use xml::ParserConfig;
+
+use crate::event::{visit, Attr, Event, XmlSource};
+use crate::{EventVisitor, Result};
+
+/// Returns an xml-rs parser config that produces an event stream
+/// as close as possible to the quick_xml one.
+///
+/// Note that xml-rs reports namespace declarations before other attributes.
+pub fn xml_rs_config() -> ParserConfig {
+    ParserConfig::new()
+        .trim_whitespace(false)
+        .whitespace_to_characters(true)
+        .cdata_to_characters(false)
+        .ignore_comments(false)
+        .coalesce_characters(false)
+}
+
+/// An xml-rs reader that lowers events into backend-agnostic ones.
+///
+/// The stream matches the quick_xml one: `<a/>` is reported as an Empty event
+/// instead of a Start/End pair and a Decl event is reported only when the document has a declaration.
+///
+/// The only difference is the order of attributes: namespace declarations are always reported first,
+/// since xml-rs doesn't keep them in the attribute list.
+pub struct XmlRsReader<R: Read> {
+    reader: EventReader<DeclarationSniffer<R>>,
+    ns_stack: Vec<Namespace>,
+    doctype_reported: bool,
+    // The event after a Start one, read ahead to detect an empty element.
+    peeked: Option<xml::reader::Result<XmlEvent>>,
+}
+
+impl<R: Read> XmlRsReader<R> {
+    /// Creates a new reader with `xml_rs_config`.
+    pub fn new(source: R) -> Self {
+        XmlRsReader::with_config(source, xml_rs_config())
+    }
+
+    /// Creates a new reader with a custom config.
+    ///
+    /// Options that change the event stream, like `trim_whitespace`, are applied as is.
+    pub fn with_config(source: R, config: impl Into<ParserConfig2>) -> Self {
+        let source = DeclarationSniffer { inner: source, head: Vec::new() };
+        XmlRsReader {
+            reader: EventReader::new_with_config(source, config),
+            ns_stack: vec![Namespace::empty()],
+            doctype_reported: false,
+            peeked: None,
+        }
+    }
+}
+
+// xml-rs events are already owned, so everything is moved.
+impl<R: Read> XmlSource for XmlRsReader<R> {
+    fn next_event(&mut self) -> Result<Event<'_>> {
+        let event = match self.peeked.take() {
+            Some(event) => event?,
+            None => self.reader.next()?,
+        };
+
+        // xml-rs doesn't report DOCTYPE as an event, but keeps it once it was parsed.
+        if !self.doctype_reported {
+            if let Some(doctype) = self.reader.doctype() {
+                self.doctype_reported = true;
+                let content = doctype.trim_start_matches("<!DOCTYPE").trim_end_matches('>').to_string();
+                self.peeked = Some(Ok(event));
+                return Ok(Event::DocType(content.into()));
+            }
+        }
+
+        let event = match event {
+            // xml-rs reports a default declaration when there is none.
+            // xml-rs reports a default declaration when there is none,
+            // and a default encoding when the declaration has none.
+            XmlEvent::StartDocument { version, encoding, standalone } => {
+                let declaration = match self.reader.source().declaration() {
+                    Some(declaration) => declaration,
+                    None => return self.next_event(),
+                };
+
+                let version = match version {
+                    XmlVersion::Version10 => "1.0",
+                    XmlVersion::Version11 => "1.1",
+                };
+
+                Event::Decl {
+                    version: Some(version.into()),
+                    encoding: if declaration.contains("encoding") { Some(encoding.into()) } else { None },
+                    standalone: standalone.map(|v| if v { "yes" } else { "no" }.into()),
+                }
+            }
+            XmlEvent::StartElement { name, attributes, namespace } => {
+                let mut attrs = namespace_declarations(self.ns_stack.last().unwrap(), &namespace);
+                for a in attributes {
+                    let key = match a.name.prefix {
+                        Some(prefix) => format!("{}:{}", prefix, a.name.local_name),
+                        None => a.name.local_name,
+                    };
+                    attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: a.value.into() });
+                }
+
+                let ns = name.namespace.filter(|ns| !ns.is_empty()).map(|ns| ns.into_bytes().into());
+                let prefix = name.prefix.map(|p| p.into_bytes().into());
+                let name = Cow::Owned(name.local_name.into_bytes());
+
+                // The End event of `<a/>` has the same position as the Start one.
+                let position = self.reader.position();
+                let next = self.reader.next();
+                if matches!(next, Ok(XmlEvent::EndElement { .. })) && self.reader.position() == position {
+                    Event::Empty { ns, prefix, name, attrs }
+                } else {
+                    self.peeked = Some(next);
+                    self.ns_stack.push(namespace);
+                    Event::Start { ns, prefix, name, attrs }
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                self.ns_stack.pop();
+
+                Event::End {
+                    ns: name.namespace.filter(|ns| !ns.is_empty()).map(|ns| ns.into_bytes().into()),
+                    prefix: name.prefix.map(|p| p.into_bytes().into()),
+                    name: Cow::Owned(name.local_name.into_bytes()),
+                }
+            }
+            XmlEvent::ProcessingInstruction { name, data } => match data {
+                Some(data) => Event::PI(format!("{} {}", name, data).into()),
+                None => Event::PI(name.into()),
+            },
+            XmlEvent::CData(text) => Event::CData(text.into()),
+            XmlEvent::Comment(text) => Event::Comment(text.into()),
+            XmlEvent::Characters(text) | XmlEvent::Whitespace(text) => Event::Text(text.into()),
+            XmlEvent::EndDocument => Event::Eof,
+        };
+
+        Ok(event)
+    }
+}
+
+// Enough for a declaration with all pseudo-attributes, even in UTF-16.
+const HEAD_LEN: usize = 256;
+
+// Keeps the first bytes of the input, so the declaration can be detected.
+struct DeclarationSniffer<R> {
+    inner: R,
+    head: Vec<u8>,
+}
+
+impl<R: Read> Read for DeclarationSniffer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let len = n.min(HEAD_LEN - self.head.len());
+        self.head.extend_from_slice(&buf[..len]);
+        Ok(n)
+    }
+}
+
+impl<R> DeclarationSniffer<R> {
+    // Returns the declaration text without `<?xml` and `?>`.
+    fn declaration(&self) -> Option<String> {
+        let head = match self.head.as_slice() {
+            [0xEF, 0xBB, 0xBF, rest @ ..] | [0xFF, 0xFE, rest @ ..] | [0xFE, 0xFF, rest @ ..] => rest,
+            head => head,
+        };
+
+        // Zero bytes are dropped, so UTF-16 looks like ASCII.
+        let head: Vec<u8> = head.iter().copied().filter(|b| *b != 0).collect();
+        if !(head.starts_with(b"<?xml") && head.get(5).is_some_and(u8::is_ascii_whitespace)) {
+            return None;
+        }
+
+        let head = String::from_utf8_lossy(&head[5..]);
+        Some(head.split("?>").next().unwrap_or_default().to_string())
+    }
+}
+
+/// Reads all events from the xml-rs `reader` and passes them to the `visitor`.
+///
+/// This is an xml-rs counterpart of `walk_events`.
+pub fn walk_xml_rs_events<R: Read>(mut reader: XmlRsReader<R>, visitor: &mut impl EventVisitor) -> Result<()> {
+    let mut depth = 0;
+
+    loop {
+        match reader.next_event()? {
+            Event::Eof => break,
+            ref e => visit(e, &mut depth, visitor)?,
+        }
+    }
+
+    Ok(())
+}
+
+// xml-rs moves `xmlns` attributes into the namespace map,
+// so we have to restore them by comparing it with the parent one.
+fn namespace_declarations(parent: &Namespace, namespace: &Namespace) -> Vec<Attr<'static>> {
+    let mut attrs = Vec::new();
+    for (prefix, uri) in namespace {
+        if prefix == NS_XML_PREFIX || prefix == NS_XMLNS_PREFIX {
+            continue;
+        }
+
+        if parent.get(prefix) == Some(uri) || (parent.get(prefix).is_none() && uri.is_empty()) {
+            continue;
+        }
+
+        let key = if prefix.is_empty() {
+            "xmlns".to_string()
+        } else {
+            format!("xmlns:{}", prefix)
+        };
+
+        attrs.push(Attr { key: Cow::Owned(key.into_bytes()), value: Cow::Owned(uri.to_string()) });
+    }
+
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{dump_events, DumpOptions, PrintVisitor};
+
+    // The output of the quick_xml example.
+    fn quick_xml_dump(text: &str) -> String {
+        let mut out = Vec::new();
+        dump_events(text.as_bytes(), &mut out, &DumpOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn xml_rs_dump(text: &str) -> String {
+        let mut visitor = PrintVisitor::new(Vec::new());
+        walk_xml_rs_events(XmlRsReader::new(text.as_bytes()), &mut visitor).unwrap();
+        String::from_utf8(visitor.into_inner()).unwrap()
+    }
+
+    // `data/prefixes.xml` declares several namespaces on one element, which xml-rs reorders.
+    #[test]
+    fn matches_quick_xml_output() {
+        for path in &["data/small.xml", "data/namespaces.xml", "data/base.xml", "data/lang.xml"] {
+            let text = fs::read_to_string(path).unwrap();
+            assert_eq!(xml_rs_dump(&text), quick_xml_dump(&text), "{}", path);
+        }
+    }
+
+    #[test]
+    fn reports_empty_elements() {
+        let text = "<a><b/><c></c></a>";
+        assert_eq!(xml_rs_dump(text), quick_xml_dump(text));
+    }
+
+    #[test]
+    fn skips_missing_declaration() {
+        let mut reader = XmlRsReader::new("<a/>".as_bytes());
+        assert!(matches!(reader.next_event().unwrap(), Event::Empty { .. }));
+        assert_eq!(reader.next_event().unwrap(), Event::Eof);
+    }
+
+    #[test]
+    fn keeps_missing_encoding() {
+        let mut reader = XmlRsReader::new("<?xml version=\"1.0\"?><a/>".as_bytes());
+        let expected = Event::Decl { version: Some("1.0".into()), encoding: None, standalone: None };
+        assert_eq!(reader.next_event().unwrap(), expected);
+    }
+}
@@ -0,0 +1,911 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::node::{NsElement, NsNode};
+
+/// An XPath expression is malformed or uses an unsupported feature.
+#[derive(Clone, PartialEq, Debug)]
+pub struct XPathError {
+    pub expr: String,
+    pub message: String,
+}
+
+impl fmt::Display for XPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "XPath expression '{}': {}", self.expr, self.message)
+    }
+}
+
+impl std::error::Error for XPathError {}
+
+/// A node selected by `evaluate_xpath`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum XPathNode<'a> {
+    /// The document node, i.e. the parent of the root element, selected by `/`.
+    Document(&'a NsElement),
+    Element(&'a NsElement),
+    /// `name` is a local name.
+    Attribute {
+        namespace: Option<&'a str>,
+        name: &'a str,
+        value: &'a str,
+    },
+    /// A text or CDATA child, which is the `index`-th child of the `parent`.
+    ///
+    /// The position identifies the node, since different empty texts can't be told apart otherwise.
+    Text {
+        text: &'a str,
+        parent: &'a NsElement,
+        index: usize,
+    },
+}
+
+/// A result of `evaluate_xpath`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum XPathResult<'a> {
+    /// Nodes in document order.
+    NodeSet(Vec<XPathNode<'a>>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+/// Evaluates an XPath 1.0 expression with the `root` element as the context node.
+///
+/// Only a subset of XPath is supported:
+///
+/// - absolute and relative location paths with `/`, `//`, `.`, `*` and `@`
+/// - the `child`, `descendant`, `descendant-or-self`, `attribute` and `self` axes
+/// - the `text()` and `node()` node tests
+/// - predicates, including positions like `[2]` and comparisons like `[@id='1']`
+/// - `or`, `and`, `=`, `!=`, `<`, `<=`, `>`, `>=` and `|`
+/// - `last`, `position`, `count`, `string`, `number`, `boolean`, `not`, `true`, `false`,
+///   `concat`, `contains`, `starts-with`, `string-length`, `normalize-space`, `local-name` and `name`
+///
+/// Name tests match local names, since there is no way to bind prefixes, so prefixed names are rejected.
+/// For the same reason, `name()` returns a local name too.
+/// Parent, ancestor, sibling, following, preceding and namespace axes, `..`, arithmetic,
+/// variables, comments and processing instructions are not supported.
+pub fn evaluate_xpath<'a>(root: &'a NsElement, expr: &str) -> Result<XPathResult<'a>, XPathError> {
+    let error = |message: String| XPathError { expr: expr.to_string(), message };
+
+    let tokens = tokenize(expr).map_err(error)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let parsed = parser.parse_expr().map_err(error)?;
+    if let Some(token) = parser.peek() {
+        return Err(error(format!("unexpected {:?}", token)));
+    }
+
+    let order = document_positions(root);
+    let ctx = Context { order: &order, node: XPathNode::Element(root), root, position: 1, size: 1 };
+    ctx.eval(&parsed).map_err(error)
+}
+
+type ParseResult<T> = Result<T, String>;
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    At,
+    Comma,
+    Pipe,
+    Dot,
+    DotDot,
+    Star,
+    Name(String),
+    Axis(String),
+    Literal(String),
+    Number(f64),
+    Op(&'static str),
+}
+
+fn tokenize(expr: &str) -> ParseResult<Vec<Token>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if next == Some('/') => Token::DoubleSlash,
+            '/' => Token::Slash,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '@' => Token::At,
+            ',' => Token::Comma,
+            '|' => Token::Pipe,
+            '*' => Token::Star,
+            '=' => Token::Op("="),
+            '!' if next == Some('=') => Token::Op("!="),
+            '<' if next == Some('=') => Token::Op("<="),
+            '<' => Token::Op("<"),
+            '>' if next == Some('=') => Token::Op(">="),
+            '>' => Token::Op(">"),
+            '.' if next == Some('.') => Token::DotDot,
+            '.' if !next.is_some_and(|c| c.is_ascii_digit()) => Token::Dot,
+            '"' | '\'' => {
+                let end = chars[i + 1..].iter().position(|q| *q == c).ok_or("an unterminated string literal")?;
+                let literal: String = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 2;
+                tokens.push(Token::Literal(literal));
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let len = chars[i..].iter().take_while(|c| c.is_ascii_digit() || **c == '.').count();
+                let number: String = chars[i..i + len].iter().collect();
+                let number = number.parse().map_err(|_| format!("an invalid number '{}'", number))?;
+                i += len;
+                tokens.push(Token::Number(number));
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut len = 0;
+                while let Some(&c) = chars.get(i + len) {
+                    let is_prefix = c == ':' && chars.get(i + len + 1).is_some_and(|c| c.is_alphabetic());
+                    if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || is_prefix {
+                        len += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                let name: String = chars[i..i + len].iter().collect();
+                i += len;
+                if chars.get(i) == Some(&':') && chars.get(i + 1) == Some(&':') {
+                    i += 2;
+                    tokens.push(Token::Axis(name));
+                } else {
+                    tokens.push(Token::Name(name));
+                }
+                continue;
+            }
+            c => return Err(format!("an unexpected character '{}'", c)),
+        };
+
+        i += match token {
+            Token::DoubleSlash | Token::DotDot | Token::Op("!=") | Token::Op("<=") | Token::Op(">=") => 2,
+            _ => 1,
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(&'static str, Box<Expr>, Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Call(String, Vec<Expr>),
+    Path {
+        absolute: bool,
+        steps: Vec<Step>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Attribute,
+    // `self` is a keyword.
+    Itself,
+}
+
+#[derive(Debug)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Node,
+}
+
+#[derive(Debug)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+impl Step {
+    // `//` is short for `/descendant-or-self::node()/`.
+    fn descendant_or_self() -> Self {
+        Step { axis: Axis::DescendantOrSelf, test: NodeTest::Node, predicates: Vec::new() }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_next(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    fn is_next_name(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == name)
+    }
+
+    fn expect(&mut self, token: Token) -> ParseResult<()> {
+        match self.next() {
+            Some(ref t) if *t == token => Ok(()),
+            Some(t) => Err(format!("expected {:?}, found {:?}", token, t)),
+            None => Err(format!("expected {:?}, found the end", token)),
+        }
+    }
+
+    fn parse_expr(&mut self) -> ParseResult<Expr> {
+        let mut left = self.parse_and()?;
+        while self.is_next_name("or") {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Expr> {
+        let mut left = self.parse_equality()?;
+        while self.is_next_name("and") {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.parse_equality()?));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_equality(&mut self) -> ParseResult<Expr> {
+        let mut left = self.parse_relational()?;
+        while let Some(&Token::Op(op @ "=")) | Some(&Token::Op(op @ "!=")) = self.peek() {
+            self.pos += 1;
+            left = Expr::Compare(op, Box::new(left), Box::new(self.parse_relational()?));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_relational(&mut self) -> ParseResult<Expr> {
+        let mut left = self.parse_union()?;
+        while let Some(&Token::Op(op @ ("<" | "<=" | ">" | ">="))) = self.peek() {
+            self.pos += 1;
+            left = Expr::Compare(op, Box::new(left), Box::new(self.parse_union()?));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_union(&mut self) -> ParseResult<Expr> {
+        let mut left = self.parse_primary()?;
+        while self.is_next(&Token::Pipe) {
+            self.pos += 1;
+            left = Expr::Union(Box::new(left), Box::new(self.parse_primary()?));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expr> {
+        match self.peek().cloned() {
+            Some(Token::Literal(s)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(s))
+            }
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Name(ref name))
+                if self.tokens.get(self.pos + 1) == Some(&Token::LParen) && !is_node_type(name) =>
+            {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if !self.is_next(&Token::RParen) {
+                    args.push(self.parse_expr()?);
+                    while self.is_next(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.parse_expr()?);
+                    }
+                }
+                self.expect(Token::RParen)?;
+                Ok(Expr::Call(name.clone(), args))
+            }
+            Some(_) => self.parse_path(),
+            None => Err("an unexpected end of the expression".to_string()),
+        }
+    }
+
+    fn parse_path(&mut self) -> ParseResult<Expr> {
+        let mut steps = Vec::new();
+        let absolute = match self.peek() {
+            Some(Token::Slash) => {
+                self.pos += 1;
+                // A lone `/` selects the document node.
+                if !self.is_step_start() {
+                    return Ok(Expr::Path { absolute: true, steps });
+                }
+                true
+            }
+            Some(Token::DoubleSlash) => {
+                self.pos += 1;
+                steps.push(Step::descendant_or_self());
+                true
+            }
+            _ => false,
+        };
+
+        steps.push(self.parse_step()?);
+        loop {
+            match self.peek() {
+                Some(Token::Slash) => self.pos += 1,
+                Some(Token::DoubleSlash) => {
+                    self.pos += 1;
+                    steps.push(Step::descendant_or_self());
+                }
+                _ => break,
+            }
+            steps.push(self.parse_step()?);
+        }
+
+        Ok(Expr::Path { absolute, steps })
+    }
+
+    fn is_step_start(&self) -> bool {
+        matches!(self.peek(),
+                 Some(Token::Name(_)) | Some(Token::Axis(_)) | Some(Token::Star) | Some(Token::At)
+                 | Some(Token::Dot) | Some(Token::DotDot))
+    }
+
+    fn parse_step(&mut self) -> ParseResult<Step> {
+        let axis = match self.peek().cloned() {
+            Some(Token::Dot) => {
+                self.pos += 1;
+                return Ok(Step { axis: Axis::Itself, test: NodeTest::Node, predicates: Vec::new() });
+            }
+            Some(Token::DotDot) => return Err("the parent axis is not supported".to_string()),
+            Some(Token::At) => {
+                self.pos += 1;
+                Axis::Attribute
+            }
+            Some(Token::Axis(name)) => {
+                self.pos += 1;
+                match name.as_str() {
+                    "child" => Axis::Child,
+                    "descendant" => Axis::Descendant,
+                    "descendant-or-self" => Axis::DescendantOrSelf,
+                    "attribute" => Axis::Attribute,
+                    "self" => Axis::Itself,
+                    _ => return Err(format!("the {} axis is not supported", name)),
+                }
+            }
+            _ => Axis::Child,
+        };
+
+        let test = match self.next() {
+            Some(Token::Star) => NodeTest::Any,
+            Some(Token::Name(name)) if self.is_next(&Token::LParen) => {
+                self.pos += 1;
+                self.expect(Token::RParen)?;
+                match name.as_str() {
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => return Err(format!("the {}() node test is not supported", name)),
+                }
+            }
+            Some(Token::Name(name)) => {
+                if name.contains(':') {
+                    return Err(format!("a prefixed name '{}' can't be resolved", name));
+                }
+                NodeTest::Name(name)
+            }
+            Some(t) => return Err(format!("expected a node test, found {:?}", t)),
+            None => return Err("expected a node test, found the end".to_string()),
+        };
+
+        let mut predicates = Vec::new();
+        while self.is_next(&Token::LBracket) {
+            self.pos += 1;
+            predicates.push(self.parse_expr()?);
+            self.expect(Token::RBracket)?;
+        }
+
+        Ok(Step { axis, test, predicates })
+    }
+}
+
+fn is_node_type(name: &str) -> bool {
+    matches!(name, "text" | "node" | "comment" | "processing-instruction")
+}
+
+#[derive(Clone, Copy)]
+struct Context<'a, 'b> {
+    root: &'a NsElement,
+    // Positions of all nodes in the document order, computed once per expression.
+    order: &'b HashMap<NodeId, usize>,
+    node: XPathNode<'a>,
+    position: usize,
+    size: usize,
+}
+
+impl<'a, 'b> Context<'a, 'b> {
+    fn with_node(self, node: XPathNode<'a>, position: usize, size: usize) -> Self {
+        Context { node, position, size, ..self }
+    }
+
+    fn eval(&self, expr: &Expr) -> ParseResult<XPathResult<'a>> {
+        let result = match *expr {
+            Expr::Or(ref a, ref b) => {
+                XPathResult::Boolean(to_bool(&self.eval(a)?) || to_bool(&self.eval(b)?))
+            }
+            Expr::And(ref a, ref b) => {
+                XPathResult::Boolean(to_bool(&self.eval(a)?) && to_bool(&self.eval(b)?))
+            }
+            Expr::Compare(op, ref a, ref b) => XPathResult::Boolean(compare(op, &self.eval(a)?, &self.eval(b)?)),
+            Expr::Union(ref a, ref b) => match (self.eval(a)?, self.eval(b)?) {
+                (XPathResult::NodeSet(mut a), XPathResult::NodeSet(b)) => {
+                    a.extend(b);
+                    XPathResult::NodeSet(document_order(self.order, a))
+                }
+                _ => return Err("`|` requires node-sets".to_string()),
+            },
+            Expr::Literal(ref s) => XPathResult::String(s.clone()),
+            Expr::Number(n) => XPathResult::Number(n),
+            Expr::Call(ref name, ref args) => self.call(name, args)?,
+            Expr::Path { absolute, ref steps } => {
+                let mut nodes = vec![if absolute { XPathNode::Document(self.root) } else { self.node }];
+                for step in steps {
+                    let mut next = Vec::new();
+                    for node in nodes {
+                        next.extend(self.eval_step(node, step)?);
+                    }
+                    nodes = document_order(self.order, next);
+                }
+                XPathResult::NodeSet(nodes)
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn eval_step(&self, node: XPathNode<'a>, step: &Step) -> ParseResult<Vec<XPathNode<'a>>> {
+        let mut nodes: Vec<_> = axis_nodes(node, step.axis).into_iter()
+            .filter(|n| matches_test(*n, &step.test, step.axis))
+            .collect();
+
+        for predicate in &step.predicates {
+            let size = nodes.len();
+            let mut selected = Vec::new();
+            for (i, n) in nodes.into_iter().enumerate() {
+                let ctx = self.with_node(n, i + 1, size);
+                let keep = match ctx.eval(predicate)? {
+                    XPathResult::Number(position) => position == (i + 1) as f64,
+                    ref value => to_bool(value),
+                };
+
+                if keep {
+                    selected.push(n);
+                }
+            }
+            nodes = selected;
+        }
+
+        Ok(nodes)
+    }
+
+    fn call(&self, name: &str, args: &[Expr]) -> ParseResult<XPathResult<'a>> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.eval(arg)?);
+        }
+
+        let arity = |min: usize, max: usize| {
+            if values.len() < min || values.len() > max {
+                Err(format!("wrong number of arguments for {}()", name))
+            } else {
+                Ok(())
+            }
+        };
+
+        // Functions with an optional argument use the context node by default.
+        let context = XPathResult::NodeSet(vec![self.node]);
+        let first = values.first().unwrap_or(&context);
+
+        let result = match name {
+            "last" => {
+                arity(0, 0)?;
+                XPathResult::Number(self.size as f64)
+            }
+            "position" => {
+                arity(0, 0)?;
+                XPathResult::Number(self.position as f64)
+            }
+            "count" => {
+                arity(1, 1)?;
+                match values[0] {
+                    XPathResult::NodeSet(ref nodes) => XPathResult::Number(nodes.len() as f64),
+                    _ => return Err("count() requires a node-set".to_string()),
+                }
+            }
+            "string" => {
+                arity(0, 1)?;
+                XPathResult::String(to_string(first))
+            }
+            "number" => {
+                arity(0, 1)?;
+                XPathResult::Number(to_number(first))
+            }
+            "boolean" => {
+                arity(1, 1)?;
+                XPathResult::Boolean(to_bool(&values[0]))
+            }
+            "not" => {
+                arity(1, 1)?;
+                XPathResult::Boolean(!to_bool(&values[0]))
+            }
+            "true" | "false" => {
+                arity(0, 0)?;
+                XPathResult::Boolean(name == "true")
+            }
+            "concat" => {
+                arity(2, usize::MAX)?;
+                XPathResult::String(values.iter().map(to_string).collect())
+            }
+            "contains" => {
+                arity(2, 2)?;
+                XPathResult::Boolean(to_string(&values[0]).contains(&to_string(&values[1])))
+            }
+            "starts-with" => {
+                arity(2, 2)?;
+                XPathResult::Boolean(to_string(&values[0]).starts_with(&to_string(&values[1])))
+            }
+            "string-length" => {
+                arity(0, 1)?;
+                XPathResult::Number(to_string(first).chars().count() as f64)
+            }
+            "normalize-space" => {
+                arity(0, 1)?;
+                XPathResult::String(to_string(first).split_whitespace().collect::<Vec<_>>().join(" "))
+            }
+            "local-name" | "name" => {
+                arity(0, 1)?;
+                let name = match *first {
+                    XPathResult::NodeSet(ref nodes) => match nodes.first() {
+                        Some(XPathNode::Element(n)) => n.name.as_str(),
+                        Some(XPathNode::Attribute { name, .. }) => name,
+                        _ => "",
+                    },
+                    _ => return Err(format!("{}() requires a node-set", name)),
+                };
+                XPathResult::String(name.to_string())
+            }
+            _ => return Err(format!("the {}() function is not supported", name)),
+        };
+
+        Ok(result)
+    }
+}
+
+fn axis_nodes(node: XPathNode<'_>, axis: Axis) -> Vec<XPathNode<'_>> {
+    let mut nodes = Vec::new();
+    match axis {
+        Axis::Child => push_children(node, &mut nodes),
+        Axis::Descendant => push_descendants(node, &mut nodes),
+        Axis::DescendantOrSelf => {
+            nodes.push(node);
+            push_descendants(node, &mut nodes);
+        }
+        Axis::Attribute => {
+            if let XPathNode::Element(n) = node {
+                // Attributes are unordered, so they are sorted to keep results stable.
+                let mut attrs: Vec<_> = n.attributes.iter().collect();
+                attrs.sort();
+                for ((ns, name), value) in attrs {
+                    nodes.push(XPathNode::Attribute { namespace: ns.as_deref(), name, value });
+                }
+            }
+        }
+        Axis::Itself => nodes.push(node),
+    }
+
+    nodes
+}
+
+fn push_children<'a>(node: XPathNode<'a>, out: &mut Vec<XPathNode<'a>>) {
+    match node {
+        XPathNode::Document(root) => out.push(XPathNode::Element(root)),
+        XPathNode::Element(n) => {
+            for (index, child) in n.children.iter().enumerate() {
+                match *child {
+                    NsNode::Element(ref e) => out.push(XPathNode::Element(e)),
+                    NsNode::Text(ref text) | NsNode::CData(ref text) => {
+                        out.push(XPathNode::Text { text, parent: n, index })
+                    }
+                    NsNode::Comment(_) => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn push_descendants<'a>(node: XPathNode<'a>, out: &mut Vec<XPathNode<'a>>) {
+    let mut children = Vec::new();
+    push_children(node, &mut children);
+    for child in children {
+        out.push(child);
+        push_descendants(child, out);
+    }
+}
+
+// `*` and names match the principal node type of the axis: attributes or elements.
+fn matches_test(node: XPathNode<'_>, test: &NodeTest, axis: Axis) -> bool {
+    match *test {
+        NodeTest::Node => true,
+        NodeTest::Text => matches!(node, XPathNode::Text { .. }),
+        NodeTest::Any => match node {
+            XPathNode::Attribute { .. } => axis == Axis::Attribute,
+            XPathNode::Element(_) => axis != Axis::Attribute,
+            _ => false,
+        },
+        NodeTest::Name(ref test) => match node {
+            XPathNode::Attribute { name, .. } => axis == Axis::Attribute && name == test,
+            XPathNode::Element(n) => axis != Axis::Attribute && n.name == *test,
+            _ => false,
+        },
+    }
+}
+
+// Steps over several contexts and unions can select the same nodes several times and out of order.
+fn document_order<'a>(order: &HashMap<NodeId, usize>, nodes: Vec<XPathNode<'a>>) -> Vec<XPathNode<'a>> {
+    let mut seen = HashSet::new();
+    let mut nodes: Vec<_> = nodes.into_iter().filter(|n| seen.insert(node_id(*n))).collect();
+    nodes.sort_by_key(|n| order[&node_id(*n)]);
+    nodes
+}
+
+// Attributes follow their element, like in the XPath data model.
+fn document_positions(root: &NsElement) -> HashMap<NodeId, usize> {
+    let mut all = vec![XPathNode::Document(root)];
+    push_descendants(XPathNode::Document(root), &mut all);
+
+    let mut order = HashMap::new();
+    for node in all {
+        order.insert(node_id(node), order.len());
+        for attr in axis_nodes(node, Axis::Attribute) {
+            order.insert(node_id(attr), order.len());
+        }
+    }
+
+    order
+}
+
+// A kind and an address of a node. Elements are identified by themselves,
+// texts by their parent and index, and attributes by their names, since values can be empty.
+type NodeId = (u8, usize, usize);
+
+fn node_id(node: XPathNode<'_>) -> NodeId {
+    match node {
+        XPathNode::Document(n) => (0, n as *const NsElement as usize, 0),
+        XPathNode::Element(n) => (1, n as *const NsElement as usize, 0),
+        XPathNode::Attribute { name, .. } => (2, name.as_ptr() as usize, 0),
+        XPathNode::Text { parent, index, .. } => (3, parent as *const NsElement as usize, index),
+    }
+}
+
+fn string_value(node: XPathNode<'_>) -> String {
+    match node {
+        XPathNode::Document(n) | XPathNode::Element(n) => {
+            let mut text = String::new();
+            push_text(n, &mut text);
+            text
+        }
+        XPathNode::Attribute { value, .. } => value.to_string(),
+        XPathNode::Text { text, .. } => text.to_string(),
+    }
+}
+
+fn push_text(node: &NsElement, out: &mut String) {
+    for child in &node.children {
+        match *child {
+            NsNode::Element(ref n) => push_text(n, out),
+            NsNode::Text(ref text) | NsNode::CData(ref text) => out.push_str(text),
+            NsNode::Comment(_) => {}
+        }
+    }
+}
+
+fn to_string(value: &XPathResult) -> String {
+    match *value {
+        XPathResult::NodeSet(ref nodes) => nodes.first().map(|n| string_value(*n)).unwrap_or_default(),
+        XPathResult::String(ref s) => s.clone(),
+        XPathResult::Number(n) => number_to_string(n),
+        XPathResult::Boolean(b) => b.to_string(),
+    }
+}
+
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n.fract() == 0.0 {
+        format!("{:.0}", n)
+    } else {
+        n.to_string()
+    }
+}
+
+fn to_number(value: &XPathResult) -> f64 {
+    match *value {
+        XPathResult::Number(n) => n,
+        XPathResult::Boolean(b) => if b { 1.0 } else { 0.0 },
+        _ => to_string(value).trim().parse().unwrap_or(f64::NAN),
+    }
+}
+
+fn to_bool(value: &XPathResult) -> bool {
+    match *value {
+        XPathResult::NodeSet(ref nodes) => !nodes.is_empty(),
+        XPathResult::String(ref s) => !s.is_empty(),
+        XPathResult::Number(n) => n != 0.0 && !n.is_nan(),
+        XPathResult::Boolean(b) => b,
+    }
+}
+
+// Node-sets are compared by the string values of their nodes, converted to the type
+// of the other operand, and the comparison is true if it's true for any node.
+fn compare(op: &str, a: &XPathResult, b: &XPathResult) -> bool {
+    match (a, b) {
+        (XPathResult::NodeSet(a), XPathResult::NodeSet(b)) => a.iter().any(|x| {
+            b.iter().any(|y| compare_atoms(op, &XPathResult::String(string_value(*x)),
+                                           &XPathResult::String(string_value(*y))))
+        }),
+        (XPathResult::NodeSet(_), XPathResult::Boolean(_)) | (XPathResult::Boolean(_), XPathResult::NodeSet(_)) => {
+            compare_atoms(op, &XPathResult::Boolean(to_bool(a)), &XPathResult::Boolean(to_bool(b)))
+        }
+        (XPathResult::NodeSet(nodes), other) => nodes.iter()
+            .any(|n| compare_atoms(op, &XPathResult::String(string_value(*n)), other)),
+        (other, XPathResult::NodeSet(nodes)) => nodes.iter()
+            .any(|n| compare_atoms(op, other, &XPathResult::String(string_value(*n)))),
+        _ => compare_atoms(op, a, b),
+    }
+}
+
+fn compare_atoms(op: &str, a: &XPathResult, b: &XPathResult) -> bool {
+    let is = |v: &XPathResult, bool_type: bool| match *v {
+        XPathResult::Boolean(_) => bool_type,
+        XPathResult::Number(_) => !bool_type,
+        _ => false,
+    };
+
+    match op {
+        "=" | "!=" => {
+            let equal = if is(a, true) || is(b, true) {
+                to_bool(a) == to_bool(b)
+            } else if is(a, false) || is(b, false) {
+                to_number(a) == to_number(b)
+            } else {
+                to_string(a) == to_string(b)
+            };
+
+            (op == "=") == equal
+        }
+        "<" => to_number(a) < to_number(b),
+        "<=" => to_number(a) <= to_number(b),
+        ">" => to_number(a) > to_number(b),
+        _ => to_number(a) >= to_number(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use super::*;
+    use crate::node::DomBuilder;
+    use crate::QuickXmlReader;
+
+    fn parse(text: &str) -> NsElement {
+        DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_str(text))).unwrap()
+    }
+
+    fn names<'a>(result: &XPathResult<'a>) -> Vec<&'a str> {
+        match *result {
+            XPathResult::NodeSet(ref nodes) => nodes.iter().map(|n| match *n {
+                XPathNode::Element(e) => e.name.as_str(),
+                XPathNode::Attribute { value, .. } => value,
+                XPathNode::Text { text, .. } => text,
+                XPathNode::Document(_) => "/",
+            }).collect(),
+            ref r => panic!("not a node set: {:?}", r),
+        }
+    }
+
+    const CATALOG: &str = concat!(
+        "<catalog>",
+        "<book id=\"1\"><title>First</title><price>10</price></book>",
+        "<book id=\"2\"><title>Second</title><price>25</price></book>",
+        "<shelf><book id=\"3\"><title>Third</title><price>15</price></book></shelf>",
+        "</catalog>",
+    );
+
+    #[test]
+    fn selects_paths() {
+        let root = parse(CATALOG);
+        assert_eq!(names(&evaluate_xpath(&root, "/catalog/book/title/text()").unwrap()), ["First", "Second"]);
+        assert_eq!(names(&evaluate_xpath(&root, "//book/@id").unwrap()), ["1", "2", "3"]);
+        assert_eq!(names(&evaluate_xpath(&root, "*").unwrap()), ["book", "book", "shelf"]);
+        assert_eq!(names(&evaluate_xpath(&root, "/").unwrap()), ["/"]);
+    }
+
+    #[test]
+    fn filters_by_predicates() {
+        let root = parse(CATALOG);
+        assert_eq!(names(&evaluate_xpath(&root, "book[2]/title/text()").unwrap()), ["Second"]);
+        assert_eq!(names(&evaluate_xpath(&root, "//book[@id='3']/title/text()").unwrap()), ["Third"]);
+        assert_eq!(names(&evaluate_xpath(&root, "//book[price > 12]/@id").unwrap()), ["2", "3"]);
+        assert_eq!(names(&evaluate_xpath(&root, "book[last()]/@id").unwrap()), ["2"]);
+    }
+
+    #[test]
+    fn evaluates_functions() {
+        let root = parse(CATALOG);
+        assert_eq!(evaluate_xpath(&root, "count(//book)").unwrap(), XPathResult::Number(3.0));
+        assert_eq!(evaluate_xpath(&root, "string(//title)").unwrap(), XPathResult::String("First".to_string()));
+        assert_eq!(evaluate_xpath(&root, "contains(//book[@id='3']/title, 'hi')").unwrap(), XPathResult::Boolean(true));
+        // Positions are relative to the parent, so no element is the third `book` child.
+        assert_eq!(evaluate_xpath(&root, "boolean(//book[3])").unwrap(), XPathResult::Boolean(false));
+        assert_eq!(evaluate_xpath(&root, "not(//missing)").unwrap(), XPathResult::Boolean(true));
+    }
+
+    #[test]
+    fn identifies_empty_texts_by_position() {
+        let root = parse("<a><![CDATA[]]><b/><![CDATA[]]></a>");
+        assert_eq!(evaluate_xpath(&root, "count(/a/node())").unwrap(), XPathResult::Number(3.0));
+        assert_eq!(evaluate_xpath(&root, "count(//text())").unwrap(), XPathResult::Number(2.0));
+        assert_eq!(evaluate_xpath(&root, "count(//text() | /a/node())").unwrap(), XPathResult::Number(3.0));
+    }
+
+    #[test]
+    fn rejects_unsupported_expressions() {
+        let root = parse(CATALOG);
+        for expr in &["book/..", "p:book", "book[", "1 + 2"] {
+            assert!(evaluate_xpath(&root, expr).is_err(), "`{}` is accepted", expr);
+        }
+    }
+}
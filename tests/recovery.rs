@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use xml5ever::driver::{parse_document, XmlParseOpts};
+use xml5ever::tendril::TendrilSink;
+
+use choose_your_xml::{dump_events, DumpOptions};
+
+// The same sample as in the `xml5ever_rcdom` example.
+const BROKEN: &str = "data/broken.xml";
+
+fn element_names(node: &Handle, names: &mut Vec<String>) {
+    if let NodeData::Element { ref name, .. } = node.data {
+        names.push(name.local.to_string());
+    }
+
+    for child in node.children.borrow().iter() {
+        element_names(child, names);
+    }
+}
+
+#[test]
+fn quick_xml_rejects_broken_sample() {
+    let file = BufReader::new(File::open(BROKEN).unwrap());
+    assert!(dump_events(file, &mut Vec::new(), &DumpOptions::default()).is_err());
+}
+
+#[test]
+fn xml5ever_recovers_broken_sample() {
+    let dom = parse_document(RcDom::default(), XmlParseOpts::default())
+        .from_utf8()
+        .read_from(&mut BufReader::new(File::open(BROKEN).unwrap()))
+        .unwrap();
+
+    let mut names = Vec::new();
+    element_names(&dom.document, &mut names);
+    assert_eq!(names, ["root", "item", "item", "item"]);
+    assert!(!dom.errors.is_empty());
+}
@@ -0,0 +1,92 @@
+use std::fs;
+
+use quick_xml::Reader;
+
+use choose_your_xml::node::{DomBuilder, NsElement};
+use choose_your_xml::{evaluate_xpath, QuickXmlReader, XPathNode, XPathResult};
+
+// The examples of the abbreviated syntax from the section 2.5 of the XPath 1.0 specification,
+// adapted to the sample, with `doc` as the context node.
+const SAMPLE: &str = "data/xpath.xml";
+
+fn parse() -> NsElement {
+    let text = fs::read_to_string(SAMPLE).unwrap();
+    DomBuilder::new().build(&mut QuickXmlReader::new(Reader::from_str(&text))).unwrap()
+}
+
+// Elements are identified by their `id` or, without one, by their names.
+fn nodes(root: &NsElement, expr: &str) -> Vec<String> {
+    match evaluate_xpath(root, expr).unwrap() {
+        XPathResult::NodeSet(nodes) => nodes.into_iter()
+            .map(|n| match n {
+                XPathNode::Document(_) => "/".to_string(),
+                XPathNode::Element(e) => e.attributes.get("id").unwrap_or(&e.name).to_string(),
+                XPathNode::Attribute { value, .. } => value.to_string(),
+                XPathNode::Text { text, .. } => text.to_string(),
+            })
+            .collect(),
+        r => panic!("`{}` is not a node-set: {:?}", expr, r),
+    }
+}
+
+#[test]
+fn abbreviated_location_paths() {
+    let root = parse();
+    let cases: &[(&str, &[&str])] = &[
+        ("para", &["p1", "p2", "p3"]),
+        ("*", &["p1", "p2", "p3", "c1", "c2", "e1", "e2"]),
+        ("para[2]/text()", &["Two"]),
+        ("para[1]/@id", &["p1"]),
+        ("para[1]/@*", &["p1", "warning"]),
+        ("para[1]", &["p1"]),
+        ("para[last()]", &["p3"]),
+        ("*/para", &["p4", "p6", "p7", "p8", "p9", "p10"]),
+        ("/doc/chapter[2]/para[3]", &["p8"]),
+        ("chapter//para", &["p4", "p5", "p6", "p7", "p8", "p9", "p10"]),
+        ("//para", &["p1", "p2", "p3", "p4", "p5", "p6", "p7", "p8", "p9", "p10"]),
+        ("//olist/item/text()", &["i1", "i2"]),
+        (".", &["doc"]),
+        (".//para", &["p1", "p2", "p3", "p4", "p5", "p6", "p7", "p8", "p9", "p10"]),
+        ("para[@type=\"warning\"]", &["p1", "p3"]),
+        ("chapter[2]/para[@type=\"warning\"][3]", &["p9"]),
+        ("chapter[2]/para[3][@type=\"warning\"]", &[]),
+        ("chapter[title=\"Introduction\"]", &["c1"]),
+        ("chapter[title]", &["c1"]),
+        ("employee[@secretary and @assistant]", &["e1"]),
+    ];
+
+    for &(expr, expected) in cases {
+        assert_eq!(nodes(&root, expr), expected, "{}", expr);
+    }
+}
+
+#[test]
+fn results_are_in_document_order() {
+    let root = parse();
+    assert_eq!(nodes(&root, "//section | //title"), ["title", "s1", "s2"]);
+    assert_eq!(nodes(&root, "//para[position() = last()]"), ["p3", "p4", "p5", "p10"]);
+    assert_eq!(nodes(&root, "chapter/section | chapter | para[2]"), ["p2", "c1", "s1", "s2", "c2"]);
+}
+
+#[test]
+fn core_functions() {
+    let root = parse();
+    let cases = [
+        ("count(//para[@type='warning'])", XPathResult::Number(7.0)),
+        ("count(/doc/*)", XPathResult::Number(7.0)),
+        ("string(chapter[1]/section[1])", XPathResult::String("Five".to_string())),
+        ("normalize-space(chapter[1]/title)", XPathResult::String("Introduction".to_string())),
+        ("local-name(*[last()])", XPathResult::String("employee".to_string())),
+        ("concat(para[1], '-', para[2])", XPathResult::String("One-Two".to_string())),
+        ("string-length(para[3])", XPathResult::Number(5.0)),
+        ("number(//olist/item[1]) = number('x')", XPathResult::Boolean(false)),
+        ("//para = 'Nine'", XPathResult::Boolean(true)),
+        ("//para != 'Nine'", XPathResult::Boolean(true)),
+        ("starts-with(//title, 'Intro')", XPathResult::Boolean(true)),
+        ("boolean(//missing) or not(employee[2]/@assistant)", XPathResult::Boolean(true)),
+    ];
+
+    for (expr, expected) in &cases {
+        assert_eq!(&evaluate_xpath(&root, expr).unwrap(), expected, "{}", expr);
+    }
+}